@@ -1,28 +1,690 @@
 //! Deals entirely with schema analysis for the purpose of creating output structs + members
-use crate::{Container, MapType, Member, Output};
+use crate::{
+    output::{sanitize_ident, IdentKind},
+    Constraints, Container, DurationType, EmbeddedType, MapType, Member, Naming, Output, Warning,
+    WarningKind,
+};
 use anyhow::{bail, Result};
-use heck::ToUpperCamelCase;
+use heck::ToSnakeCase;
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
     JSONSchemaProps, JSONSchemaPropsOrArray, JSONSchemaPropsOrBool, JSON,
 };
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
 
 const IGNORED_KEYS: [&str; 3] = ["metadata", "apiVersion", "kind"];
+/// Nesting limit guarding against schemas that recurse without a `$ref`
+const MAX_DEPTH: u8 = 64;
+/// Types the generated code names through its prelude or the std prelude, which structs must not shadow
+const RESERVED_NAMES: [&str; 24] = [
+    "BTreeMap",
+    "BTreeSet",
+    "Box",
+    "ByteString",
+    "Condition",
+    "CustomResource",
+    "DateTime",
+    "Deserialize",
+    "Duration",
+    "DynamicObject",
+    "HashMap",
+    "IndexMap",
+    "IntOrString",
+    "JsonSchema",
+    "NaiveDate",
+    "Option",
+    "RawExtension",
+    "Result",
+    "Serialize",
+    "String",
+    "TypedBuilder",
+    "Utc",
+    "Validate",
+    "Vec",
+];
 
 #[derive(Default)]
 pub struct Config {
     pub no_condition: bool,
     pub map: MapType,
     pub relaxed: bool,
+    /// Use unsigned integers for integers declaring a non-negative minimum
+    pub prefer_unsigned: bool,
+    /// Use chrono types for strings with date and date-time formats
+    pub chrono: bool,
+    /// Type used for strings with a duration format
+    pub duration: DurationType,
+    /// Type used for objects embedding a Kubernetes resource
+    pub embedded: EmbeddedType,
+    /// Use String rather than IntOrString for integer-or-string values
+    pub coerce_int_or_string: bool,
+    /// Name structs for list items and map values after the exact key, rather than its singular
+    pub no_singularize: bool,
+    /// Name structs with a schema title after the title alone, rather than prefixing the kind
+    pub no_title_prefix: bool,
+    /// Words kept uppercase in struct and enum names, such as `TLS` for `FooTLSConfig` rather than `FooTlsConfig`
+    pub acronyms: Vec<String>,
+    /// Strategy for naming structs and enums
+    pub naming: Naming,
+    /// Prefix of struct and enum names instead of the kind, which may be empty
+    pub struct_prefix: Option<String>,
+    /// Whether optional members with a schema default take it when missing, rather than being an Option
+    pub schema_defaults: bool,
+    /// Whether members record the validation constraints of their schema, such as a minimum or pattern
+    pub constraints: bool,
+    /// Represent lists with `x-kubernetes-list-type: map` as maps, when keyed by a single string field
+    pub list_map_as_map: bool,
+    /// Represent lists of unique scalars (`x-kubernetes-list-type: set` or `uniqueItems`) as BTreeSets
+    pub sets: bool,
+    /// Whether members whose type does not describe their schema faithfully record it, for derived schemas
+    pub schemas: bool,
+}
+
+/// State of a single analysis run, wrapping the [`Config`] it was started with
+#[derive(Default)]
+struct Context {
+    cfg: Config,
+    /// Warnings collected during analysis, handed back as part of the [`Output`]
+    warnings: RefCell<Vec<Warning>>,
+    /// Kind the analysis runs for, which kube names the root struct after
+    kind: String,
+    /// Prefix of all struct and enum names, the kind unless configured otherwise
+    prefix: String,
+    /// Enum names handed out so far, keyed by the schema path of the enum
+    enum_names: RefCell<BTreeMap<String, String>>,
+    /// Struct names handed out so far, keyed by the schema path of the struct
+    struct_names: RefCell<BTreeMap<String, String>>,
+    /// Definitions kept as references, keyed by their `$ref`
+    definitions: BTreeMap<String, Definition>,
+}
+
+/// An object schema from the root `definitions`, generated once as a struct shared by its references
+#[derive(Default)]
+struct Definition {
+    /// Name of the generated struct
+    name: String,
+    /// Schema path the definition is analyzed at
+    path: String,
+    schema: JSONSchemaProps,
+    /// Definitions reachable through references, including this one when it is recursive
+    reaches: BTreeSet<String>,
+    /// Whether the root schema references this definition, directly or through other definitions
+    used: bool,
+}
+
+impl std::ops::Deref for Context {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.cfg
+    }
+}
+
+impl Context {
+    /// Record a warning to be reported once analysis completes
+    fn warn(&self, kind: WarningKind, msg: String) {
+        debug!("warning: {}", msg);
+        self.warnings.borrow_mut().push(Warning::new(kind, msg));
+    }
+
+    /// Name of the enum generated for the schema at `path`
+    ///
+    /// The first request for a path decides the name, suffixing a counter to `name`
+    /// when another enum or struct already took it (e.g. for both `foo_bar` and `fooBar` members).
+    fn enum_name(&self, path: &str, name: String) -> String {
+        if let Some(existing) = self.enum_names.borrow().get(path) {
+            return existing.clone();
+        }
+        let unique = self.unique_name(self.destutter(name));
+        self.enum_names
+            .borrow_mut()
+            .insert(path.to_string(), unique.clone());
+        unique
+    }
+
+    /// Name of the struct generated for the object schema `value` at `path`
+    ///
+    /// The first request for a path decides the name, so that member types and the structs
+    /// generated for them agree. Nested objects with a `title` that is an identifier (e.g. the
+    /// `EmbeddedObjectMeta` of controller-gen) are named after it instead of `name`. Names that
+    /// another struct or enum already took get a counter suffixed, as property keys can concatenate
+    /// to the same name at different paths (e.g. `fooBar.baz` and `foo.barBaz`).
+    /// Repeated words, such as a `gateway` key under the `Gateway` kind, are dropped when possible.
+    ///
+    /// Schemas that do not generate a struct, such as maps, keep `name` without taking it.
+    fn struct_name(&self, path: &str, name: String, value: &JSONSchemaProps) -> String {
+        if !is_struct(value) {
+            return name;
+        }
+        if let Some(existing) = self.struct_names.borrow().get(path) {
+            return existing.clone();
+        }
+        // spec and status keep their names, which the kube attributes rely on
+        let top_level = path.rfind('.') == Some(0) && !path.contains(['[', '*']);
+        let title = value.title.as_deref().filter(|t| is_identifier(t));
+        let name = match title {
+            Some(title) if value.properties.is_some() && !top_level && self.naming == Naming::Short => {
+                let title = type_name(title, self);
+                if self.no_title_prefix {
+                    title
+                } else {
+                    format!("{}{}", self.prefix, title)
+                }
+            }
+            _ => name,
+        };
+        let name = self.destutter(name);
+        let unique = self.unique_name(name.clone());
+        if unique != name {
+            debug!("struct name {} at {} is taken - using {}", name, path, unique);
+        }
+        self.struct_names
+            .borrow_mut()
+            .insert(path.to_string(), unique.clone());
+        unique
+    }
+
+    /// `name`, or `name` with a counter suffixed when an enum or struct already took it
+    ///
+    /// Names are compared as they are emitted, where names below the spec drop their `Spec` infix
+    /// (so that e.g. `FooSpecStatus` does not clash with `FooStatus` once emitted).
+    fn unique_name(&self, name: String) -> String {
+        let mut unique = name.clone();
+        let mut counter = 2;
+        while self.is_taken(&unique) {
+            unique = format!("{}{}", name, counter);
+            counter += 1;
+        }
+        unique
+    }
+
+    /// Whether the root struct, a prelude type, or an enum or struct handed out so far, is emitted as `name`
+    ///
+    /// Names below the spec drop their `Spec` infix once emitted
+    /// (so that e.g. `FooSpecStatus` clashes with `FooStatus`).
+    fn is_taken(&self, name: &str) -> bool {
+        let name = self.emitted(name);
+        let (enums, structs) = (self.enum_names.borrow(), self.struct_names.borrow());
+        name == self.kind
+            || RESERVED_NAMES.contains(&name.as_str())
+            || enums
+                .values()
+                .chain(structs.values())
+                .any(|t| self.emitted(t) == name)
+    }
+
+    /// A type name as it is emitted, without the `Spec` infix unless it names the spec itself
+    ///
+    /// Full path names keep the infix.
+    fn emitted(&self, name: &str) -> String {
+        let spec = format!("{}Spec", self.prefix);
+        match name.strip_prefix(&spec) {
+            Some(rest) if rest.starts_with(|c: char| c.is_ascii_uppercase() || c.is_ascii_digit()) => {
+                match self.naming {
+                    Naming::Short => format!("{}{}", self.prefix, rest),
+                    Naming::FullPath => name.to_string(),
+                }
+            }
+            _ => name.to_string(),
+        }
+    }
+
+    /// `name` as emitted without repeated words (e.g. `GatewayListeners` for `GatewaySpecGatewayListeners`)
+    ///
+    /// Names that would clash with a name already handed out keep their repetition, as do full path names.
+    fn destutter(&self, name: String) -> String {
+        if self.naming == Naming::FullPath {
+            return name;
+        }
+        let emitted = self.emitted(&name);
+        let short = without_repeated_words(&emitted);
+        if short != emitted && !self.is_taken(&short) {
+            debug!("dropping repeated words from {} - using {}", name, short);
+            short
+        } else {
+            name
+        }
+    }
+
+    /// Type used for the value at `path` that is either an integer or a string
+    fn int_or_string(&self, path: &str) -> String {
+        if self.coerce_int_or_string {
+            self.warn(
+                WarningKind::Coerced,
+                format!("int-or-string at {} - using String", path),
+            );
+            "String".into()
+        } else {
+            "IntOrString".into()
+        }
+    }
+
+    /// Name of the struct generated for the definition a `$ref` schema points to
+    fn referenced_type(&self, value: &JSONSchemaProps) -> Option<String> {
+        let reference = value.ref_path.as_ref()?;
+        self.definitions.get(reference).map(|d| d.name.clone())
+    }
+
+    /// Whether a member at `path` referencing `value` makes its definition contain itself
+    ///
+    /// Such members need a `Box` to give the generated struct a finite size.
+    fn is_recursive_reference(&self, value: &JSONSchemaProps, path: &str) -> bool {
+        let Some(target) = value.ref_path.as_ref().and_then(|r| self.definitions.get(r)) else {
+            return false;
+        };
+        // the innermost definition containing the member, e.g. `#/definitions/Step` for `#/definitions/Step.next`
+        self.definitions
+            .iter()
+            .filter(|(_, d)| {
+                path.strip_prefix(d.path.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+            })
+            .max_by_key(|(_, d)| d.path.len())
+            .is_some_and(|(current, _)| target.reaches.contains(current))
+    }
 }
 
 /// Scan a schema for structs and members, and recurse to find all structs
 ///
-/// All found output structs will have its names prefixed by the kind it is for
+/// All found output structs will have its names prefixed by the kind it is for,
+/// or by the [`Config::struct_prefix`] when set.
 pub fn analyze(schema: JSONSchemaProps, kind: &str, cfg: Config) -> Result<Output> {
+    let prefix = cfg.struct_prefix.clone().unwrap_or_else(|| kind.to_string());
+    let (schema, definitions) = resolve_refs(&schema, &prefix, &cfg)?;
+    let cfg = Context {
+        cfg,
+        kind: kind.to_string(),
+        prefix: prefix.clone(),
+        definitions,
+        ..Context::default()
+    };
+    // definitions are named up front, structs at other paths must not take their names
+    for definition in cfg.definitions.values().filter(|d| d.used) {
+        cfg.struct_names
+            .borrow_mut()
+            .insert(definition.path.clone(), definition.name.clone());
+    }
+    let schema = flatten_all_of(&schema)?;
     let mut res = vec![];
-    analyze_(&schema, "", kind, 0, &mut res, &cfg)?;
-    Ok(Output(res))
+    analyze_(&schema, "", &prefix, "", 0, &mut res, &cfg)?;
+    for (reference, definition) in cfg.definitions.iter().filter(|(_, d)| d.used) {
+        debug!("Generating definition {} as {}", reference, definition.name);
+        analyze_(
+            &definition.schema,
+            "",
+            &definition.name,
+            &definition.path,
+            1,
+            &mut res,
+            &cfg,
+        )?;
+    }
+    Ok(Output {
+        containers: res,
+        warnings: cfg.warnings.into_inner(),
+    })
+}
+
+/// Substitute `$ref`s to the root `definitions` of a schema
+///
+/// References to objects with properties are kept, so that each of those definitions is generated
+/// once as a struct shared by all its references. Other definitions are inlined at the reference,
+/// with keywords next to the `$ref` (such as a description) taking precedence.
+fn resolve_refs(
+    schema: &JSONSchemaProps,
+    prefix: &str,
+    cfg: &Config,
+) -> Result<(JSONSchemaProps, BTreeMap<String, Definition>)> {
+    let mut root = serde_json::to_value(schema)?;
+    let mut definitions = lift_pointer_refs(&mut root);
+    if let Some(serde_json::Value::Object(defs)) = root.as_object_mut().and_then(|r| r.remove("definitions"))
+    {
+        for (name, def) in defs {
+            definitions.insert(format!("#/definitions/{}", name), def);
+        }
+    }
+    let shared = definitions
+        .iter()
+        .filter(|(_, def)| def["type"] == "object" && def.get("properties").is_some())
+        .map(|(reference, _)| reference.clone())
+        .collect::<BTreeSet<_>>();
+
+    inline_refs(&mut root, &definitions, &shared, &mut vec![], "")?;
+    let mut used = BTreeSet::new();
+    collect_refs(&root, &mut used);
+
+    let mut kept = BTreeMap::new();
+    let mut edges = BTreeMap::new();
+    for reference in &shared {
+        let mut def = definitions[reference].clone();
+        inline_refs(
+            &mut def,
+            &definitions,
+            &shared,
+            &mut vec![reference.clone()],
+            reference,
+        )?;
+        let mut refs = BTreeSet::new();
+        collect_refs(&def, &mut refs);
+        edges.insert(reference.clone(), refs);
+        let (path, name) = reference_location(reference, prefix, cfg);
+        let definition = Definition {
+            name,
+            path,
+            schema: flatten_all_of(&serde_json::from_value(def)?)?,
+            ..Definition::default()
+        };
+        kept.insert(reference.clone(), definition);
+    }
+    // transitive closure of the references between definitions
+    for (reference, definition) in kept.iter_mut() {
+        let mut todo = edges[reference].iter().collect::<Vec<_>>();
+        while let Some(next) = todo.pop() {
+            if definition.reaches.insert(next.clone()) {
+                todo.extend(&edges[next]);
+            }
+        }
+    }
+    let reachable = used
+        .iter()
+        .flat_map(|r| kept[r].reaches.iter().cloned())
+        .collect::<Vec<_>>();
+    for reference in used.into_iter().chain(reachable) {
+        if let Some(definition) = kept.get_mut(&reference) {
+            definition.used = true;
+        }
+    }
+    Ok((serde_json::from_value(root)?, kept))
+}
+
+/// Move the schemas that `$ref` JSON pointers such as `#/properties/spec` point to into definitions
+///
+/// Their original location is replaced by a reference as well, so that recursive schemas
+/// (e.g. steps that contain steps) generate a single struct. Arrays of objects are lifted
+/// together with their items, since only object definitions can be recursive.
+fn lift_pointer_refs(root: &mut serde_json::Value) -> BTreeMap<String, serde_json::Value> {
+    let mut refs = BTreeSet::new();
+    collect_refs(root, &mut refs);
+    for def in root
+        .get("definitions")
+        .and_then(|d| d.as_object())
+        .into_iter()
+        .flatten()
+    {
+        collect_refs(def.1, &mut refs);
+    }
+    let mut pointers = BTreeSet::new();
+    for reference in refs {
+        let is_definition = reference
+            .strip_prefix("#/definitions/")
+            .is_some_and(|name| !name.contains('/'));
+        let Some(target) = reference
+            .strip_prefix('#')
+            .filter(|p| p.starts_with('/') && !is_definition)
+            .and_then(|p| root.pointer(p))
+        else {
+            continue; // definitions are resolved separately, and anything else is reported when inlining
+        };
+        if target["type"] == "array" && target["items"].get("properties").is_some() {
+            pointers.insert(format!("{}/items", reference));
+        }
+        pointers.insert(reference);
+    }
+    // innermost first, so that the pointers to their ancestors remain valid
+    let mut pointers = pointers.into_iter().collect::<Vec<_>>();
+    pointers.sort_by_key(|p| std::cmp::Reverse(p.len()));
+    let mut lifted = BTreeMap::new();
+    for pointer in pointers {
+        let Some(target) = root.pointer_mut(&pointer[1..]) else {
+            continue;
+        };
+        let mut reference = serde_json::json!({ "$ref": pointer });
+        if let Some(description) = target.get("description") {
+            reference["description"] = description.clone();
+        }
+        lifted.insert(pointer, std::mem::replace(target, reference));
+    }
+    lifted
+}
+
+/// Schema path and struct name for the definition a `$ref` points to
+///
+/// Schemas lifted out of the root schema get the path and name they would have had in place.
+fn reference_location(reference: &str, prefix: &str, cfg: &Config) -> (String, String) {
+    let (mut path, mut name) = (String::new(), prefix.to_string());
+    // where the name of the last property starts, as items and values are named after it
+    let mut key_start = name.len();
+    let mut segments = reference
+        .trim_start_matches("#/")
+        .split('/')
+        .map(|s| s.replace("~1", "/").replace("~0", "~"));
+    while let Some(segment) = segments.next() {
+        match segment.as_str() {
+            "definitions" => {
+                let def = segments.next().unwrap_or_default();
+                path = format!("#/definitions/{}", def);
+                name = format!("{}{}", prefix, type_name(&def, cfg));
+                key_start = prefix.len();
+            }
+            "properties" => {
+                let key = segments.next().unwrap_or_default();
+                path.push_str(&format!(".{}", key));
+                key_start = name.len();
+                name.push_str(&type_name(&key, cfg));
+            }
+            "items" | "additionalProperties" => {
+                path.push_str(if segment == "items" { "[]" } else { ".*" });
+                let suffix = item_suffix(&name[key_start..], cfg);
+                name.replace_range(key_start.., &suffix);
+            }
+            keyword => {
+                let index = segments.next().unwrap_or_default();
+                path.push_str(&format!(".{}[{}]", keyword, index));
+            }
+        }
+    }
+    (path, name)
+}
+
+/// Inline references to the definitions that are not shared, checking that all references resolve
+///
+/// `inlining` holds the definitions currently being inlined, to reject references to themselves.
+fn inline_refs(
+    value: &mut serde_json::Value,
+    definitions: &BTreeMap<String, serde_json::Value>,
+    shared: &BTreeSet<String>,
+    inlining: &mut Vec<String>,
+    path: &str,
+) -> Result<()> {
+    let Some(obj) = value.as_object_mut() else {
+        return Ok(()); // boolean additionalProperties
+    };
+    if let Some(reference) = obj.get("$ref") {
+        let reference = reference.as_str().unwrap_or_default().to_string();
+        if !definitions.contains_key(&reference) {
+            bail!("unresolved $ref {} at {}", reference, path)
+        }
+        if !shared.contains(&reference) {
+            substitute_ref(obj, &reference, definitions, shared, inlining, path)?;
+        }
+        return Ok(());
+    }
+    if let Some(serde_json::Value::Object(props)) = obj.get_mut("properties") {
+        for (key, prop) in props.iter_mut() {
+            inline_refs(prop, definitions, shared, inlining, &format!("{}.{}", path, key))?;
+        }
+    }
+    if let Some(items) = obj.get_mut("items") {
+        inline_refs(items, definitions, shared, inlining, &format!("{}[]", path))?;
+    }
+    if let Some(additional) = obj.get_mut("additionalProperties") {
+        inline_refs(additional, definitions, shared, inlining, &format!("{}.*", path))?;
+    }
+    for keyword in ["oneOf", "anyOf", "allOf"] {
+        if let Some(serde_json::Value::Array(alternatives)) = obj.get_mut(keyword) {
+            for (i, alt) in alternatives.iter_mut().enumerate() {
+                let alt_path = format!("{}.{}[{}]", path, keyword, i);
+                let reference = alt.get("$ref").and_then(|r| r.as_str()).map(String::from);
+                if let (Some(branch), Some(reference)) = (alt.as_object_mut(), reference) {
+                    // allOf branches are merged into this schema, so even shared definitions are inlined
+                    if keyword == "allOf" && shared.contains(&reference) {
+                        substitute_ref(branch, &reference, definitions, shared, inlining, &alt_path)?;
+                    }
+                }
+                inline_refs(alt, definitions, shared, inlining, &alt_path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replace the `$ref` of a schema with the (resolved) keywords of the definition
+fn substitute_ref(
+    obj: &mut serde_json::Map<String, serde_json::Value>,
+    reference: &str,
+    definitions: &BTreeMap<String, serde_json::Value>,
+    shared: &BTreeSet<String>,
+    inlining: &mut Vec<String>,
+    path: &str,
+) -> Result<()> {
+    if inlining.iter().any(|r| r == reference) {
+        bail!("recursive $ref {} at {} cannot be inlined", reference, path)
+    }
+    let mut def = definitions[reference].clone();
+    inlining.push(reference.to_string());
+    inline_refs(&mut def, definitions, shared, inlining, reference)?;
+    inlining.pop();
+    obj.remove("$ref");
+    if let serde_json::Value::Object(def) = def {
+        for (keyword, v) in def {
+            obj.entry(keyword).or_insert(v);
+        }
+    }
+    Ok(())
+}
+
+/// Collect the references left in a schema after inlining
+fn collect_refs(value: &serde_json::Value, refs: &mut BTreeSet<String>) {
+    let Some(obj) = value.as_object() else {
+        return;
+    };
+    if let Some(reference) = obj.get("$ref").and_then(|r| r.as_str()) {
+        refs.insert(reference.to_string());
+    }
+    for prop in obj
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .into_iter()
+        .flatten()
+    {
+        collect_refs(prop.1, refs);
+    }
+    for nested in ["items", "additionalProperties"]
+        .iter()
+        .filter_map(|k| obj.get(*k))
+    {
+        collect_refs(nested, refs);
+    }
+    for keyword in ["oneOf", "anyOf", "allOf"] {
+        for alt in obj.get(keyword).and_then(|a| a.as_array()).into_iter().flatten() {
+            collect_refs(alt, refs);
+        }
+    }
+}
+
+/// Merge the branches of every allOf in the schema into the schema declaring it
+///
+/// Properties and required lists are combined, nested schemas are merged recursively,
+/// and for other keywords the first declaration wins. Only conflicting types are an error.
+fn flatten_all_of(schema: &JSONSchemaProps) -> Result<JSONSchemaProps> {
+    let mut value = serde_json::to_value(schema)?;
+    flatten_value(&mut value, "")?;
+    Ok(serde_json::from_value(value)?)
+}
+
+fn flatten_value(value: &mut serde_json::Value, path: &str) -> Result<()> {
+    let Some(obj) = value.as_object_mut() else {
+        return Ok(()); // boolean additionalProperties, or arrays of items
+    };
+    if let Some(serde_json::Value::Object(props)) = obj.get_mut("properties") {
+        for (key, prop) in props.iter_mut() {
+            flatten_value(prop, &format!("{}.{}", path, key))?;
+        }
+    }
+    if let Some(items) = obj.get_mut("items") {
+        flatten_value(items, &format!("{}[]", path))?;
+    }
+    if let Some(additional) = obj.get_mut("additionalProperties") {
+        flatten_value(additional, &format!("{}.*", path))?;
+    }
+    for keyword in ["oneOf", "anyOf"] {
+        if let Some(serde_json::Value::Array(alternatives)) = obj.get_mut(keyword) {
+            for (i, alt) in alternatives.iter_mut().enumerate() {
+                flatten_value(alt, &format!("{}.{}[{}]", path, keyword, i))?;
+            }
+        }
+    }
+    if let Some(serde_json::Value::Array(branches)) = obj.remove("allOf") {
+        for mut branch in branches {
+            flatten_value(&mut branch, path)?;
+            merge_value(value, branch, path)?;
+        }
+    }
+    Ok(())
+}
+
+fn merge_value(target: &mut serde_json::Value, branch: serde_json::Value, path: &str) -> Result<()> {
+    let (serde_json::Value::Object(target), serde_json::Value::Object(branch)) = (target, branch) else {
+        return Ok(());
+    };
+    for (keyword, incoming) in branch {
+        let Some(existing) = target.get_mut(&keyword) else {
+            target.insert(keyword, incoming);
+            continue;
+        };
+        match keyword.as_str() {
+            "type" if *existing != incoming => {
+                bail!(
+                    "conflicting types {} and {} in allOf at {}",
+                    existing,
+                    incoming,
+                    path
+                )
+            }
+            "required" => {
+                if let (serde_json::Value::Array(required), serde_json::Value::Array(more)) =
+                    (existing, incoming)
+                {
+                    for r in more {
+                        if !required.contains(&r) {
+                            required.push(r);
+                        }
+                    }
+                }
+            }
+            "properties" => {
+                if let (serde_json::Value::Object(props), serde_json::Value::Object(more)) =
+                    (existing, incoming)
+                {
+                    for (key, prop) in more {
+                        let prop_path = format!("{}.{}", path, key);
+                        match props.get_mut(&key) {
+                            Some(current) => merge_value(current, prop, &prop_path)?,
+                            None => {
+                                props.insert(key, prop);
+                            }
+                        }
+                    }
+                }
+            }
+            "items" => merge_value(existing, incoming, &format!("{}[]", path))?,
+            "additionalProperties" => merge_value(existing, incoming, &format!("{}.*", path))?,
+            _ => {} // first declaration wins
+        }
+    }
+    Ok(())
 }
 
 /// Scan a schema for structs and members, and recurse to find all structs
@@ -30,18 +692,37 @@ pub fn analyze(schema: JSONSchemaProps, kind: &str, cfg: Config) -> Result<Outpu
 /// schema: root schema / sub schema
 /// current: current key name (or empty string for first call) - must capitalize first letter
 /// stack: stacked concat of kind + current_{n-1} + ... + current (used to create dedup names/types)
+/// path: schema path from the root to the current schema (e.g. `.spec.containers[]`) used in errors
 /// level: recursion level (start at 0)
 /// results: multable list of generated structs (not deduplicated)
 fn analyze_(
     schema: &JSONSchemaProps,
     current: &str,
     stack: &str,
+    path: &str,
     level: u8,
     results: &mut Vec<Container>,
-    cfg: &Config,
+    cfg: &Context,
 ) -> Result<()> {
+    if level > MAX_DEPTH {
+        bail!(
+            "schema at {} is nested more than {} levels deep - recursive schemas need a $ref",
+            path,
+            MAX_DEPTH
+        )
+    }
     let props = schema.properties.clone().unwrap_or_default();
     let mut array_recurse_level: HashMap<String, u8> = Default::default();
+    // structs for map values are named after a single value
+    let mut map_value_name = match stack.strip_suffix(current) {
+        Some(parent) => format!("{}{}", parent, item_suffix(current, cfg)),
+        None => stack.to_string(),
+    };
+    if let Some(JSONSchemaPropsOrBool::Schema(s)) = &schema.additional_properties {
+        if s.properties.is_some() {
+            map_value_name = cfg.struct_name(&format!("{}.*", path), map_value_name, s);
+        }
+    }
 
     // create a Container if we have a container type:
     //trace!("analyze_ with {} + {}", current, stack);
@@ -51,24 +732,55 @@ fn analyze_(
         if let Some(JSONSchemaPropsOrBool::Schema(s)) = schema.additional_properties.as_ref() {
             let dict_type = s.type_.clone().unwrap_or_default();
             // object with additionalProperties == map
-            if let Some(extra_props) = &s.properties {
+            if is_embedded_resource(s) {
+                return Ok(()); // map of whole objects, nothing to generate
+            } else if let Some(extra_props) = &s.properties {
                 // map values is an object with properties
                 debug!("Generating map struct for {} (under {})", current, stack);
-                let c = extract_container(extra_props, stack, &mut array_recurse_level, level, schema, cfg)?;
+                let values_path = format!("{}.*", path);
+                let mut c = extract_container(
+                    extra_props,
+                    &map_value_name,
+                    &values_path,
+                    &mut array_recurse_level,
+                    level,
+                    s,
+                    cfg,
+                )?;
+                if keeps_unknown_fields(s) {
+                    c.members
+                        .push(unknown_fields_member(extra_props, &values_path, cfg));
+                }
                 results.push(c);
+            } else if let Some(JSONSchemaPropsOrBool::Schema(_)) = &s.additional_properties {
+                // map of maps, the innermost values may need a struct
+                let values_path = format!("{}.*", path);
+                return analyze_(s, current, stack, &values_path, level, results, cfg);
             } else if !dict_type.is_empty() {
-                warn!("not generating type {} - using {} map", current, dict_type);
+                cfg.warn(
+                    WarningKind::Notice,
+                    format!(
+                        "not generating type {} at {} - using {} map",
+                        current, path, dict_type
+                    ),
+                );
                 return Ok(()); // no members here - it'll be inlined
             }
         } else {
             // else, regular properties only
             debug!("Generating struct for {} (under {})", current, stack);
             // initial analysis of properties (we do not recurse here, we need to find members first)
-            if props.is_empty() && schema.x_kubernetes_preserve_unknown_fields.unwrap_or(false) {
-                warn!("not generating type {} - using map", current);
+            if props.is_empty() && keeps_unknown_fields(schema) {
+                cfg.warn(
+                    WarningKind::Notice,
+                    format!("not generating type {} at {} - using map", current, path),
+                );
                 return Ok(());
             }
-            let c = extract_container(&props, stack, &mut array_recurse_level, level, schema, cfg)?;
+            let mut c = extract_container(&props, stack, path, &mut array_recurse_level, level, schema, cfg)?;
+            if keeps_unknown_fields(schema) {
+                c.members.push(unknown_fields_member(&props, path, cfg));
+            }
             results.push(c);
         }
     }
@@ -82,10 +794,19 @@ fn analyze_(
     // again; additionalProperties XOR properties
     let extras = if let Some(JSONSchemaPropsOrBool::Schema(s)) = schema.additional_properties.as_ref() {
         let extra_props = s.properties.clone().unwrap_or_default();
-        find_containers(&extra_props, stack, &mut array_recurse_level, level, schema, cfg)?
+        let path = format!("{}.*", path);
+        find_containers(
+            &extra_props,
+            &map_value_name,
+            &path,
+            &mut array_recurse_level,
+            level,
+            schema,
+            cfg,
+        )?
     } else {
         // regular properties only
-        find_containers(&props, stack, &mut array_recurse_level, level, schema, cfg)?
+        find_containers(&props, stack, path, &mut array_recurse_level, level, schema, cfg)?
     };
     results.extend(extras);
 
@@ -100,10 +821,11 @@ fn analyze_(
 fn find_containers(
     props: &BTreeMap<String, JSONSchemaProps>,
     stack: &str,
+    path: &str,
     array_recurse_level: &mut HashMap<String, u8>,
     level: u8,
     schema: &JSONSchemaProps,
-    cfg: &Config,
+    cfg: &Context,
 ) -> Result<Vec<Container>> {
     //trace!("finding containers in: {}", serde_yaml::to_string(&props)?);
     let mut results = vec![];
@@ -112,8 +834,32 @@ fn find_containers(
             debug!("not recursing into ignored {}", key); // handled elsewhere
             continue;
         }
-        let next_key = key.to_upper_camel_case();
+        let next_key = type_name(key, cfg);
         let next_stack = format!("{}{}", stack, next_key);
+        let next_path = format!("{}.{}", path, key);
+        if value.ref_path.is_some() {
+            debug!("..not recursing into reference {}", key); // generated once from its definition
+            continue;
+        }
+        if is_embedded_resource(value) {
+            debug!("..not recursing into embedded resource {}", key);
+            continue;
+        }
+        if let Some(alternatives) = union_alternatives(value) {
+            if indistinguishable_alternatives(&alternatives).is_none() {
+                let name = cfg.enum_name(&next_path, next_stack);
+                analyze_union(
+                    &alternatives,
+                    value,
+                    &name,
+                    &next_path,
+                    level + 1,
+                    &mut results,
+                    cfg,
+                )?;
+            }
+            continue;
+        }
         let value_type = value.type_.clone().unwrap_or_default();
         match value_type.as_ref() {
             "object" => {
@@ -122,10 +868,26 @@ fn find_containers(
                 if let Some(JSONSchemaPropsOrBool::Schema(s)) = &value.additional_properties {
                     let dict_type = s.type_.clone().unwrap_or_default();
                     if dict_type == "array" {
-                        // unpack the inner object from the array wrap
-                        if let Some(JSONSchemaPropsOrArray::Schema(items)) = &s.as_ref().items {
+                        // unpack the inner object from the (possibly nested) array wrap
+                        let mut items_path = format!("{}.*", next_path);
+                        let mut inner = s.as_ref();
+                        while let Some(JSONSchemaPropsOrArray::Schema(items)) = &inner.items {
+                            inner = items;
+                            items_path.push_str("[]");
+                        }
+                        if !std::ptr::eq(inner, s.as_ref()) {
                             debug!("..recursing into object member {}", key);
-                            analyze_(items, &next_key, &next_stack, level + 1, &mut results, cfg)?;
+                            let item_key = item_suffix(key, cfg);
+                            let name = format!("{}{}", stack, item_key);
+                            analyze_(
+                                inner,
+                                &item_key,
+                                &cfg.struct_name(&items_path, name, inner),
+                                &items_path,
+                                level + 1,
+                                &mut results,
+                                cfg,
+                            )?;
                             handled_inner = true;
                         }
                     }
@@ -139,12 +901,21 @@ fn find_containers(
                 }
                 if !handled_inner {
                     // normal object recurse
-                    analyze_(value, &next_key, &next_stack, level + 1, &mut results, cfg)?;
+                    analyze_(
+                        value,
+                        &next_key,
+                        &cfg.struct_name(&next_path, next_stack, value),
+                        &next_path,
+                        level + 1,
+                        &mut results,
+                        cfg,
+                    )?;
                 }
             }
             "array" => {
                 if let Some(recurse) = array_recurse_level.get(key).cloned() {
                     let mut inner = value.clone();
+                    let mut inner_path = next_path.clone();
                     for _i in 0..recurse {
                         debug!("..recursing into props for {}", key);
                         if let Some(sub) = inner.items {
@@ -152,18 +923,42 @@ fn find_containers(
                                 JSONSchemaPropsOrArray::Schema(s) => {
                                     //info!("got inner: {}", serde_json::to_string_pretty(&s)?);
                                     inner = *s.clone();
+                                    inner_path.push_str("[]");
                                 }
-                                _ => bail!("only handling single type in arrays"),
+                                _ => bail!("only handling single type in arrays at {}", inner_path),
                             }
                         } else {
-                            bail!("could not recurse into vec");
+                            bail!("could not recurse into vec at {}", inner_path);
+                        }
+                    }
+                    let map_key = list_map_key(value, cfg);
+                    if let Some(key) = map_key {
+                        // the key of the map is pulled out of the items
+                        if let Some(props) = inner.properties.as_mut() {
+                            props.remove(key);
+                        }
+                        if let Some(required) = inner.required.as_mut() {
+                            required.retain(|r| r != key);
                         }
                     }
-                    analyze_(&inner, &next_key, &next_stack, level + 1, &mut results, cfg)?;
+                    let item_key = item_suffix(key, cfg);
+                    let name = cfg.struct_name(&inner_path, format!("{}{}", stack, item_key), &inner);
+                    analyze_(
+                        &inner,
+                        &item_key,
+                        &name,
+                        &inner_path,
+                        level + 1,
+                        &mut results,
+                        cfg,
+                    )?;
+                    if let Some(c) = results.iter_mut().find(|c| c.name == name && map_key.is_some()) {
+                        c.map_key = map_key.map(String::from);
+                    }
                 }
             }
             "" => {
-                if value.x_kubernetes_int_or_string.is_some() {
+                if is_int_or_string(value) {
                     debug!("..not recursing into IntOrString {}", key)
                 } else {
                     debug!("..not recursing into unknown empty type {}", key)
@@ -174,7 +969,10 @@ fn find_containers(
                     // plain enums do not need to recurse, can collect it here
                     // ....although this makes it impossible for us to handle enums at the top level
                     // TODO: move this to the top level
-                    let new_result = analyze_enum_properties(en, &next_stack, level, schema)?;
+                    let name = cfg.enum_name(&next_path, next_stack);
+                    let default = value.default.as_ref();
+                    let new_result =
+                        analyze_enum_properties(en, default, &name, &next_path, level, schema, cfg)?;
                     results.push(new_result);
                 } else {
                     debug!("..not recursing into {} ('{}' is not a container)", key, x)
@@ -188,24 +986,36 @@ fn find_containers(
 // helper to figure out what output enums and embedded members are contained in the current object schema
 fn analyze_enum_properties(
     items: &Vec<JSON>,
+    default: Option<&JSON>,
     stack: &str,
+    path: &str,
     level: u8,
     schema: &JSONSchemaProps,
+    cfg: &Context,
 ) -> Result<Container, anyhow::Error> {
     let mut members = vec![];
     debug!("analyzing enum {}", serde_json::to_string(&schema).unwrap());
     for en in items {
         debug!("got enum {:?}", en);
         // TODO: do we need to verify enum elements? only in oneOf only right?
-        let name = match &en.0 {
-            serde_json::Value::String(name) => name.to_string(),
+        let (name, discriminant) = match &en.0 {
+            serde_json::Value::String(name) => (name.to_string(), None),
             serde_json::Value::Number(val) => {
-                if !val.is_u64() {
-                    bail!("enum member cannot have signed/floating discriminants");
-                }
-                val.to_string()
+                let Some(val) = val.as_i64() else {
+                    bail!(
+                        "enum member cannot have floating or out of range discriminants at {}",
+                        path
+                    );
+                };
+                // integers are not identifiers, so name variants after their value
+                let name = if val < 0 {
+                    format!("ValueMinus{}", val.unsigned_abs())
+                } else {
+                    format!("Value{}", val)
+                };
+                (name, Some(val))
             }
-            _ => bail!("not handling non-string/int enum outside oneOf block"),
+            _ => bail!("not handling non-string/int enum outside oneOf block at {}", path),
         };
         let rust_type = "".to_string();
         // Create member and wrap types correctly
@@ -217,8 +1027,25 @@ fn analyze_enum_properties(
             serde_annot: vec![],
             extra_annot: vec![],
             docs: member_doc,
+            discriminant,
+            default: None,
+            constraints: None,
+            schema: None,
         })
     }
+    if let Some(default) = default {
+        // the variant matching the schema default becomes the #[default] one
+        match items.iter().position(|en| en.0 == default.0) {
+            Some(i) => members[i].extra_annot.push("#[default]".to_string()),
+            None => cfg.warn(
+                WarningKind::Notice,
+                format!(
+                    "default {} is not an enum value at {} - not deriving Default",
+                    default.0, path
+                ),
+            ),
+        }
+    }
     Ok(Container {
         name: stack.to_string(),
         members,
@@ -233,21 +1060,60 @@ fn analyze_enum_properties(
 fn extract_container(
     props: &BTreeMap<String, JSONSchemaProps>,
     stack: &str,
+    path: &str,
     array_recurse_level: &mut HashMap<String, u8>,
     level: u8,
     schema: &JSONSchemaProps,
-    cfg: &Config,
+    cfg: &Context,
 ) -> Result<Container, anyhow::Error> {
     let mut members = vec![];
     //debug!("analyzing object {}", serde_json::to_string(&schema).unwrap());
     let reqs = schema.required.clone().unwrap_or_default();
     for (key, value) in props {
+        let member_path = format!("{}.{}", path, key);
         let value_type = value.type_.clone().unwrap_or_default();
+        // serde helper module for types that need custom (de)serialization
+        let mut serde_with = None;
+        let union = union_alternatives(value);
+        let mut required = reqs.contains(key);
         let rust_type = match value_type.as_ref() {
+            _ if value.ref_path.is_some() => {
+                let name = cfg.referenced_type(value).unwrap_or_default();
+                if cfg.is_recursive_reference(value, &member_path) {
+                    if required {
+                        // a value would have to contain itself forever
+                        cfg.warn(
+                            WarningKind::Notice,
+                            format!("required recursive member at {} - using Option", member_path),
+                        );
+                        required = false;
+                    }
+                    format!("Box<{}>", name)
+                } else {
+                    name
+                }
+            }
+            _ if is_embedded_resource(value) => cfg.embedded.name().to_string(),
+            _ if union.is_some() => {
+                let alternatives = union.unwrap_or_default();
+                if let Some((first, second)) = indistinguishable_alternatives(&alternatives) {
+                    cfg.warn(
+                        WarningKind::Untyped,
+                        format!(
+                            "indistinguishable oneOf alternatives {} and {} at {} - using serde_json::Value",
+                            first, second, member_path
+                        ),
+                    );
+                    "serde_json::Value".to_string()
+                } else {
+                    cfg.enum_name(&member_path, format!("{}{}", stack, type_name(key, cfg)))
+                }
+            }
             "object" => {
                 let mut dict_key = None;
                 if let Some(additional) = &value.additional_properties {
-                    dict_key = resolve_additional_properties(additional, stack, key, value)?;
+                    dict_key =
+                        resolve_additional_properties(additional, stack, key, &member_path, value, cfg)?;
                 } else if value.properties.is_none()
                     && value.x_kubernetes_preserve_unknown_fields.unwrap_or(false)
                 {
@@ -256,58 +1122,159 @@ fn extract_container(
                 if let Some(dict) = dict_key {
                     format!("{}<String, {}>", cfg.map.name(), dict)
                 } else {
-                    format!("{}{}", stack, key.to_upper_camel_case())
+                    let name = format!("{}{}", stack, type_name(key, cfg));
+                    cfg.struct_name(&member_path, name, value)
                 }
             }
             "string" => {
                 if let Some(_en) = &value.enum_ {
                     trace!("got enum string: {}", serde_json::to_string(&schema).unwrap());
-                    format!("{}{}", stack, key.to_upper_camel_case())
+                    cfg.enum_name(&member_path, format!("{}{}", stack, type_name(key, cfg)))
+                } else if value.format.as_deref() == Some("duration")
+                    && cfg.duration == DurationType::Duration
+                {
+                    serde_with = Some("kopium_duration");
+                    "Duration".to_string()
                 } else {
-                    "String".to_string()
+                    extract_string_type(value, cfg)
                 }
             }
             "boolean" => "bool".to_string(),
-            "date" => extract_date_type(value)?,
-            "number" => extract_number_type(value)?,
-            "integer" => extract_integer_type(value)?,
+            "date" => extract_date_type(value, &member_path, cfg)?,
+            "number" => extract_number_type(value, &member_path, cfg),
+            "integer" => {
+                if value.enum_.is_some() {
+                    cfg.enum_name(&member_path, format!("{}{}", stack, type_name(key, cfg)))
+                } else {
+                    extract_integer_type(value, &member_path, cfg)
+                }
+            }
             "array" => {
                 // recurse through repeated arrays until we find a concrete type (keep track of how deep we went)
-                let (mut array_type, recurse_level) = array_recurse_for_type(value, stack, key, 1, cfg)?;
+                let (mut array_type, recurse_level) =
+                    array_recurse_for_type(value, stack, key, &member_path, 1, cfg)?;
                 trace!("got array {} for {} in level {}", array_type, key, recurse_level);
                 if !cfg.no_condition && key == "conditions" && is_conditions(value) {
                     array_type = "Vec<Condition>".into();
+                } else if list_map_key(value, cfg).is_some() {
+                    // the items struct leaves out the key, see find_containers
+                    array_recurse_level.insert(key.clone(), recurse_level);
+                    serde_with = Some("kopium_list_map");
+                    let items = array_type.strip_prefix("Vec<").unwrap_or(&array_type);
+                    array_type = format!("BTreeMap<String, {}", items);
                 } else {
                     array_recurse_level.insert(key.clone(), recurse_level);
+                    if cfg.list_map_as_map && value.x_kubernetes_list_type.as_deref() == Some("map") {
+                        let keys = value.x_kubernetes_list_map_keys.clone().unwrap_or_default();
+                        cfg.warn(
+                            WarningKind::Notice,
+                            format!(
+                                "list-type=map at {} is not keyed by a single string field ({}) - using Vec",
+                                member_path,
+                                keys.join(", ")
+                            ),
+                        );
+                    }
                 }
                 array_type
             }
             "" => {
                 let map_type = cfg.map.name();
-                if value.x_kubernetes_int_or_string.is_some() {
-                    "IntOrString".into()
+                if is_int_or_string(value) {
+                    cfg.int_or_string(&member_path)
                 } else if value.x_kubernetes_preserve_unknown_fields == Some(true) {
                     "serde_json::Value".into()
                 } else if cfg.relaxed {
-                    debug!("found empty object at {} key: {}", stack, key);
+                    cfg.warn(
+                        WarningKind::Untyped,
+                        format!("unknown empty dict type at {} - using map", member_path),
+                    );
                     format!("{map_type}<String, serde_json::Value>")
                 } else {
-                    bail!("unknown empty dict type for {}", key)
+                    bail!("unknown empty dict type for {} at {}", key, member_path)
+                }
+            }
+            x => {
+                if cfg.relaxed {
+                    cfg.warn(
+                        WarningKind::Untyped,
+                        format!("unknown type {} at {} - using serde_json::Value", x, member_path),
+                    );
+                    "serde_json::Value".into()
+                } else {
+                    bail!("unknown type {} at {}", x, member_path)
                 }
             }
-            x => bail!("unknown type {}", x),
         };
 
         // Create member and wrap types correctly
-        let member_doc = value.description.clone();
-        if reqs.contains(key) {
+        let mut member_doc = value.description.clone();
+        if let Some(format) = unmapped_string_format(value, cfg) {
+            // the format is lost in the String type, so keep it visible to consumers
+            let format_doc = format!("Format: {}", format);
+            member_doc = Some(match member_doc {
+                Some(d) => format!("{}\n\n{}", d, format_doc),
+                None => format_doc,
+            });
+        }
+        if !is_struct(value) {
+            // rules of objects are documented on their struct
+            member_doc = with_cel_docs(member_doc, value);
+        }
+        let constraints = extract_constraints(value).filter(|_| cfg.constraints);
+        let schema = (cfg.schemas && loses_schema(&rust_type, serde_with, value, cfg))
+            .then(|| serde_json::to_value(value))
+            .transpose()?;
+        let nullable = value.nullable.unwrap_or(false) && rust_type != "serde_json::Value";
+        if required && nullable {
+            // required, but an explicit null is valid and must survive a round-trip
+            debug!("with required nullable member {} of type {}", key, rust_type);
+            members.push(Member {
+                type_: format!("Option<{}>", rust_type),
+                name: key.to_string(),
+                serde_annot: serde_with
+                    .map(|w| format!("with = \"{}::option\"", w))
+                    .into_iter()
+                    .collect(),
+                extra_annot: vec![],
+                docs: member_doc,
+                discriminant: None,
+                default: None,
+                constraints,
+                schema,
+            })
+        } else if required {
             debug!("with required member {} of type {}", key, &rust_type);
+            members.push(Member {
+                type_: rust_type,
+                name: key.to_string(),
+                serde_annot: serde_with
+                    .map(|w| format!("with = \"{}\"", w))
+                    .into_iter()
+                    .collect(),
+                extra_annot: vec![],
+                docs: member_doc,
+                discriminant: None,
+                default: None,
+                constraints,
+                schema,
+            })
+        } else if let Some(default) = value.default.as_ref().filter(|_| {
+            // types (de)serialized through a helper module do not take the value as it is
+            cfg.schema_defaults && !nullable && serde_with.is_none()
+        }) {
+            // the schema default fills in for a missing value, see `Output::default_fns`
+            debug!("with defaulted member {} of type {}", key, rust_type);
             members.push(Member {
                 type_: rust_type,
                 name: key.to_string(),
                 serde_annot: vec![],
                 extra_annot: vec![],
                 docs: member_doc,
+                discriminant: None,
+                default: Some(default.0.clone()),
+                constraints,
+                schema,
             })
         } else {
             // option wrapping needed if not required
@@ -318,92 +1285,432 @@ fn extract_container(
                 serde_annot: vec![
                     "default".into(),
                     "skip_serializing_if = \"Option::is_none\"".into(),
-                ],
+                ]
+                .into_iter()
+                .chain(serde_with.map(|w| format!("with = \"{}::option\"", w)))
+                .collect(),
                 extra_annot: vec![],
                 docs: member_doc,
+                discriminant: None,
+                default: None,
+                constraints,
+                schema,
             })
-            // TODO: must capture `default` key here instead of blindly using serde default
-            // this will require us storing default properties for the member in above loop
-            // This is complicated because serde default requires a default fn / impl Default
-            // probably better to do impl Default to avoid having to make custom fns
         }
     }
     Ok(Container {
         name: stack.to_string(),
         members,
         level,
-        docs: schema.description.clone(),
+        docs: with_cel_docs(schema.description.clone(), schema),
         is_enum: false,
+        cel_rules: cel_rules(schema),
         ..Container::default()
     })
 }
 
-fn resolve_additional_properties(
-    additional: &JSONSchemaPropsOrBool,
-    stack: &str,
-    key: &str,
-    value: &JSONSchemaProps,
-) -> Result<Option<String>, anyhow::Error> {
-    debug!("got additional: {}", serde_json::to_string(&additional)?);
-    let JSONSchemaPropsOrBool::Schema(s) = additional else {
-        return Ok(None);
-    };
+/// CEL rules of a schema from `x-kubernetes-validations`, along with their messages
+///
+/// Rules without a message get the one the apiserver rejects violations with.
+fn cel_rules(value: &JSONSchemaProps) -> Vec<(String, String)> {
+    let validations = value.x_kubernetes_validations.iter().flatten();
+    validations
+        .map(|v| {
+            let message = v.message.clone();
+            (
+                v.rule.clone(),
+                message.unwrap_or_else(|| format!("failed rule: {}", v.rule)),
+            )
+        })
+        .collect()
+}
 
-    // This case is for maps. It is generally String -> Something, depending on the type key:
-    let dict_type = s.type_.clone().unwrap_or_default();
-    debug!("dict type is {dict_type}");
-    let dict_key = match dict_type.as_ref() {
-        "string" => Some("String".into()),
-        // We are not 100% sure the array and object subcases here are correct but they pass tests atm.
-        // authoratative, but more detailed sources than crd validation docs below are welcome
-        // https://kubernetes.io/docs/tasks/extend-kubernetes/custom-resources/custom-resource-definitions/#validation
-        "array" => {
-            let mut simple_inner = None;
-            if let Some(JSONSchemaPropsOrArray::Schema(ix)) = &s.items {
-                simple_inner = ix.type_.clone();
-                debug!("additional simple inner  type: {:?}", simple_inner);
-            }
-            // Simple case: additionalProperties contain: {items: {type: K}}
-            // Then it's a simple map (service_monitor_params) - but key is useless
-            match simple_inner.as_deref() {
-                Some("string") => Some("String".into()),
-                Some("integer") => Some(extract_integer_type(s)?),
-                Some("date") => Some(extract_date_type(value)?),
-                Some("") => {
-                    if s.x_kubernetes_int_or_string.is_some() {
-                        Some("IntOrString".into())
-                    } else {
-                        bail!("unknown inner empty dict type for {}", key)
-                    }
-                }
-                // can probably cover the regulars here as well
+/// Documentation of the CEL rules of a schema, appended to its description
+///
+/// Rules that do not fit into a code span go into a code block.
+fn with_cel_docs(docs: Option<String>, value: &JSONSchemaProps) -> Option<String> {
+    let Some(rules) = value.x_kubernetes_validations.as_ref().filter(|r| !r.is_empty()) else {
+        return docs;
+    };
+    let mut rule_docs = "Validation rules:".to_string();
+    for v in rules {
+        let message = v
+            .message
+            .as_deref()
+            .map(|m| format!(": {}", m))
+            .unwrap_or_default();
+        if v.rule.contains('\n') || v.rule.contains('`') {
+            rule_docs.push_str(&format!(
+                "\n- Rule{}\n```text\n{}\n```",
+                message,
+                v.rule.trim_end()
+            ));
+        } else {
+            rule_docs.push_str(&format!("\n- `{}`{}", v.rule, message));
+        }
+    }
+    Some(match docs {
+        Some(d) => format!("{}\n\n{}", d, rule_docs),
+        None => rule_docs,
+    })
+}
 
-                // Harder case: inline structs under items (agent test with `validationInfo`)
-                // key becomes the struct
-                Some("object") => Some(format!("{}{}", stack, key.to_upper_camel_case())),
-                None => Some(format!("{}{}", stack, key.to_upper_camel_case())),
+/// Field keying the items of a list with `x-kubernetes-list-type: map`, if the list is represented as a map
+///
+/// With `Config::list_map_as_map`, lists of structs keyed by a single string field become maps.
+fn list_map_key<'a>(value: &'a JSONSchemaProps, cfg: &Config) -> Option<&'a str> {
+    if !cfg.list_map_as_map || value.x_kubernetes_list_type.as_deref() != Some("map") {
+        return None;
+    }
+    let Some(JSONSchemaPropsOrArray::Schema(items)) = &value.items else {
+        return None;
+    };
+    let [key] = value.x_kubernetes_list_map_keys.as_deref()? else {
+        return None;
+    };
+    let key_type = items.properties.as_ref()?.get(key)?.type_.as_deref();
+    // referenced structs are shared, so they cannot leave out the key
+    let owned_struct = is_struct(items) && items.ref_path.is_none() && items.nullable != Some(true);
+    (owned_struct && key_type == Some("string")).then_some(key.as_str())
+}
 
-                // leftovers, array of arrays?... need a better way to recurse probably
-                Some(x) => bail!("unknown inner empty dict type {} for {}", x, key),
+/// Whether a member of type `rust_type` gets a derived schema that differs from its `schema`
+///
+/// Values derive an empty schema, sets derive `uniqueItems` which the apiserver rejects, ByteString derives none,
+/// and types (de)serialized through a helper module or coerced to a String derive the schema of their rust type.
+fn loses_schema(rust_type: &str, serde_with: Option<&str>, schema: &JSONSchemaProps, cfg: &Config) -> bool {
+    rust_type.contains("serde_json::Value")
+        || rust_type.contains("BTreeSet<")
+        || rust_type.contains("ByteString")
+        || serde_with.is_some()
+        || (cfg.coerce_int_or_string && is_int_or_string(schema))
+}
+
+/// Validation constraints of a schema, if it declares any
+fn extract_constraints(value: &JSONSchemaProps) -> Option<Constraints> {
+    // exclusive bounds only qualify a minimum or maximum
+    let bounded = value.minimum.is_some() || value.maximum.is_some();
+    let sized = [
+        value.min_length,
+        value.max_length,
+        value.min_items,
+        value.max_items,
+    ]
+    .iter()
+    .any(Option::is_some);
+    if !bounded && !sized && value.pattern.is_none() {
+        return None;
+    }
+    Some(Constraints {
+        minimum: value.minimum,
+        exclusive_minimum: value.exclusive_minimum.unwrap_or(false),
+        maximum: value.maximum,
+        exclusive_maximum: value.exclusive_maximum.unwrap_or(false),
+        min_length: value.min_length,
+        max_length: value.max_length,
+        pattern: value.pattern.clone(),
+        min_items: value.min_items,
+        max_items: value.max_items,
+    })
+}
+
+/// Catch-all member keeping the fields of an object that its properties do not declare
+///
+/// Named `extra`, unless a property already takes that field name.
+/// Field names of properties are snake cased, so they never end in an underscore like `extra_`.
+fn unknown_fields_member(props: &BTreeMap<String, JSONSchemaProps>, path: &str, cfg: &Context) -> Member {
+    let mut name = "extra".to_string();
+    if let Some(key) = props.keys().find(|k| k.to_snake_case() == name) {
+        cfg.warn(
+            WarningKind::Notice,
+            format!(
+                "property {} at {} clashes with the field for unknown fields - using extra_",
+                key, path
+            ),
+        );
+        name.push('_');
+    }
+    Member {
+        type_: format!("{}<String, serde_json::Value>", cfg.map.name()),
+        name,
+        serde_annot: vec!["flatten".into()],
+        extra_annot: vec![],
+        docs: Some("Fields not declared by the schema".into()),
+        discriminant: None,
+        default: None,
+        constraints: None,
+        schema: None,
+    }
+}
+
+/// Alternatives of a oneOf that declare types of their own, merged with the surrounding schema
+///
+/// Alternatives that only list `required` properties constrain which fields of the surrounding
+/// object must be set. That object is generated as a struct holding all of them, so it is no union.
+fn union_alternatives(value: &JSONSchemaProps) -> Option<Vec<JSONSchemaProps>> {
+    let alternatives = value.one_of.as_ref()?;
+    if !alternatives
+        .iter()
+        .any(|alt| alt.type_.is_some() || alt.properties.is_some())
+    {
+        return None;
+    }
+    let merged = alternatives
+        .iter()
+        .map(|alt| {
+            let mut merged = alt.clone();
+            if let Some(shared) = &value.properties {
+                let mut props = shared.clone();
+                props.extend(alt.properties.clone().unwrap_or_default());
+                merged.properties = Some(props);
+            }
+            if let Some(shared) = &value.required {
+                let mut required = shared.clone();
+                required.extend(alt.required.clone().unwrap_or_default());
+                required.sort();
+                required.dedup();
+                merged.required = Some(required);
+            }
+            if merged.type_.is_none() {
+                merged.type_ = if merged.properties.is_some() {
+                    Some("object".to_string())
+                } else {
+                    value.type_.clone()
+                };
+            }
+            merged
+        })
+        .collect();
+    Some(merged)
+}
+
+/// The first pair of (1-indexed) alternatives that an untagged enum cannot tell apart, if any
+///
+/// Objects are told apart by their required properties and anything else by its type.
+/// Alternatives without a type match anything, so they cannot be told apart from any other.
+fn indistinguishable_alternatives(alternatives: &[JSONSchemaProps]) -> Option<(usize, usize)> {
+    let shape = |alt: &JSONSchemaProps| {
+        let type_ = alt.type_.clone().unwrap_or_default();
+        let required = match type_.as_ref() {
+            "object" => alt.required.clone().unwrap_or_default(),
+            _ => vec![],
+        };
+        (type_, required)
+    };
+    for (i, first) in alternatives.iter().enumerate() {
+        for (j, second) in alternatives.iter().enumerate().skip(i + 1) {
+            if shape(first).0.is_empty() || shape(second).0.is_empty() || shape(first) == shape(second) {
+                return Some((i + 1, j + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Name of the enum variant for a oneOf alternative
+fn union_variant_name(alt: &JSONSchemaProps, original: &JSONSchemaProps) -> String {
+    if let Some(title) = &original.title {
+        return pascal(title);
+    }
+    match alt.type_.as_deref().unwrap_or_default() {
+        "object" => {
+            // alternatives are commonly told apart by a single property of their own
+            let own = original
+                .properties
+                .iter()
+                .flat_map(|p| p.keys())
+                .collect::<Vec<_>>();
+            let own_required = original.required.iter().flatten().collect::<Vec<_>>();
+            match (&own[..], &own_required[..]) {
+                ([key], _) | (_, [key]) => pascal(key),
+                _ => "Object".to_string(),
+            }
+        }
+        x => pascal(x),
+    }
+}
+
+/// Generate an untagged enum for a property with oneOf alternatives, along with the types of its variants
+///
+/// Object alternatives become structs wrapped by their variant, other types become newtype variants.
+fn analyze_union(
+    alternatives: &[JSONSchemaProps],
+    value: &JSONSchemaProps,
+    name: &str,
+    path: &str,
+    level: u8,
+    results: &mut Vec<Container>,
+    cfg: &Context,
+) -> Result<()> {
+    let originals = value.one_of.clone().unwrap_or_default();
+    // an earlier object alternative matches every value of a later one requiring more properties
+    for (i, first) in alternatives.iter().enumerate() {
+        for (j, second) in alternatives.iter().enumerate().skip(i + 1) {
+            let (Some(first_required), Some(second_required)) = (&first.required, &second.required) else {
+                continue;
+            };
+            let objects = first.type_.as_deref() == Some("object") && second.type_ == first.type_;
+            if objects && first_required.iter().all(|r| second_required.contains(r)) {
+                cfg.warn(
+                    WarningKind::Notice,
+                    format!(
+                        "ambiguous oneOf alternatives {} and {} at {} - trying them in schema order",
+                        i + 1,
+                        j + 1,
+                        path
+                    ),
+                );
+            }
+        }
+    }
+
+    let mut members: Vec<Member> = vec![];
+    let mut variants = vec![];
+    for (i, (alt, original)) in alternatives.iter().zip(&originals).enumerate() {
+        let alt_path = format!("{}.oneOf[{}]", path, i);
+        // alternatives may share a name (e.g. several `Object`s), suffix a counter as enum_name does
+        let base = union_variant_name(alt, original);
+        let mut variant = base.clone();
+        let mut counter = 2;
+        while members.iter().any(|m| m.name == variant) {
+            variant = format!("{}{}", base, counter);
+            counter += 1;
+        }
+        let struct_name = format!("{}{}", name, with_acronyms(&variant, cfg));
+        let rust_type = match alt.type_.as_deref().unwrap_or_default() {
+            "object" if alt.properties.is_some() => {
+                let struct_name = cfg.struct_name(&alt_path, struct_name, alt);
+                variants.push((alt, struct_name.clone(), alt_path));
+                struct_name
             }
+            "object" => format!("{}<String, serde_json::Value>", cfg.map.name()),
+            "string" => extract_string_type(alt, cfg),
+            "boolean" => "bool".to_string(),
+            "number" => extract_number_type(alt, &alt_path, cfg),
+            "integer" => extract_integer_type(alt, &alt_path, cfg),
+            "array" => {
+                let (array_type, recurse) = array_recurse_for_type(alt, name, &variant, &alt_path, 1, cfg)?;
+                // unwrap the object items, as the array arm of find_containers does
+                let mut inner = alt;
+                let mut inner_path = alt_path.clone();
+                for _ in 0..recurse {
+                    if let Some(JSONSchemaPropsOrArray::Schema(s)) = &inner.items {
+                        inner = s;
+                        inner_path.push_str("[]");
+                    }
+                }
+                if recurse > 0 && is_struct(inner) {
+                    let struct_name = format!("{}{}", name, item_suffix(&variant, cfg));
+                    let struct_name = cfg.struct_name(&inner_path, struct_name, inner);
+                    variants.push((inner, struct_name, inner_path));
+                }
+                array_type
+            }
+            x => bail!("unsupported oneOf alternative type '{}' at {}", x, alt_path),
+        };
+        members.push(Member {
+            type_: rust_type,
+            name: variant,
+            serde_annot: vec![],
+            extra_annot: vec![],
+            docs: original.description.clone(),
+            discriminant: None,
+            default: None,
+            constraints: None,
+            schema: None,
+        });
+    }
+    results.push(Container {
+        name: name.to_string(),
+        members,
+        level,
+        docs: value.description.clone(),
+        is_enum: true,
+        ..Container::default()
+    });
+    for (schema, struct_name, alt_path) in variants {
+        analyze_(schema, "", &struct_name, &alt_path, level + 1, results, cfg)?;
+    }
+    Ok(())
+}
+
+fn resolve_additional_properties(
+    additional: &JSONSchemaPropsOrBool,
+    stack: &str,
+    key: &str,
+    path: &str,
+    value: &JSONSchemaProps,
+    cfg: &Context,
+) -> Result<Option<String>, anyhow::Error> {
+    debug!("got additional: {}", serde_json::to_string(&additional)?);
+    let JSONSchemaPropsOrBool::Schema(s) = additional else {
+        // any value is allowed, but with declared properties this is a struct with a catch-all member
+        return Ok(is_free_form(value).then(|| "serde_json::Value".into()));
+    };
+    if let Some(name) = cfg.referenced_type(s) {
+        return Ok(Some(name));
+    }
+    if is_embedded_resource(s) {
+        return Ok(Some(cfg.embedded.name().to_string()));
+    }
+
+    // This case is for maps. It is generally String -> Something, depending on the type key:
+    let dict_type = s.type_.clone().unwrap_or_default();
+    debug!("dict type is {dict_type}");
+    let dict_key = match dict_type.as_ref() {
+        "string" => Some(extract_string_type(s, cfg)),
+        // We are not 100% sure the array and object subcases here are correct but they pass tests atm.
+        // authoratative, but more detailed sources than crd validation docs below are welcome
+        // https://kubernetes.io/docs/tasks/extend-kubernetes/custom-resources/custom-resource-definitions/#validation
+        "array" => {
+            // lists as map values get the same item types as array members
+            let (array_type, _) = array_recurse_for_type(s, stack, key, &format!("{}.*", path), 1, cfg)?;
+            Some(array_type)
         }
+        "object" if is_free_form(s) => Some(format!("{}<String, serde_json::Value>", cfg.map.name())),
         "object" => {
-            // cluster test with `failureDomains` uses this spec format
-            Some(format!("{}{}", stack, key.to_upper_camel_case()))
+            if let Some(inner @ JSONSchemaPropsOrBool::Schema(_)) = &s.additional_properties {
+                // map of maps
+                let values_path = format!("{}.*", path);
+                resolve_additional_properties(inner, stack, key, &values_path, s, cfg)?
+                    .map(|v| format!("{}<String, {}>", cfg.map.name(), v))
+            } else {
+                // cluster test with `failureDomains` uses this spec format
+                let name = format!("{}{}", stack, item_suffix(key, cfg));
+                Some(cfg.struct_name(&format!("{}.*", path), name, s))
+            }
         }
         "" => {
-            if s.x_kubernetes_int_or_string.is_some() {
-                Some("IntOrString".into())
-            } else if s.x_kubernetes_preserve_unknown_fields == Some(true) {
+            if is_int_or_string(s) {
+                Some(cfg.int_or_string(&format!("{}.*", path)))
+            } else if s.x_kubernetes_preserve_unknown_fields == Some(true) || cfg.relaxed {
+                if cfg.relaxed && s.x_kubernetes_preserve_unknown_fields != Some(true) {
+                    cfg.warn(
+                        WarningKind::Untyped,
+                        format!("unknown empty dict type at {}.* - using serde_json::Value", path),
+                    );
+                }
                 Some("serde_json::Value".into())
             } else {
-                bail!("unknown empty dict type for {}", key)
+                bail!("unknown empty dict type for {} at {}.*", key, path)
             }
         }
+        // scalar values map like scalar members, including their format
         "boolean" => Some("bool".to_string()),
-        "integer" => Some(extract_integer_type(s)?),
-        // think the type we get is the value type
-        x => Some(x.to_upper_camel_case()), // best guess
+        "date" => Some(extract_date_type(s, &format!("{}.*", path), cfg)?),
+        "integer" => Some(extract_integer_type(s, &format!("{}.*", path), cfg)),
+        "number" => Some(extract_number_type(s, &format!("{}.*", path), cfg)),
+        x => {
+            if cfg.relaxed {
+                cfg.warn(
+                    WarningKind::Untyped,
+                    format!("unknown dict type {} at {}.* - using serde_json::Value", x, path),
+                );
+                Some("serde_json::Value".into())
+            } else {
+                bail!("unknown dict type {} for {} at {}.*", x, key, path)
+            }
+        }
     };
 
     Ok(dict_key)
@@ -415,66 +1722,361 @@ fn array_recurse_for_type(
     value: &JSONSchemaProps,
     stack: &str,
     key: &str,
+    path: &str,
     level: u8,
-    cfg: &Config,
+    cfg: &Context,
+) -> Result<(String, u8)> {
+    let (array_type, level) = array_items_type(value, stack, key, path, level, cfg)?;
+    match &value.items {
+        // lists holding explicit nulls, e.g. as tombstones, have optional items
+        Some(JSONSchemaPropsOrArray::Schema(s)) if s.nullable == Some(true) => {
+            Ok((nullable_items(&array_type), level))
+        }
+        _ => Ok((array_type, level)),
+    }
+}
+
+/// A list type such as `Vec<T>` with its items wrapped in an Option
+///
+/// Json values are left as they are, as they hold nulls themselves.
+fn nullable_items(array_type: &str) -> String {
+    match array_type.split_once('<') {
+        Some((list, item)) if item != "serde_json::Value>" => format!("{}<Option<{}>", list, item),
+        _ => array_type.to_string(),
+    }
+}
+
+// the list type of an array, with the items of nested arrays already made optional where nullable
+fn array_items_type(
+    value: &JSONSchemaProps,
+    stack: &str,
+    key: &str,
+    path: &str,
+    level: u8,
+    cfg: &Context,
 ) -> Result<(String, u8)> {
+    let items_path = format!("{}[]", path);
     if let Some(items) = &value.items {
         match items {
             JSONSchemaPropsOrArray::Schema(s) => {
+                if let Some(name) = cfg.referenced_type(s) {
+                    // level 0 means there is nothing to recurse into for this member
+                    return Ok((format!("Vec<{}>", name), 0));
+                }
+                if is_embedded_resource(s) {
+                    return Ok((format!("Vec<{}>", cfg.embedded.name()), 0));
+                }
                 if s.type_.is_none() && s.x_kubernetes_preserve_unknown_fields == Some(true) {
                     let map_type = cfg.map.name();
                     return Ok((format!("Vec<{}<String, serde_json::Value>>", map_type), level));
                 }
                 let inner_array_type = s.type_.clone().unwrap_or_default();
-                return match inner_array_type.as_ref() {
+                match inner_array_type.as_ref() {
                     "object" => {
                         // Same logic as in `extract_container` to simplify types to maps.
                         let mut dict_value = None;
                         if let Some(additional) = &s.additional_properties {
-                            dict_value = resolve_additional_properties(additional, stack, key, s)?;
+                            dict_value =
+                                resolve_additional_properties(additional, stack, key, &items_path, s, cfg)?;
                         }
 
                         let vec_value = if let Some(dict_value) = dict_value {
                             let map_type = cfg.map.name();
                             format!("{map_type}<String, {dict_value}>")
+                        } else if is_free_form(s) {
+                            let map_type = cfg.map.name();
+                            format!("{map_type}<String, serde_json::Value>")
                         } else {
-                            let structsuffix = key.to_upper_camel_case();
-                            format!("{stack}{structsuffix}")
+                            let structsuffix = item_suffix(key, cfg);
+                            cfg.struct_name(&items_path, format!("{stack}{structsuffix}"), s)
                         };
 
                         Ok((format!("Vec<{}>", vec_value), level))
                     }
-                    "string" => Ok(("Vec<String>".into(), level)),
-                    "boolean" => Ok(("Vec<bool>".into(), level)),
-                    "date" => Ok((format!("Vec<{}>", extract_date_type(value)?), level)),
-                    "number" => Ok((format!("Vec<{}>", extract_number_type(value)?), level)),
-                    "integer" => Ok((format!("Vec<{}>", extract_integer_type(value)?), level)),
+                    "string" => Ok((list_type(value, &extract_string_type(s, cfg), cfg), level)),
+                    "boolean" => Ok((list_type(value, "bool", cfg), level)),
+                    "date" => Ok((
+                        format!("Vec<{}>", extract_date_type(value, &items_path, cfg)?),
+                        level,
+                    )),
+                    "number" => Ok((
+                        format!("Vec<{}>", extract_number_type(s, &items_path, cfg)),
+                        level,
+                    )),
+                    "integer" => Ok((
+                        list_type(value, &extract_integer_type(s, &items_path, cfg), cfg),
+                        level,
+                    )),
+                    "" if is_int_or_string(s) => {
+                        Ok((format!("Vec<{}>", cfg.int_or_string(&items_path)), level))
+                    }
                     "array" => {
-                        if s.items.is_some() {
-                            Ok(array_recurse_for_type(s, stack, key, level + 1, cfg)?)
+                        if s.items.is_some() || s.x_kubernetes_preserve_unknown_fields == Some(true) {
+                            // one Vec per level, the innermost type comes from the deepest items
+                            let (inner_type, inner_level) =
+                                array_recurse_for_type(s, stack, key, &items_path, level + 1, cfg)?;
+                            Ok((format!("Vec<{}>", inner_type), inner_level))
                         } else if cfg.relaxed {
-                            warn!("Empty inner array in: {} key: {}", stack, key);
-                            let map_type = cfg.map.name();
-                            Ok((format!("{}<String, serde_json::Value>", map_type), level))
+                            cfg.warn(
+                                WarningKind::Untyped,
+                                format!("Empty inner array at {} - using serde_json::Value", items_path),
+                            );
+                            Ok(("Vec<Vec<serde_json::Value>>".into(), 0))
                         } else {
-                            bail!("Empty inner array in: {} key: {}", stack, key);
+                            bail!("Empty inner array in: {} key: {} at {}", stack, key, items_path);
                         }
                     }
                     unknown => {
-                        bail!("unsupported recursive array type \"{unknown}\" for {key}")
+                        if cfg.relaxed {
+                            cfg.warn(WarningKind::Untyped, format!(
+                                "unsupported array type \"{unknown}\" at {items_path} - using serde_json::Value"
+                            ));
+                            // level 0 means there is nothing to recurse into for this member
+                            Ok(("Vec<serde_json::Value>".into(), 0))
+                        } else {
+                            bail!("unsupported recursive array type \"{unknown}\" for {key} at {items_path}")
+                        }
                     }
-                };
+                }
+            }
+            JSONSchemaPropsOrArray::Schemas(schemas) => {
+                // tuple validation, level 0 as there is nothing to recurse into either way
+                if let Some(tuple) = tuple_type(schemas, path, cfg) {
+                    Ok((tuple, 0))
+                } else {
+                    cfg.warn(
+                        WarningKind::Untyped,
+                        format!(
+                            "tuple items with non-scalar schemas at {} - using serde_json::Value",
+                            path
+                        ),
+                    );
+                    Ok(("Vec<serde_json::Value>".into(), 0))
+                }
             }
-            // maybe fallback to serde_json::Value
-            _ => bail!("only support single schema in array {}", key),
         }
+    } else if value.x_kubernetes_preserve_unknown_fields == Some(true) {
+        // free-form list, nothing to recurse into
+        Ok(("Vec<serde_json::Value>".into(), 0))
+    } else if cfg.relaxed {
+        cfg.warn(
+            WarningKind::Untyped,
+            format!("missing items in array at {} - using serde_json::Value", path),
+        );
+        Ok(("Vec<serde_json::Value>".into(), 0))
     } else {
-        bail!("missing items in array type")
+        bail!("missing items in array type at {}", path)
     }
 }
 
 // ----------------------------------------------------------------------------
 // helpers
+/// Type of an array with scalar items of type `item`
+///
+/// With `Config::sets`, arrays of unique items become a BTreeSet, if their items are ordered.
+/// Floats are not, and neither are the structs and enums generated for objects and enum values.
+fn list_type(value: &JSONSchemaProps, item: &str, cfg: &Config) -> String {
+    let unique = value.x_kubernetes_list_type.as_deref() == Some("set") || value.unique_items == Some(true);
+    let ordered = matches!(item, "String" | "bool" | "i32" | "i64" | "u32" | "u64");
+    if cfg.sets && unique && ordered {
+        format!("BTreeSet<{}>", item)
+    } else {
+        format!("Vec<{}>", item)
+    }
+}
+
+/// Rust tuple type for tuple-style array items, if all of the item schemas are scalars
+fn tuple_type(schemas: &[JSONSchemaProps], path: &str, cfg: &Context) -> Option<String> {
+    let types = schemas
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let item_path = format!("{}[{}]", path, i);
+            match s.type_.as_deref().unwrap_or_default() {
+                "string" => Some(extract_string_type(s, cfg)),
+                "boolean" => Some("bool".to_string()),
+                "integer" => Some(extract_integer_type(s, &item_path, cfg)),
+                "number" => Some(extract_number_type(s, &item_path, cfg)),
+                "" if is_int_or_string(s) => Some(cfg.int_or_string(&item_path)),
+                _ => None,
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+    match types.as_slice() {
+        [] => None,
+        [single] => Some(format!("({},)", single)),
+        _ => Some(format!("({})", types.join(", "))),
+    }
+}
+
+/// Whether a schema without a type is an integer or a string
+///
+/// Besides the `x-kubernetes-int-or-string` extension, this recognizes the `anyOf` of an integer
+/// and a string schema that some CRDs use instead, regardless of order and formats.
+fn is_int_or_string(value: &JSONSchemaProps) -> bool {
+    if value.x_kubernetes_int_or_string.is_some() {
+        return true;
+    }
+    let Some(any_of) = &value.any_of else {
+        return false;
+    };
+    let mut types = any_of
+        .iter()
+        .map(|s| s.type_.as_deref().unwrap_or_default())
+        .collect::<Vec<_>>();
+    types.sort_unstable();
+    types == ["integer", "string"]
+}
+
+/// A pascal cased name with every repeated run of words kept once, e.g. `FooTemplate` for `FooTemplateTemplate`
+///
+/// Only words of several letters count, so that acronyms such as `DNSSEC` are left alone.
+fn without_repeated_words(name: &str) -> String {
+    let mut kept: Vec<&str> = vec![];
+    for word in words(name) {
+        kept.push(word);
+        for len in 1..=kept.len() / 2 {
+            let (run, repeat) = kept[kept.len() - 2 * len..].split_at(len);
+            let same = run.iter().zip(repeat).all(|(a, b)| a.eq_ignore_ascii_case(b));
+            if same && run.iter().all(|w| w.len() > 1) {
+                kept.truncate(kept.len() - len);
+                break;
+            }
+        }
+    }
+    kept.concat()
+}
+
+/// Singular form of a pascal cased plural such as `Resources` or `HostAliases`
+///
+/// Only the last word is changed. Covers regular english plurals and a few irregular words
+/// common in CRDs, and leaves words such as `Status`, `Analysis` or `Tls` alone.
+fn singular(name: &str) -> String {
+    let (head, word) = name.split_at(name.rfind(|c: char| c.is_ascii_uppercase()).unwrap_or(0));
+    let word = match word {
+        "Always" | "Canvas" | "Cors" | "Dns" | "Https" | "Kubernetes" | "News" | "Series" | "Species"
+        | "Tls" => word.to_string(),
+        "Aliases" => "Alias".to_string(),
+        "Analyses" => "Analysis".to_string(),
+        "Caches" => "Cache".to_string(),
+        "Cookies" => "Cookie".to_string(),
+        "Indices" => "Index".to_string(),
+        "Statuses" => "Status".to_string(),
+        "Vertices" => "Vertex".to_string(),
+        _ => regular_singular(word),
+    };
+    format!("{}{}", head, word)
+}
+
+/// Singular form of a word with a regular english plural
+fn regular_singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies").filter(|s| !s.is_empty()) {
+        return format!("{}y", stem);
+    }
+    if ["sses", "shes", "ches", "xes"].iter().any(|s| word.ends_with(s)) {
+        return word[..word.len() - 2].to_string();
+    }
+    if word.len() > 1 && word.ends_with('s') && !["ss", "us", "is"].iter().any(|s| word.ends_with(s)) {
+        return word[..word.len() - 1].to_string();
+    }
+    word.to_string()
+}
+
+/// Whether an object schema is generated as a struct, rather than as a map or an embedded resource
+fn is_struct(value: &JSONSchemaProps) -> bool {
+    value.type_.as_deref() == Some("object")
+        && !matches!(
+            value.additional_properties,
+            Some(JSONSchemaPropsOrBool::Schema(_))
+        )
+        && !is_free_form(value)
+        && !is_embedded_resource(value)
+}
+
+/// Whether a string can be used as a name as it is, such as `EmbeddedObjectMeta`
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Struct name suffix for the items of a list or the values of a map under a key
+fn item_suffix(key: &str, cfg: &Config) -> String {
+    let suffix = pascal(key);
+    if cfg.no_singularize || cfg.naming == Naming::FullPath {
+        with_acronyms(&suffix, cfg)
+    } else {
+        with_acronyms(&singular(&suffix), cfg)
+    }
+}
+
+/// Pascal cased name part for a property key, definition or title, e.g. `HttpGet` for `httpGet`
+///
+/// Member types and the structs generated for them both take their names from here, so they always match.
+fn type_name(key: &str, cfg: &Config) -> String {
+    with_acronyms(&pascal(key), cfg)
+}
+
+/// Pascal cased name part for a key, sanitized the way member names are (e.g. `Cafe` for `café`)
+fn pascal(key: &str) -> String {
+    sanitize_ident(key, IdentKind::Suffix).unwrap_or_default()
+}
+
+/// A pascal cased name with the configured acronyms in uppercase, e.g. `TLSConfig` for `TlsConfig`
+fn with_acronyms(name: &str, cfg: &Config) -> String {
+    if cfg.acronyms.is_empty() {
+        return name.to_string();
+    }
+    words(name)
+        .into_iter()
+        .map(|word| {
+            if cfg.acronyms.iter().any(|a| a.eq_ignore_ascii_case(word)) {
+                word.to_ascii_uppercase()
+            } else {
+                word.to_string()
+            }
+        })
+        .collect()
+}
+
+/// The words of a pascal cased name, each starting at an uppercase letter
+fn words(name: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut rest = name;
+    while !rest.is_empty() {
+        let end = rest
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| c.is_ascii_uppercase())
+            .map_or(rest.len(), |(i, _)| i);
+        let (word, tail) = rest.split_at(end);
+        words.push(word);
+        rest = tail;
+    }
+    words
+}
+
+/// Whether a schema holds a whole Kubernetes object, with apiVersion, kind and metadata
+fn is_embedded_resource(value: &JSONSchemaProps) -> bool {
+    value.x_kubernetes_embedded_resource == Some(true)
+}
+
+/// Whether a schema without properties keeps whatever fields it is given
+fn is_free_form(value: &JSONSchemaProps) -> bool {
+    value.properties.is_none() && keeps_unknown_fields(value)
+}
+
+/// Whether an object keeps fields it does not declare, rather than pruning them
+///
+/// Either through `x-kubernetes-preserve-unknown-fields`, or `additionalProperties: true`.
+/// A schema for the additionalProperties makes the object a map instead.
+fn keeps_unknown_fields(value: &JSONSchemaProps) -> bool {
+    match value.additional_properties {
+        Some(JSONSchemaPropsOrBool::Bool(allowed)) => allowed,
+        Some(JSONSchemaPropsOrBool::Schema(_)) => false,
+        None => value.x_kubernetes_preserve_unknown_fields == Some(true),
+    }
+}
+
 fn is_conditions(value: &JSONSchemaProps) -> bool {
     if let Some(JSONSchemaPropsOrArray::Schema(props)) = &value.items {
         if let Some(p) = &props.properties {
@@ -491,7 +2093,7 @@ fn is_conditions(value: &JSONSchemaProps) -> bool {
     false
 }
 
-fn extract_date_type(value: &JSONSchemaProps) -> Result<String> {
+fn extract_date_type(value: &JSONSchemaProps, path: &str, cfg: &Context) -> Result<String> {
     Ok(if let Some(f) = &value.format {
         // NB: these need chrono feature on serde
         match f.as_ref() {
@@ -500,32 +2102,74 @@ fn extract_date_type(value: &JSONSchemaProps) -> Result<String> {
             "date" => "NaiveDate".to_string(),
             "date-time" => "DateTime<Utc>".to_string(),
             x => {
-                bail!("unknown date {}", x);
+                bail!("unknown date {} at {}", x, path);
             }
         }
     } else {
+        cfg.warn(
+            WarningKind::Coerced,
+            format!("date without format at {} - using String", path),
+        );
         "String".to_string()
     })
 }
 
-fn extract_number_type(value: &JSONSchemaProps) -> Result<String> {
-    // TODO: byte / password here?
-    Ok(if let Some(f) = &value.format {
+fn extract_string_type(value: &JSONSchemaProps, cfg: &Context) -> String {
+    match value.format.as_deref() {
+        // NB: these need chrono feature on serde
+        Some("date-time") if cfg.chrono => "DateTime<Utc>".to_string(),
+        Some("date") if cfg.chrono => "NaiveDate".to_string(),
+        // base64 encoded data, which ByteString (de)serializes
+        Some("byte") => "ByteString".to_string(),
+        _ => "String".to_string(),
+    }
+}
+
+/// Date formats of a string member (or of its array items or map values) that were left as String
+fn unmapped_string_format<'a>(value: &'a JSONSchemaProps, cfg: &Context) -> Option<&'a str> {
+    let inner = match (&value.items, &value.additional_properties) {
+        (Some(JSONSchemaPropsOrArray::Schema(s)), _) => s.as_ref(),
+        (_, Some(JSONSchemaPropsOrBool::Schema(s))) => s.as_ref(),
+        _ => value,
+    };
+    if cfg.chrono || inner.type_.as_deref() != Some("string") {
+        return None;
+    }
+    inner
+        .format
+        .as_deref()
+        .filter(|f| ["date", "date-time"].contains(f))
+}
+
+fn extract_number_type(value: &JSONSchemaProps, path: &str, cfg: &Context) -> String {
+    if let Some(f) = &value.format {
         match f.as_ref() {
             "float" => "f32".to_string(),
             "double" => "f64".to_string(),
-            _ => "f64".to_string(),
+            x => {
+                cfg.warn(
+                    WarningKind::UnknownFormat,
+                    format!("unknown number format {} at {} - using f64", x, path),
+                );
+                "f64".to_string()
+            }
         }
     } else {
         "f64".to_string()
-    })
+    }
 }
 
-fn extract_integer_type(value: &JSONSchemaProps) -> Result<String> {
-    // Think kubernetes go types just do signed ints, but set a minimum to zero..
-    // rust will set uint, so emitting that when possible
-    Ok(if let Some(f) = &value.format {
+fn extract_integer_type(value: &JSONSchemaProps, path: &str, cfg: &Context) -> String {
+    // kubernetes go types are signed and at most 64 bit, so i64 is the safe default
+    // (a minimum of zero is common, but negative sentinels like -1 are used as well)
+    let unsigned = cfg.prefer_unsigned && value.minimum.is_some_and(|m| m >= 0.0);
+    if let Some(f) = &value.format {
         match f.as_ref() {
+            "int8" if unsigned => "u8".to_string(),
+            "int16" if unsigned => "u16".to_string(),
+            "int32" if unsigned => "u32".to_string(),
+            "int64" if unsigned => "u64".to_string(),
+            "int128" if unsigned => "u128".to_string(),
             "int8" => "i8".to_string(),
             "int16" => "i16".to_string(),
             "int32" => "i32".to_string(),
@@ -536,17 +2180,25 @@ fn extract_integer_type(value: &JSONSchemaProps) -> Result<String> {
             "uint32" => "u32".to_string(),
             "uint64" => "u64".to_string(),
             "uint128" => "u128".to_string(),
-            _ => "i64".to_string(),
+            x => {
+                cfg.warn(
+                    WarningKind::UnknownFormat,
+                    format!("unknown integer format {} at {} - using i64", x, path),
+                );
+                "i64".to_string()
+            }
         }
+    } else if unsigned {
+        "u64".to_string()
     } else {
         "i64".to_string()
-    })
+    }
 }
 
 // unit tests particular schema patterns
 #[cfg(test)]
 mod test {
-    use super::{analyze, Config as Cfg};
+    use super::{analyze, Config as Cfg, Output, WarningKind};
     use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::JSONSchemaProps;
 
     use std::sync::Once;
@@ -592,7 +2244,7 @@ mod test {
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
         //println!("schema: {}", serde_json::to_string_pretty(&schema).unwrap());
 
-        let structs = analyze(schema, "Agent", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "Agent", Cfg::default()).unwrap().containers;
         //println!("{:?}", structs);
         let root = &structs[0];
         assert_eq!(root.name, "Agent");
@@ -600,7 +2252,7 @@ mod test {
         // should have a member with a key to the map:
         let map = &root.members[0];
         assert_eq!(map.name, "validationsInfo");
-        assert_eq!(map.type_, "Option<BTreeMap<String, AgentValidationsInfo>>");
+        assert_eq!(map.type_, "Option<BTreeMap<String, Vec<AgentValidationsInfo>>>");
         // should have a separate struct
         let other = &structs[1];
         assert_eq!(other.name, "AgentValidationsInfo");
@@ -636,7 +2288,7 @@ type: object
 "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
         //println!("schema: {}", serde_json::to_string_pretty(&schema).unwrap());
-        let structs = analyze(schema, "Server", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "Server", Cfg::default()).unwrap().containers;
         //println!("{:#?}", structs);
 
         let root = &structs[0];
@@ -654,53 +2306,527 @@ type: object
     }
 
     #[test]
-    fn additional_preserve_unknown() {
+    fn records_warnings_for_inlined_maps() {
         init();
         let schema_str = r#"
-    description: MiddlewareSpec defines the desired state of a Middleware.
     properties:
-      plugin:
+      labels:
         additionalProperties:
-          x-kubernetes-preserve-unknown-fields: true
-        description: traefik middleware crd
+          type: string
+        type: object
+      annotations:
+        additionalProperties:
+          type: string
         type: object
     type: object"#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        println!("got {schema:?}");
+        let output = analyze(schema, "Spec", Cfg::default()).unwrap();
+        assert_eq!(output.containers.len(), 1);
+        assert_eq!(output.warnings, vec![
+            "not generating type Annotations at .annotations - using string map",
+            "not generating type Labels at .labels - using string map",
+        ]);
+    }
 
-        let structs = analyze(schema, "Spec", Cfg::default()).unwrap().0;
-        println!("got: {structs:?}");
-        let root = &structs[0];
-        assert_eq!(root.name, "Spec");
-        let member = &root.members[0];
-        assert_eq!(member.name, "plugin");
-        assert_eq!(member.type_, "Option<BTreeMap<String, serde_json::Value>>");
+    #[test]
+    fn errors_include_schema_path() {
+        init();
+        let schema_str = r#"
+    properties:
+      template:
+        properties:
+          containers:
+            items:
+              properties:
+                resources:
+                  properties:
+                    limits:
+                      type: object
+                      additionalProperties:
+                        description: neither typed nor preserved
+                  type: object
+              type: object
+            type: array
+        type: object
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let err = analyze(schema, "Spec", Cfg::default()).err().unwrap().to_string();
+        assert_eq!(
+            err,
+            "unknown empty dict type for limits at .template.containers[].resources.limits.*"
+        );
     }
 
     #[test]
-    fn no_type_preserve_unknown_fields() {
+    fn relaxed_falls_back_to_value() {
         init();
         let schema_str = r#"
-description: Schema defines the schema of the variable.
-properties:
-  openAPIV3Schema:
-    description: |-
-      OpenAPIV3Schema defines the schema of a variable via OpenAPI v3
-      schema. The schema is a subset of the schema used in
-      Kubernetes CRDs.
     properties:
-      items:
-        description: |-
-          Items specifies fields of an array.
-          NOTE: Can only be set if type is array.
-          NOTE: This field uses PreserveUnknownFields and Schemaless,
-          because recursive validation is not possible.
-        x-kubernetes-preserve-unknown-fields: true
-      requiredItems:
-        description: |-
-          Items specifies fields of an array.
-          NOTE: Can only be set if type is array.
-          NOTE: This field uses PreserveUnknownFields and Schemaless,
+      args:
+        type: array
+      limits:
+        type: object
+        additionalProperties:
+          description: untyped
+      weird:
+        type: sometype
+    required:
+    - weird
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        assert!(analyze(schema.clone(), "Spec", Cfg::default()).is_err());
+
+        let cfg = Cfg {
+            relaxed: true,
+            ..Cfg::default()
+        };
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Spec", cfg).unwrap();
+        assert_eq!(structs.len(), 1);
+        let root = &structs[0];
+        assert_eq!(root.members[0].name, "args");
+        assert_eq!(root.members[0].type_, "Option<Vec<serde_json::Value>>");
+        assert_eq!(root.members[1].name, "limits");
+        assert_eq!(
+            root.members[1].type_,
+            "Option<BTreeMap<String, serde_json::Value>>"
+        );
+        assert_eq!(root.members[2].name, "weird");
+        assert_eq!(root.members[2].type_, "serde_json::Value");
+        assert_eq!(warnings, vec![
+            "missing items in array at .args - using serde_json::Value",
+            "unknown empty dict type at .limits.* - using serde_json::Value",
+            "unknown type sometype at .weird - using serde_json::Value",
+        ]);
+    }
+
+    #[test]
+    fn nested_arrays() {
+        init();
+        let schema_str = r#"
+    properties:
+      cells:
+        items:
+          items:
+            items:
+              properties:
+                value:
+                  type: string
+              type: object
+            type: array
+          type: array
+        type: array
+      cube:
+        items:
+          items:
+            items:
+              format: int32
+              type: integer
+            type: array
+          type: array
+        type: array
+      grid:
+        items:
+          items:
+            properties:
+              state:
+                enum: [On, Off]
+                type: string
+            required:
+            - state
+            type: object
+          type: array
+        type: array
+      matrix:
+        items:
+          items:
+            type: string
+          type: array
+        type: array
+      tables:
+        additionalProperties:
+          items:
+            items:
+              properties:
+                weight:
+                  type: number
+              type: object
+            type: array
+          type: array
+        type: object
+    required:
+    - matrix
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Board", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, [
+            "Board",
+            "BoardCell",
+            "BoardGrid",
+            "BoardGridState",
+            "BoardTable"
+        ]);
+        let types = structs[0]
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.type_.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, [
+            ("cells", "Option<Vec<Vec<Vec<BoardCell>>>>"),
+            ("cube", "Option<Vec<Vec<Vec<i32>>>>"),
+            ("grid", "Option<Vec<Vec<BoardGrid>>>"),
+            ("matrix", "Vec<Vec<String>>"),
+            ("tables", "Option<BTreeMap<String, Vec<Vec<BoardTable>>>>"),
+        ]);
+        // structs come from the innermost items
+        assert_eq!(structs[1].members[0].type_, "Option<String>");
+        assert_eq!(structs[2].members[0].type_, "BoardGridState");
+        assert_eq!(structs[4].members[0].type_, "Option<f64>");
+    }
+
+    #[test]
+    fn nullable_items_are_optional() {
+        init();
+        let schema_str = r#"
+    properties:
+      args:
+        items:
+          nullable: true
+          type: string
+        type: array
+      hooks:
+        items:
+          nullable: true
+          properties:
+            url:
+              type: string
+          type: object
+        type: array
+      matrix:
+        items:
+          items:
+            format: int32
+            nullable: true
+            type: integer
+          type: array
+        type: array
+      rows:
+        items:
+          items:
+            type: string
+          nullable: true
+          type: array
+        type: array
+      values:
+        items:
+          nullable: true
+          x-kubernetes-preserve-unknown-fields: true
+        type: array
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Job", Cfg::default()).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<Vec<Option<String>>>");
+        assert_eq!(root.members[1].type_, "Option<Vec<Option<JobHook>>>");
+        assert_eq!(structs[1].name, "JobHook");
+        assert_eq!(structs[1].members[0].type_, "Option<String>");
+        // at any depth
+        assert_eq!(root.members[2].type_, "Option<Vec<Vec<Option<i32>>>>");
+        assert_eq!(root.members[3].type_, "Option<Vec<Option<Vec<String>>>>");
+        // maps of unknown fields are no exception
+        assert_eq!(
+            root.members[4].type_,
+            "Option<Vec<Option<BTreeMap<String, serde_json::Value>>>>"
+        );
+    }
+
+    #[test]
+    fn sets_of_unique_scalars() {
+        init();
+        let schema_str = r#"
+    properties:
+      tags:
+        items:
+          type: string
+        type: array
+        x-kubernetes-list-type: set
+      ports:
+        items:
+          format: int32
+          type: integer
+        type: array
+        uniqueItems: true
+      ratios:
+        items:
+          type: number
+        type: array
+        x-kubernetes-list-type: set
+      owners:
+        items:
+          properties:
+            name:
+              type: string
+          type: object
+        type: array
+        x-kubernetes-list-type: set
+      groups:
+        items:
+          items:
+            type: string
+          type: array
+          x-kubernetes-list-type: set
+        type: array
+      names:
+        items:
+          type: string
+        type: array
+        x-kubernetes-list-type: atomic
+    required:
+    - tags
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let types = |cfg| {
+            let structs = analyze(schema.clone(), "Team", cfg).unwrap().containers;
+            structs[0]
+                .members
+                .iter()
+                .map(|m| (m.name.clone(), m.type_.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            types(Cfg {
+                sets: true,
+                ..Cfg::default()
+            }),
+            [
+                ("groups".to_string(), "Option<Vec<BTreeSet<String>>>".to_string()),
+                ("names".to_string(), "Option<Vec<String>>".to_string()),
+                // structs are not ordered
+                ("owners".to_string(), "Option<Vec<TeamOwner>>".to_string()),
+                ("ports".to_string(), "Option<BTreeSet<i32>>".to_string()),
+                // neither are floats
+                ("ratios".to_string(), "Option<Vec<f64>>".to_string()),
+                ("tags".to_string(), "BTreeSet<String>".to_string()),
+            ]
+        );
+        assert!(types(Cfg::default()).iter().all(|(_, t)| !t.contains("BTreeSet")));
+    }
+
+    #[test]
+    fn schemas_are_kept_where_types_lose_them() {
+        init();
+        let schema_str = r#"
+    properties:
+      config:
+        type: object
+        x-kubernetes-preserve-unknown-fields: true
+      port:
+        x-kubernetes-int-or-string: true
+      replicas:
+        type: integer
+      selector:
+        type: object
+        properties:
+          app:
+            type: string
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let kept = |cfg| {
+            let structs = analyze(schema.clone(), "Collector", cfg).unwrap().containers;
+            let root = structs.into_iter().find(|c| c.is_root()).unwrap();
+            root.members
+                .into_iter()
+                .filter_map(|m| Some((m.name, m.schema?)))
+                .collect::<Vec<_>>()
+        };
+        let config = serde_json::json!({ "type": "object", "x-kubernetes-preserve-unknown-fields": true });
+        assert_eq!(
+            kept(Cfg {
+                schemas: true,
+                ..Cfg::default()
+            }),
+            [("config".to_string(), config.clone())]
+        );
+        // int-or-string values only lose their schema when coerced to strings
+        assert_eq!(
+            kept(Cfg {
+                schemas: true,
+                coerce_int_or_string: true,
+                ..Cfg::default()
+            }),
+            [
+                ("config".to_string(), config),
+                (
+                    "port".to_string(),
+                    serde_json::json!({ "x-kubernetes-int-or-string": true })
+                ),
+            ]
+        );
+        assert!(kept(Cfg::default()).is_empty());
+    }
+
+    #[test]
+    fn tuple_items() {
+        init();
+        let schema_str = r#"
+    properties:
+      flag:
+        items:
+        - type: boolean
+        type: array
+      mixed:
+        items:
+        - type: string
+        - properties:
+            name:
+              type: string
+          type: object
+        type: array
+      range:
+        items:
+        - type: string
+        - format: int32
+          type: integer
+        - type: integer
+        type: array
+    required:
+    - range
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Spec", Cfg::default()).unwrap();
+        // no struct for the object in the mixed tuple
+        assert_eq!(structs.len(), 1);
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<(bool,)>");
+        assert_eq!(root.members[1].type_, "Option<Vec<serde_json::Value>>");
+        assert_eq!(root.members[2].type_, "(String, i32, i64)");
+        assert_eq!(warnings, [
+            "tuple items with non-scalar schemas at .mixed - using serde_json::Value"
+        ]);
+    }
+
+    #[test]
+    fn free_form_arrays() {
+        init();
+        let schema_str = r#"
+    properties:
+      args:
+        type: array
+        x-kubernetes-preserve-unknown-fields: true
+      steps:
+        type: array
+        x-kubernetes-preserve-unknown-fields: true
+    required:
+    - steps
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Spec", Cfg::default()).unwrap();
+        assert_eq!(structs.len(), 1);
+        let root = &structs[0];
+        assert_eq!(root.members[0].name, "args");
+        assert_eq!(root.members[0].type_, "Option<Vec<serde_json::Value>>");
+        assert_eq!(root.members[0].serde_annot, vec![
+            "default",
+            "skip_serializing_if = \"Option::is_none\""
+        ]);
+        assert_eq!(root.members[1].name, "steps");
+        assert_eq!(root.members[1].type_, "Vec<serde_json::Value>");
+        assert!(root.members[1].serde_annot.is_empty());
+        assert!(warnings.is_empty());
+
+        // without the extension the items are still required
+        let schema_str = r#"
+    properties:
+      spec:
+        properties:
+          args:
+            type: array
+        type: object
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let err = analyze(schema, "Job", Cfg::default()).err().unwrap();
+        assert_eq!(err.to_string(), "missing items in array type at .spec.args");
+    }
+
+    #[test]
+    fn date_without_format_warns() {
+        init();
+        let schema_str = r#"
+    properties:
+      since:
+        format: date
+        type: date
+      until:
+        type: date
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Spec", Cfg::default()).unwrap();
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<NaiveDate>");
+        assert_eq!(root.members[1].type_, "Option<String>");
+        assert_eq!(warnings, vec!["date without format at .until - using String"]);
+    }
+
+    #[test]
+    fn additional_preserve_unknown() {
+        init();
+        let schema_str = r#"
+    description: MiddlewareSpec defines the desired state of a Middleware.
+    properties:
+      plugin:
+        additionalProperties:
+          x-kubernetes-preserve-unknown-fields: true
+        description: traefik middleware crd
+        type: object
+    type: object"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        println!("got {schema:?}");
+
+        let structs = analyze(schema, "Spec", Cfg::default()).unwrap().containers;
+        println!("got: {structs:?}");
+        let root = &structs[0];
+        assert_eq!(root.name, "Spec");
+        let member = &root.members[0];
+        assert_eq!(member.name, "plugin");
+        assert_eq!(member.type_, "Option<BTreeMap<String, serde_json::Value>>");
+    }
+
+    #[test]
+    fn no_type_preserve_unknown_fields() {
+        init();
+        let schema_str = r#"
+description: Schema defines the schema of the variable.
+properties:
+  openAPIV3Schema:
+    description: |-
+      OpenAPIV3Schema defines the schema of a variable via OpenAPI v3
+      schema. The schema is a subset of the schema used in
+      Kubernetes CRDs.
+    properties:
+      items:
+        description: |-
+          Items specifies fields of an array.
+          NOTE: Can only be set if type is array.
+          NOTE: This field uses PreserveUnknownFields and Schemaless,
+          because recursive validation is not possible.
+        x-kubernetes-preserve-unknown-fields: true
+      requiredItems:
+        description: |-
+          Items specifies fields of an array.
+          NOTE: Can only be set if type is array.
+          NOTE: This field uses PreserveUnknownFields and Schemaless,
           because recursive validation is not possible.
         x-kubernetes-preserve-unknown-fields: true
     required:
@@ -712,7 +2838,7 @@ type: object
 "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
         // println!("schema: {}", serde_json::to_string_pretty(&schema).unwrap());
-        let structs = analyze(schema, "Variables", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "Variables", Cfg::default()).unwrap().containers;
         // println!("{:#?}", structs);
 
         let root = &structs[0];
@@ -733,299 +2859,2492 @@ type: object
     }
 
     #[test]
-    fn int_or_string() {
+    fn nested_preserve_unknown_fields() {
         init();
         let schema_str = r#"
-            properties:
-              port:
-                description: A port name or number. Must exist in a pod spec.
-                x-kubernetes-int-or-string: true
-            required:
-            - port
+        properties:
+          helmValues:
+            additionalProperties:
+              type: object
+              x-kubernetes-preserve-unknown-fields: true
+            type: object
+          manifests:
+            items:
+              type: object
+              x-kubernetes-preserve-unknown-fields: true
+            type: array
+          patches:
+            additionalProperties:
+              items:
+                x-kubernetes-preserve-unknown-fields: true
+              type: array
+            type: object
+          resources:
+            additionalProperties:
+              items:
+                type: object
+                x-kubernetes-preserve-unknown-fields: true
+              type: array
             type: object
+        type: object
 "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-
-        let structs = analyze(schema, "Server", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "Release", Cfg::default()).unwrap().containers;
+        // nothing to generate beyond the root
+        assert_eq!(structs.len(), 1);
         let root = &structs[0];
-        assert_eq!(root.name, "Server");
-        // should have an IntOrString member:
-        let member = &root.members[0];
-        assert_eq!(member.name, "port");
-        assert_eq!(member.type_, "IntOrString");
-        assert!(root.uses_int_or_string());
-        // TODO: check that anyOf: [type: integer, type: string] also works
+        assert_eq!(
+            root.members[0].type_,
+            "Option<BTreeMap<String, BTreeMap<String, serde_json::Value>>>"
+        );
+        assert_eq!(
+            root.members[1].type_,
+            "Option<Vec<BTreeMap<String, serde_json::Value>>>"
+        );
+        assert_eq!(
+            root.members[2].type_,
+            "Option<BTreeMap<String, Vec<BTreeMap<String, serde_json::Value>>>>"
+        );
+        assert_eq!(
+            root.members[3].type_,
+            "Option<BTreeMap<String, Vec<BTreeMap<String, serde_json::Value>>>>"
+        );
     }
 
     #[test]
-    fn boolean_in_additionals() {
-        // as found in argo-app
+    fn preserve_unknown_fields_with_properties() {
         init();
         let schema_str = r#"
+        properties:
+          spec:
             properties:
-              options:
-                additionalProperties:
-                  type: boolean
-                type: object
-              patch:
+              name:
                 type: string
+              vendor:
+                properties:
+                  extra:
+                    type: boolean
+                type: object
+                x-kubernetes-preserve-unknown-fields: true
             type: object
+            x-kubernetes-preserve-unknown-fields: true
+        type: object
 "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        let structs = analyze(schema, "Options", Cfg::default()).unwrap().0;
-        println!("got {:?}", structs);
-        let root = &structs[0];
-        assert_eq!(root.name, "Options");
-        assert_eq!(root.level, 0);
-        assert_eq!(&root.members[0].name, "options");
-        assert_eq!(&root.members[0].type_, "Option<BTreeMap<String, bool>>");
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Plugin", Cfg::default()).unwrap();
+        let spec = &structs[1];
+        assert_eq!(spec.members.len(), 3);
+        assert_eq!(spec.members[2].name, "extra");
+        assert_eq!(spec.members[2].type_, "BTreeMap<String, serde_json::Value>");
+        assert_eq!(spec.members[2].serde_annot, ["flatten"]);
+
+        // the catch-all moves out of the way of a real property
+        let vendor = &structs[2];
+        assert_eq!(vendor.members[0].name, "extra");
+        assert_eq!(vendor.members[1].name, "extra_");
+        assert_eq!(vendor.members[1].serde_annot, ["flatten"]);
+        assert_eq!(warnings, [
+            "property extra at .spec.vendor clashes with the field for unknown fields - using extra_"
+        ]);
+    }
+
+    #[test]
+    fn embedded_resources() {
+        init();
+        let schema_str = r#"
+        properties:
+          byName:
+            additionalProperties:
+              properties:
+                apiVersion:
+                  type: string
+                kind:
+                  type: string
+              type: object
+              x-kubernetes-embedded-resource: true
+              x-kubernetes-preserve-unknown-fields: true
+            type: object
+          resources:
+            items:
+              type: object
+              x-kubernetes-embedded-resource: true
+              x-kubernetes-preserve-unknown-fields: true
+            type: array
+          template:
+            properties:
+              apiVersion:
+                type: string
+              kind:
+                type: string
+              metadata:
+                type: object
+            type: object
+            x-kubernetes-embedded-resource: true
+            x-kubernetes-preserve-unknown-fields: true
+        required:
+        - template
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema.clone(), "Bundle", Cfg::default())
+            .unwrap()
+            .containers;
+        // no structs for the embedded objects
+        assert_eq!(structs.len(), 1);
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<BTreeMap<String, RawExtension>>");
+        assert_eq!(root.members[1].type_, "Option<Vec<RawExtension>>");
+        assert_eq!(root.members[2].type_, "RawExtension");
+
+        let cfg = Cfg {
+            embedded: crate::EmbeddedType::DynamicObject,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema, "Bundle", cfg).unwrap().containers;
+        assert_eq!(structs[0].members[1].type_, "Option<Vec<DynamicObject>>");
+        assert_eq!(structs[0].members[2].type_, "DynamicObject");
+    }
+
+    #[test]
+    fn boolean_additional_properties() {
+        init();
+        let schema_str = r#"
+        properties:
+          closed:
+            additionalProperties: false
+            properties:
+              name:
+                type: string
+            type: object
+          labels:
+            additionalProperties: true
+            type: object
+          selectors:
+            items:
+              additionalProperties: true
+              type: object
+            type: array
+          settings:
+            additionalProperties: true
+            properties:
+              debug:
+                type: boolean
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Agent", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Agent", "AgentClosed", "AgentSettings"]);
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<AgentClosed>");
+        assert_eq!(
+            root.members[1].type_,
+            "Option<BTreeMap<String, serde_json::Value>>"
+        );
+        assert_eq!(
+            root.members[2].type_,
+            "Option<Vec<BTreeMap<String, serde_json::Value>>>"
+        );
+        assert_eq!(root.members[3].type_, "Option<AgentSettings>");
+
+        // false leaves the declared properties only
+        assert_eq!(structs[1].members.len(), 1);
+
+        // true next to declared properties keeps the rest in a catch-all
+        let settings = &structs[2];
+        assert_eq!(settings.members[0].type_, "Option<bool>");
+        assert_eq!(settings.members[1].name, "extra");
+        assert_eq!(settings.members[1].type_, "BTreeMap<String, serde_json::Value>");
+        assert_eq!(settings.members[1].serde_annot, ["flatten"]);
+    }
+
+    #[test]
+    fn int_or_string() {
+        init();
+        let schema_str = r#"
+            properties:
+              port:
+                description: A port name or number. Must exist in a pod spec.
+                x-kubernetes-int-or-string: true
+            required:
+            - port
+            type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+
+        let structs = analyze(schema, "Server", Cfg::default()).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.name, "Server");
+        // should have an IntOrString member:
+        let member = &root.members[0];
+        assert_eq!(member.name, "port");
+        assert_eq!(member.type_, "IntOrString");
+        assert!(root.uses_int_or_string());
+    }
+
+    #[test]
+    fn int_or_string_any_of() {
+        init();
+        let schema_str = r#"
+            properties:
+              maxSurge:
+                anyOf:
+                - type: string
+                - format: int32
+                  type: integer
+              ports:
+                items:
+                  anyOf:
+                  - type: integer
+                  - type: string
+                type: array
+              limits:
+                additionalProperties:
+                  anyOf:
+                  - type: integer
+                  - pattern: ^[0-9]+m?$
+                    type: string
+                type: object
+              notIntOrString:
+                anyOf:
+                - type: integer
+                - type: boolean
+            type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let cfg = Cfg {
+            relaxed: true,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema.clone(), "Rollout", cfg).unwrap().containers;
+        let root = &structs[0];
+        let member = |name: &str| {
+            root.members
+                .iter()
+                .find(|m| m.name == name)
+                .unwrap()
+                .type_
+                .clone()
+        };
+        assert_eq!(member("maxSurge"), "Option<IntOrString>");
+        assert_eq!(member("ports"), "Option<Vec<IntOrString>>");
+        assert_eq!(member("limits"), "Option<BTreeMap<String, IntOrString>>");
+        assert!(root.uses_int_or_string());
+
+        // other anyOf combinations are not int-or-string
+        assert_eq!(
+            member("notIntOrString"),
+            "Option<BTreeMap<String, serde_json::Value>>"
+        );
+        let err = analyze(schema.clone(), "Rollout", Cfg::default()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown empty dict type for notIntOrString at .notIntOrString"
+        );
+
+        let cfg = Cfg {
+            relaxed: true,
+            coerce_int_or_string: true,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema, "Rollout", cfg).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<BTreeMap<String, String>>");
+        assert_eq!(root.members[1].type_, "Option<String>");
+        assert_eq!(root.members[3].type_, "Option<Vec<String>>");
+        assert!(!root.uses_int_or_string());
+    }
+
+    #[test]
+    fn boolean_in_additionals() {
+        // as found in argo-app
+        init();
+        let schema_str = r#"
+            properties:
+              options:
+                additionalProperties:
+                  type: boolean
+                type: object
+              patch:
+                type: string
+            type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Options", Cfg::default()).unwrap().containers;
+        println!("got {:?}", structs);
+        let root = &structs[0];
+        assert_eq!(root.name, "Options");
+        assert_eq!(root.level, 0);
+        assert_eq!(&root.members[0].name, "options");
+        assert_eq!(&root.members[0].type_, "Option<BTreeMap<String, bool>>");
+    }
+
+    #[test]
+    fn enum_string() {
+        init();
+        let schema_str = r#"
+      properties:
+        operator:
+          enum:
+          - In
+          - NotIn
+          - Exists
+          - DoesNotExist
+          type: string
+      required:
+      - operator
+      type: object
+"#;
+
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "MatchExpressions", Cfg::default())
+            .unwrap()
+            .containers;
+        println!("got {:?}", structs);
+        let root = &structs[0];
+        assert_eq!(root.name, "MatchExpressions");
+        assert_eq!(root.level, 0);
+        assert_eq!(&root.members[0].name, "operator");
+        assert_eq!(&root.members[0].type_, "MatchExpressionsOperator");
+
+        // operator member
+        let op = &structs[1];
+        assert!(op.is_enum);
+        assert_eq!(op.name, "MatchExpressionsOperator");
+
+        // should have enum members:
+        assert_eq!(&op.members[0].name, "In");
+        assert_eq!(&op.members[0].type_, "");
+        assert_eq!(&op.members[1].name, "NotIn");
+        assert_eq!(&op.members[1].type_, "");
+        assert_eq!(&op.members[2].name, "Exists");
+        assert_eq!(&op.members[2].type_, "");
+        assert_eq!(&op.members[3].name, "DoesNotExist");
+        assert_eq!(&op.members[3].type_, "");
+    }
+
+    #[test]
+    fn enum_string_within_container() {
+        init();
+        let schema_str = r#"
+      description: Endpoint
+      properties:
+        relabelings:
+          items:
+            properties:
+              action:
+                default: replace
+                enum:
+                - replace
+                - keep
+                - drop
+                - hashmod
+                - labelmap
+                - labeldrop
+                - labelkeep
+                type: string
+              modulus:
+                format: int64
+                type: integer
+            type: object
+          type: array
+      type: object
+        "#;
+
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Endpoint", Cfg::default()).unwrap().containers;
+        println!("got {:?}", structs);
+        let root = &structs[0];
+        assert_eq!(root.name, "Endpoint");
+        assert_eq!(root.level, 0);
+        assert!(!root.is_enum);
+        assert_eq!(&root.members[0].name, "relabelings");
+        assert_eq!(&root.members[0].type_, "Option<Vec<EndpointRelabeling>>");
+
+        let rel = &structs[1];
+        assert_eq!(rel.name, "EndpointRelabeling");
+        assert!(!rel.is_enum);
+        assert_eq!(&rel.members[0].name, "action");
+        assert_eq!(&rel.members[0].type_, "Option<EndpointRelabelingAction>");
+        // TODO: verify rel.members[0].field_annot uses correct default
+
+        // action enum member
+        let act = &structs[2];
+        assert_eq!(act.name, "EndpointRelabelingAction");
+        assert!(act.is_enum);
+
+        // should have enum members:
+        assert_eq!(&act.members[0].name, "replace");
+        assert_eq!(&act.members[0].type_, "");
+        assert_eq!(&act.members[1].name, "keep");
+        assert_eq!(&act.members[1].type_, "");
+        assert_eq!(&act.members[2].name, "drop");
+        assert_eq!(&act.members[2].type_, "");
+        assert_eq!(&act.members[3].name, "hashmod");
+        assert_eq!(&act.members[3].type_, "");
+    }
+
+    #[test]
+    #[ignore] // oneof support not done
+    fn enum_oneof() {
+        init();
+        let schema_str = r#"
+    description: "Auto-generated derived type for ServerSpec via `CustomResource`"
+    properties:
+      spec:
+        properties:
+          podSelector:
+            oneOf:
+              - required:
+                  - matchExpressions
+              - required:
+                  - matchLabels
+            properties:
+              matchExpressions:
+                items:
+                  properties:
+                    key:
+                      type: string
+                    operator:
+                      enum:
+                        - In
+                        - NotIn
+                        - Exists
+                        - DoesNotExists
+                      type: string
+                    values:
+                      items:
+                        type: string
+                      nullable: true
+                      type: array
+                  required:
+                    - key
+                    - operator
+                  type: object
+                type: array
+              matchLabels:
+                additionalProperties:
+                  type: string
+                type: object
+            type: object
+        required:
+          - podSelector
+        type: object
+    required:
+      - spec
+    title: Server
+    type: object"#;
+
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "ServerSpec", Cfg::default()).unwrap().containers;
+        println!("got {:?}", structs);
+        let root = &structs[0];
+        assert_eq!(root.name, "ServerSpec");
+        assert_eq!(root.level, 0);
+
+        // should have a required selector
+        let member = &root.members[0];
+        assert_eq!(member.name, "pod_selector");
+        assert_eq!(member.type_, "ServerPodSelector");
+
+        // and this should be an enum
+        let ps = &structs[1]; // TODO: encode as struct?
+        assert_eq!(ps.name, "ServerPodSelector");
+        assert_eq!(ps.level, 1);
+
+        // should have enum members: TODO: encode inner type as type_?
+        assert_eq!(&ps.members[0].name, "MatchExpressions");
+        assert_eq!(&ps.members[0].type_, "Vec<ServerPodSelectorMatchExpressions");
+        assert_eq!(&ps.members[1].name, "MatchLabels");
+        assert_eq!(&ps.members[1].type_, "BTreeMap<String, String>");
+
+        // should have the inner struct match expressions
+        let me = &structs[2];
+        assert_eq!(me.name, "ServerPodSelectorMatchExpressions");
+        assert_eq!(me.level, 2);
+
+        // which should have 3 members
+        assert_eq!(&me.members[0].name, "key");
+        assert_eq!(&me.members[0].type_, "String");
+        assert_eq!(&me.members[1].name, "operator");
+        assert_eq!(&me.members[1].type_, "ServerPodSelectorMatchExpressionsOperator");
+        assert_eq!(&me.members[2].name, "values");
+        assert_eq!(&me.members[2].type_, " Option<Vec<String>>");
+
+        // last struct being the innermost enum operator:
+        let op = &structs[3];
+        assert_eq!(op.name, "ServerPodSelectorMatchExpressionsOperator");
+        assert_eq!(op.level, 3);
+
+        // with enum members:
+        assert_eq!(&op.members[0].name, "In");
+        assert_eq!(&op.members[1].name, "In");
+        assert_eq!(&op.members[2].name, "In");
+        assert_eq!(&op.members[3].name, "In");
+    }
+
+    #[test]
+    fn service_monitor_params() {
+        init();
+        let schema_str = r#"
+        properties:
+          endpoints:
+            items:
+              description: Endpoint defines a scrapeable endpoint serving Prometheus
+                metrics.
+              properties:
+                params:
+                  additionalProperties:
+                    items:
+                      type: string
+                    type: array
+                  description: Optional HTTP URL parameters
+                  type: object
+              type: object
+            type: array
+        required:
+        - endpoints
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "ServiceMonitor", Cfg::default())
+            .unwrap()
+            .containers;
+        println!("got {:?}", structs);
+        let root = &structs[0];
+        assert_eq!(root.name, "ServiceMonitor");
+        assert_eq!(root.level, 0);
+
+        // should have a required endpoints member
+        let member = &root.members[0];
+        assert_eq!(member.name, "endpoints");
+        assert_eq!(member.type_, "Vec<ServiceMonitorEndpoint>");
+
+        // Should have a endpoints struct:
+        let eps = &structs[1];
+        assert_eq!(eps.name, "ServiceMonitorEndpoint");
+        assert_eq!(eps.level, 1);
+        // should have an params member:
+        let member = &eps.members[0];
+        assert_eq!(member.name, "params");
+        assert_eq!(member.type_, "Option<BTreeMap<String, Vec<String>>>");
+    }
+
+    #[test]
+    fn maps_of_arrays() {
+        init();
+        let schema_str = r#"
+        properties:
+          aliases:
+            additionalProperties:
+              items:
+                type: string
+              type: array
+            type: object
+          routes:
+            additionalProperties:
+              items:
+                properties:
+                  backend:
+                    properties:
+                      port:
+                        format: int32
+                        type: integer
+                    type: object
+                  path:
+                    type: string
+                required:
+                - path
+                type: object
+              type: array
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Gateway", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Gateway", "GatewayRoute", "GatewayRouteBackend"]);
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<BTreeMap<String, Vec<String>>>");
+        assert_eq!(
+            root.members[1].type_,
+            "Option<BTreeMap<String, Vec<GatewayRoute>>>"
+        );
+
+        // item structs are generated, including their own nested structs
+        let routes = &structs[1];
+        assert_eq!(routes.members[0].type_, "Option<GatewayRouteBackend>");
+        assert_eq!(routes.members[1].type_, "String");
+    }
+
+    #[test]
+    fn arrays_of_maps() {
+        init();
+        // lists of match-label sets, as used by placement and selector specs
+        let schema_str = r#"
+        properties:
+          clusterSelectors:
+            description: Clusters matching any of the label sets are selected
+            items:
+              additionalProperties:
+                type: string
+              type: object
+            type: array
+          extras:
+            items:
+              additionalProperties:
+                x-kubernetes-preserve-unknown-fields: true
+              type: object
+            type: array
+          overrides:
+            items:
+              additionalProperties:
+                properties:
+                  op:
+                    enum: [Add, Remove]
+                    type: string
+                  value:
+                    type: string
+                required: [op]
+                type: object
+              type: object
+            type: array
+          weights:
+            items:
+              additionalProperties:
+                format: int32
+                type: integer
+              type: object
+            type: array
+        required: [clusterSelectors]
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "PlacementSpec", Cfg::default())
+            .unwrap()
+            .containers;
+        // no structs for the scalar maps, only for the object values of overrides
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, [
+            "PlacementSpec",
+            "PlacementSpecOverride",
+            "PlacementSpecOverrideOp"
+        ]);
+        let types = structs[0]
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.type_.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, [
+            ("clusterSelectors", "Vec<BTreeMap<String, String>>"),
+            ("extras", "Option<Vec<BTreeMap<String, serde_json::Value>>>"),
+            (
+                "overrides",
+                "Option<Vec<BTreeMap<String, PlacementSpecOverride>>>"
+            ),
+            ("weights", "Option<Vec<BTreeMap<String, i32>>>"),
+        ]);
+        assert_eq!(structs[1].members[0].type_, "PlacementSpecOverrideOp");
+    }
+
+    #[test]
+    fn nested_maps() {
+        init();
+        let schema_str = r#"
+        properties:
+          labels:
+            additionalProperties:
+              additionalProperties:
+                additionalProperties:
+                  type: string
+                type: object
+              type: object
+            type: object
+          metadata:
+            additionalProperties:
+              additionalProperties:
+                additionalProperties:
+                  x-kubernetes-preserve-unknown-fields: true
+                  type: object
+                type: object
+              type: object
+            type: object
+          rules:
+            additionalProperties:
+              additionalProperties:
+                additionalProperties:
+                  properties:
+                    weight:
+                      format: int32
+                      type: integer
+                  type: object
+                type: object
+              type: object
+            type: object
+          selectors:
+            items:
+              additionalProperties:
+                additionalProperties:
+                  type: string
+                type: object
+              type: object
+            type: array
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Policy", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Policy", "PolicyRule"]);
+        let types = structs[0]
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.type_.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, [
+            (
+                "labels",
+                "Option<BTreeMap<String, BTreeMap<String, BTreeMap<String, String>>>>"
+            ),
+            (
+                "metadata",
+                "Option<BTreeMap<String, BTreeMap<String, BTreeMap<String, BTreeMap<String, serde_json::Value>>>>>"
+            ),
+            (
+                "rules",
+                "Option<BTreeMap<String, BTreeMap<String, BTreeMap<String, PolicyRule>>>>"
+            ),
+            (
+                "selectors",
+                "Option<Vec<BTreeMap<String, BTreeMap<String, String>>>>"
+            ),
+        ]);
+        assert_eq!(structs[1].members[0].type_, "Option<i32>");
+    }
+
+    #[test]
+    fn scalar_map_values() {
+        init();
+        let schema_str = r#"
+        properties:
+          counts:
+            additionalProperties:
+              format: int32
+              type: integer
+            type: object
+          enabled:
+            additionalProperties:
+              type: boolean
+            type: object
+          limits:
+            additionalProperties:
+              type: integer
+            type: object
+          sizes:
+            additionalProperties:
+              format: int64
+              type: integer
+            type: object
+          weights:
+            additionalProperties:
+              type: number
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Quota", Cfg::default()).unwrap().containers;
+        assert_eq!(structs.len(), 1);
+        let types = structs[0]
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.type_.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, [
+            ("counts", "Option<BTreeMap<String, i32>>"),
+            ("enabled", "Option<BTreeMap<String, bool>>"),
+            ("limits", "Option<BTreeMap<String, i64>>"),
+            ("sizes", "Option<BTreeMap<String, i64>>"),
+            ("weights", "Option<BTreeMap<String, f64>>"),
+        ]);
+
+        // unknown value types are not guessed at
+        let schema_str = r#"
+        properties:
+          things:
+            additionalProperties:
+              type: thing
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let err = analyze(schema.clone(), "Quota", Cfg::default()).err().unwrap();
+        assert_eq!(err.to_string(), "unknown dict type thing for things at .things.*");
+        let cfg = Cfg {
+            relaxed: true,
+            ..Cfg::default()
+        };
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Quota", cfg).unwrap();
+        assert_eq!(
+            structs[0].members[0].type_,
+            "Option<BTreeMap<String, serde_json::Value>>"
+        );
+        assert_eq!(warnings, [
+            "unknown dict type thing at .things.* - using serde_json::Value",
+            "not generating type Things at .things - using thing map",
+        ]);
+    }
+
+    #[test]
+    fn number_handling() {
+        init();
+        let schema_str = r#"
+        properties:
+          replicasPercentage:
+            type: number
+          ratio:
+            format: float
+            type: number
+          weights:
+            items:
+              type: number
+            type: array
+          smallWeights:
+            items:
+              format: float
+              type: number
+            type: array
+          scores:
+            additionalProperties:
+              type: number
+            type: object
+          ranges:
+            additionalProperties:
+              items:
+                format: float
+                type: number
+              type: array
+            type: object
+        required:
+        - replicasPercentage
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Rollout", Cfg::default()).unwrap().containers;
+        let root = &structs[0];
+        let types = root
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.type_.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![
+            ("ranges", "Option<BTreeMap<String, Vec<f32>>>"),
+            ("ratio", "Option<f32>"),
+            ("replicasPercentage", "f64"),
+            ("scores", "Option<BTreeMap<String, f64>>"),
+            ("smallWeights", "Option<Vec<f32>>"),
+            ("weights", "Option<Vec<f64>>"),
+        ]);
+    }
+
+    #[test]
+    fn number_formats() {
+        init();
+        let schema_str = r#"
+        properties:
+          double:
+            format: double
+            type: number
+          float:
+            format: float
+            type: number
+          plain:
+            type: number
+          unknown:
+            format: decimal
+            type: number
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Metric", Cfg::default()).unwrap();
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<f64>");
+        assert_eq!(root.members[1].type_, "Option<f32>");
+        assert_eq!(root.members[2].type_, "Option<f64>");
+        assert_eq!(root.members[3].type_, "Option<f64>");
+        assert_eq!(warnings, vec![
+            "unknown number format decimal at .unknown - using f64"
+        ]);
+    }
+
+    #[test]
+    fn integer_formats() {
+        init();
+        let schema_str = r#"
+        properties:
+          generation:
+            type: integer
+          ports:
+            items:
+              format: int32
+              type: integer
+            type: array
+          sentinels:
+            items:
+              type: integer
+            type: array
+          limits:
+            additionalProperties:
+              type: integer
+            type: object
+          big:
+            format: bigint
+            type: integer
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Spec", Cfg::default()).unwrap();
+        let root = &structs[0];
+        assert_eq!(root.members[0].name, "big");
+        assert_eq!(root.members[0].type_, "Option<i64>");
+        assert_eq!(root.members[1].name, "generation");
+        assert_eq!(root.members[1].type_, "Option<i64>");
+        assert_eq!(root.members[2].name, "limits");
+        assert_eq!(root.members[2].type_, "Option<BTreeMap<String, i64>>");
+        assert_eq!(root.members[3].name, "ports");
+        assert_eq!(root.members[3].type_, "Option<Vec<i32>>");
+        assert_eq!(root.members[4].name, "sentinels");
+        assert_eq!(root.members[4].type_, "Option<Vec<i64>>");
+        assert_eq!(warnings, vec![
+            "unknown integer format bigint at .big - using i64",
+            "not generating type Limits at .limits - using integer map",
+        ]);
+        // only the unknown format loses information, the map still holds every integer
+        let kinds = warnings.iter().map(|w| w.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [WarningKind::UnknownFormat, WarningKind::Notice]);
+        assert!(warnings[0].is_lossy() && !warnings[1].is_lossy());
+    }
+
+    #[test]
+    fn prefer_unsigned_integers() {
+        init();
+        let schema_str = r#"
+        properties:
+          offset:
+            format: int32
+            type: integer
+          port:
+            format: int32
+            minimum: 0
+            type: integer
+          replicas:
+            minimum: 1
+            type: integer
+          delta:
+            minimum: -1
+            type: integer
+          ports:
+            items:
+              format: int32
+              minimum: 0
+              type: integer
+            type: array
+          weights:
+            additionalProperties:
+              minimum: 0
+              type: integer
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let cfg = Cfg {
+            prefer_unsigned: true,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema.clone(), "Spec", cfg).unwrap().containers;
+        let types = structs[0]
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.type_.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![
+            ("delta", "Option<i64>"),
+            ("offset", "Option<i32>"),
+            ("port", "Option<u32>"),
+            ("ports", "Option<Vec<u32>>"),
+            ("replicas", "Option<u64>"),
+            ("weights", "Option<BTreeMap<String, u64>>"),
+        ]);
+
+        // signed unless opted in
+        let structs = analyze(schema, "Spec", Cfg::default()).unwrap().containers;
+        assert_eq!(structs[0].members[2].type_, "Option<i32>");
+        assert_eq!(structs[0].members[4].type_, "Option<i64>");
+    }
+
+    #[test]
+    fn chrono_date_times() {
+        init();
+        let schema_str = r#"
+        properties:
+          lastTransitionTime:
+            format: date-time
+            type: string
+          history:
+            items:
+              format: date-time
+              type: string
+            type: array
+          expiries:
+            additionalProperties:
+              format: date-time
+              type: string
+            type: object
+          name:
+            type: string
+        required:
+        - lastTransitionTime
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let cfg = Cfg {
+            chrono: true,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema.clone(), "Status", cfg).unwrap().containers;
+        let root = &structs[0];
+        let types = root
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.type_.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![
+            ("expiries", "Option<BTreeMap<String, DateTime<Utc>>>"),
+            ("history", "Option<Vec<DateTime<Utc>>>"),
+            ("lastTransitionTime", "DateTime<Utc>"),
+            ("name", "Option<String>"),
+        ]);
+        assert!(root.uses_datetime());
+
+        // strings unless opted in
+        let structs = analyze(schema, "Status", Cfg::default()).unwrap().containers;
+        assert_eq!(structs[0].members[2].type_, "String");
+        assert!(!structs[0].uses_datetime());
+    }
+
+    #[test]
+    fn chrono_dates() {
+        init();
+        let schema_str = r#"
+        properties:
+          notAfter:
+            description: Expiry of the certificate
+            format: date
+            type: string
+          notBefore:
+            format: date
+            type: string
+          renewals:
+            items:
+              format: date
+              type: string
+            type: array
+        required:
+        - notAfter
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let cfg = Cfg {
+            chrono: true,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema.clone(), "Certificate", cfg).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "NaiveDate");
+        assert_eq!(root.members[1].type_, "Option<NaiveDate>");
+        assert_eq!(root.members[2].type_, "Option<Vec<NaiveDate>>");
+        assert_eq!(root.members[0].docs.as_deref(), Some("Expiry of the certificate"));
+        assert!(root.uses_date());
+
+        // strings with the format documented unless opted in
+        let structs = analyze(schema, "Certificate", Cfg::default()).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "String");
+        assert_eq!(root.members[1].type_, "Option<String>");
+        assert_eq!(root.members[2].type_, "Option<Vec<String>>");
+        assert_eq!(
+            root.members[0].docs.as_deref(),
+            Some("Expiry of the certificate\n\nFormat: date")
+        );
+        assert_eq!(root.members[1].docs.as_deref(), Some("Format: date"));
+        assert_eq!(root.members[2].docs.as_deref(), Some("Format: date"));
+    }
+
+    #[test]
+    fn integer_enums() {
+        init();
+        let schema_str = r#"
+        properties:
+          offset:
+            enum: [-1, 0, 1]
+            type: integer
+          replicas:
+            enum: [1, 3, 5]
+            format: int32
+            type: integer
+        required:
+        - replicas
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Cluster", Cfg::default()).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<ClusterOffset>");
+        assert_eq!(root.members[1].type_, "ClusterReplicas");
+
+        let offset = &structs[1];
+        assert_eq!(offset.name, "ClusterOffset");
+        assert!(offset.is_int_enum());
+        let variants = offset.members.iter().map(|m| (m.name.as_str(), m.discriminant));
+        assert_eq!(variants.collect::<Vec<_>>(), vec![
+            ("ValueMinus1", Some(-1)),
+            ("Value0", Some(0)),
+            ("Value1", Some(1))
+        ]);
+
+        let replicas = &structs[2];
+        assert_eq!(replicas.name, "ClusterReplicas");
+        assert!(replicas.is_int_enum());
+        let variants = replicas.members.iter().map(|m| (m.name.as_str(), m.discriminant));
+        assert_eq!(variants.collect::<Vec<_>>(), vec![
+            ("Value1", Some(1)),
+            ("Value3", Some(3)),
+            ("Value5", Some(5))
+        ]);
+    }
+
+    #[test]
+    fn enum_defaults() {
+        init();
+        let schema_str = r#"
+        properties:
+          imagePullPolicy:
+            default: IfNotPresent
+            enum: [Always, IfNotPresent, Never]
+            type: string
+          protocol:
+            default: SCTP
+            enum: [TCP, UDP]
+            type: string
+          restartPolicy:
+            enum: [Always, Never]
+            type: string
+          statusCode:
+            default: 302
+            enum: [301, 302]
+            type: integer
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Pod", Cfg::default()).unwrap();
+        let defaults = |c: &crate::Container| {
+            c.members
+                .iter()
+                .filter(|m| m.extra_annot.contains(&"#[default]".to_string()))
+                .map(|m| m.name.clone())
+                .collect::<Vec<_>>()
+        };
+        // matching default
+        assert_eq!(structs[1].name, "PodImagePullPolicy");
+        assert_eq!(defaults(&structs[1]), vec!["IfNotPresent"]);
+        assert!(structs[1].can_derive_default());
+        // mismatched default
+        assert_eq!(structs[2].name, "PodProtocol");
+        assert!(defaults(&structs[2]).is_empty());
+        assert!(!structs[2].can_derive_default());
+        assert_eq!(warnings, vec![
+            "default \"SCTP\" is not an enum value at .protocol - not deriving Default"
+        ]);
+        // no default
+        assert_eq!(structs[3].name, "PodRestartPolicy");
+        assert!(defaults(&structs[3]).is_empty());
+        assert!(!structs[3].can_derive_default());
+        // integer default
+        assert_eq!(structs[4].name, "PodStatusCode");
+        assert_eq!(defaults(&structs[4]), vec!["Value302"]);
+        // optional members are unaffected
+        assert_eq!(structs[0].members[0].type_, "Option<PodImagePullPolicy>");
+        assert_eq!(structs[0].members[0].serde_annot, vec![
+            "default",
+            "skip_serializing_if = \"Option::is_none\""
+        ]);
+    }
+
+    #[test]
+    fn one_of_unions() {
+        init();
+        let schema_str = r#"
+        properties:
+          port:
+            oneOf:
+            - type: integer
+            - type: string
+          selector:
+            properties:
+              a:
+                type: string
+              b:
+                type: string
+            oneOf:
+            - required: [a]
+            - required: [b]
+            type: object
+          shared:
+            properties:
+              common:
+                type: string
+            oneOf:
+            - properties:
+                x:
+                  type: string
+              required: [x]
+            - properties:
+                x:
+                  type: string
+                y:
+                  type: string
+              required: [x, y]
+            type: object
+          source:
+            oneOf:
+            - properties:
+                configMap:
+                  properties:
+                    name:
+                      type: string
+                  type: object
+              required: [configMap]
+            - properties:
+                secret:
+                  properties:
+                    secretName:
+                      type: string
+                  type: object
+              required: [secret]
+          value:
+            oneOf:
+            - type: string
+            - format: date
+              type: string
+        required:
+        - source
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Mount", Cfg::default()).unwrap();
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<MountPort>");
+        // alternatives only listing required properties keep the struct
+        assert_eq!(root.members[1].type_, "Option<MountSelector>");
+        assert_eq!(root.members[2].type_, "Option<MountShared>");
+        assert_eq!(root.members[3].type_, "MountSource");
+        assert_eq!(root.members[4].type_, "Option<serde_json::Value>");
+        assert_eq!(warnings, vec![
+            "indistinguishable oneOf alternatives 1 and 2 at .value - using serde_json::Value",
+            "ambiguous oneOf alternatives 1 and 2 at .shared - trying them in schema order",
+        ]);
+
+        // scalar alternatives are newtype variants
+        let port = &structs[1];
+        assert_eq!(port.name, "MountPort");
+        assert!(port.is_untagged_enum());
+        let variants = port.members.iter().map(|m| (m.name.as_str(), m.type_.as_str()));
+        assert_eq!(variants.collect::<Vec<_>>(), vec![
+            ("Integer", "i64"),
+            ("String", "String")
+        ]);
+
+        let selector = &structs[2];
+        assert_eq!(selector.name, "MountSelector");
+        assert!(!selector.is_enum);
+
+        // object alternatives get a struct including the shared properties
+        let shared = &structs[3];
+        assert_eq!(shared.name, "MountShared");
+        let variants = shared.members.iter().map(|m| (m.name.as_str(), m.type_.as_str()));
+        assert_eq!(variants.collect::<Vec<_>>(), vec![
+            ("X", "MountSharedX"),
+            ("Object", "MountSharedObject")
+        ]);
+        assert_eq!(structs[4].name, "MountSharedX");
+        assert_eq!(structs[4].members[0].type_, "Option<String>");
+        assert_eq!(structs[4].members[1].type_, "String");
+        assert_eq!(structs[5].name, "MountSharedObject");
+        assert_eq!(structs[5].members.len(), 3);
+
+        let source = &structs[6];
+        assert_eq!(source.name, "MountSource");
+        let variants = source.members.iter().map(|m| (m.name.as_str(), m.type_.as_str()));
+        assert_eq!(variants.collect::<Vec<_>>(), vec![
+            ("ConfigMap", "MountSourceConfigMap"),
+            ("Secret", "MountSourceSecret")
+        ]);
+        assert_eq!(structs[7].name, "MountSourceConfigMap");
+        assert_eq!(structs[7].members[0].type_, "MountSourceConfigMapConfigMap");
+        assert_eq!(structs[8].name, "MountSourceConfigMapConfigMap");
+        assert_eq!(structs[9].name, "MountSourceSecret");
+        assert_eq!(structs[10].name, "MountSourceSecretSecret");
+        assert_eq!(structs.len(), 11);
+    }
+
+    #[test]
+    fn one_of_variants_are_unique() {
+        init();
+        let schema_str = r#"
+        properties:
+          target:
+            oneOf:
+            - properties:
+                host:
+                  type: string
+                port:
+                  type: integer
+              required: [host, port]
+            - properties:
+                path:
+                  type: string
+                mode:
+                  type: integer
+              required: [path, mode]
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Mount", Cfg::default()).unwrap();
+        assert!(warnings.is_empty());
+        let target = &structs[1];
+        assert_eq!(target.name, "MountTarget");
+        let variants = target.members.iter().map(|m| (m.name.as_str(), m.type_.as_str()));
+        assert_eq!(variants.collect::<Vec<_>>(), vec![
+            ("Object", "MountTargetObject"),
+            ("Object2", "MountTargetObject2")
+        ]);
+        assert_eq!(structs[2].name, "MountTargetObject");
+        assert_eq!(structs[2].members[0].name, "host");
+        assert_eq!(structs[3].name, "MountTargetObject2");
+        assert_eq!(structs[3].members[0].name, "mode");
+        assert_eq!(structs.len(), 4);
+    }
+
+    #[test]
+    fn all_of_is_merged() {
+        init();
+        // shapes as emitted by controller-gen for fields with markers on both the field and its type
+        let schema_str = r#"
+        properties:
+          storage:
+            allOf:
+            - properties:
+                size:
+                  allOf:
+                  - pattern: ^(\+|-)?(([0-9]+(\.[0-9]*)?)|(\.[0-9]+))(([KMGTPE]i)|[numkMGTPE]|([eE](\+|-)?(([0-9]+(\.[0-9]*)?)|(\.[0-9]+))))?$
+                  - pattern: ^[0-9]+Gi$
+                  anyOf:
+                  - type: integer
+                  - type: string
+                  x-kubernetes-int-or-string: true
+              required:
+              - size
+              type: object
+            - allOf:
+              - properties:
+                  className:
+                    description: Name of the StorageClass
+                    type: string
+              - properties:
+                  className:
+                    maxLength: 63
+                required:
+                - className
+            description: Storage for the database
+          timeout:
+            allOf:
+            - format: int32
+            - minimum: 1
+            type: integer
+        required:
+        - storage
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Database", Cfg::default()).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.members[0].name, "storage");
+        assert_eq!(root.members[0].type_, "DatabaseStorage");
+        assert_eq!(root.members[0].docs.as_deref(), Some("Storage for the database"));
+        assert_eq!(root.members[1].type_, "Option<i32>");
+
+        // properties and required lists merged from nested branches
+        let storage = &structs[1];
+        assert_eq!(storage.name, "DatabaseStorage");
+        assert_eq!(storage.members[0].name, "className");
+        assert_eq!(storage.members[0].type_, "String");
+        assert_eq!(
+            storage.members[0].docs.as_deref(),
+            Some("Name of the StorageClass")
+        );
+        assert_eq!(storage.members[1].name, "size");
+        assert_eq!(storage.members[1].type_, "IntOrString");
+    }
+
+    #[test]
+    fn all_of_with_conflicting_types() {
+        init();
+        let schema_str = r#"
+        properties:
+          replicas:
+            allOf:
+            - type: integer
+            - type: string
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let err = analyze(schema, "Database", Cfg::default()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "conflicting types \"integer\" and \"string\" in allOf at .replicas"
+        );
+    }
+
+    #[test]
+    fn refs_to_definitions() {
+        init();
+        let schema_str = r#"
+        definitions:
+          Container:
+            properties:
+              name:
+                type: string
+              ports:
+                items:
+                  $ref: '#/definitions/Port'
+                type: array
+            required:
+            - name
+            type: object
+          Labels:
+            additionalProperties:
+              type: string
+            type: object
+          Node:
+            properties:
+              children:
+                items:
+                  $ref: '#/definitions/Node'
+                type: array
+              next:
+                $ref: '#/definitions/Node'
+              value:
+                type: string
+            type: object
+          Port:
+            properties:
+              port:
+                format: int32
+                type: integer
+              protocol:
+                $ref: '#/definitions/Protocol'
+            type: object
+          Protocol:
+            enum:
+            - TCP
+            - UDP
+            type: string
+          Unused:
+            properties:
+              name:
+                type: string
+            type: object
+        properties:
+          spec:
+            properties:
+              byName:
+                additionalProperties:
+                  $ref: '#/definitions/Container'
+                type: object
+              labels:
+                $ref: '#/definitions/Labels'
+              main:
+                $ref: '#/definitions/Container'
+                description: The main container
+              sidecars:
+                items:
+                  $ref: '#/definitions/Container'
+                type: array
+              tree:
+                $ref: '#/definitions/Node'
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "App", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        // one struct per object definition in use, other definitions are inlined
+        assert_eq!(names, [
+            "App",
+            "AppSpec",
+            "AppContainer",
+            "AppNode",
+            "AppPort",
+            "AppPortProtocol"
+        ]);
+
+        let spec = &structs[1];
+        assert_eq!(spec.members[0].type_, "Option<BTreeMap<String, AppContainer>>");
+        assert_eq!(spec.members[1].type_, "Option<BTreeMap<String, String>>");
+        assert_eq!(spec.members[2].type_, "Option<AppContainer>");
+        assert_eq!(spec.members[2].docs.as_deref(), Some("The main container"));
+        assert_eq!(spec.members[3].type_, "Option<Vec<AppContainer>>");
+        assert_eq!(spec.members[4].type_, "Option<AppNode>");
+
+        let container = &structs[2];
+        assert_eq!(container.members[0].type_, "String");
+        assert_eq!(container.members[1].type_, "Option<Vec<AppPort>>");
+
+        // recursion needs a box, unless the reference is behind a Vec already
+        let node = &structs[3];
+        assert_eq!(node.members[0].type_, "Option<Vec<AppNode>>");
+        assert_eq!(node.members[1].type_, "Option<Box<AppNode>>");
+
+        let port = &structs[4];
+        assert_eq!(port.members[1].type_, "Option<AppPortProtocol>");
+        assert!(structs[5].is_enum);
+    }
+
+    #[test]
+    fn unresolvable_refs() {
+        init();
+        let schema_str = r#"
+        definitions:
+          List:
+            items:
+              $ref: '#/definitions/List'
+            type: array
+        properties:
+          spec:
+            properties:
+              list:
+                $ref: '#/definitions/List'
+              missing:
+                $ref: '#/definitions/Missing'
+            type: object
+        type: object
+"#;
+        let mut schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let props = schema.properties.as_mut().unwrap().get_mut("spec").unwrap();
+        props.properties.as_mut().unwrap().remove("list");
+        let err = analyze(schema, "App", Cfg::default()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unresolved $ref #/definitions/Missing at .spec.missing"
+        );
+
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let err = analyze(schema, "App", Cfg::default()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "recursive $ref #/definitions/List at #/definitions/List[] cannot be inlined"
+        );
+    }
+
+    #[test]
+    fn recursive_schemas() {
+        init();
+        let schema_str = r#"
+        properties:
+          spec:
+            properties:
+              onFailure:
+                $ref: '#/properties/spec/properties/steps/items'
+              steps:
+                description: Steps to run in order
+                items:
+                  properties:
+                    name:
+                      type: string
+                    parallel:
+                      $ref: '#/properties/spec/properties/steps/items'
+                    steps:
+                      $ref: '#/properties/spec/properties/steps'
+                  required:
+                  - name
+                  type: object
+                type: array
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Pipeline", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Pipeline", "PipelineSpec", "PipelineSpecStep"]);
+
+        let spec = &structs[1];
+        assert_eq!(spec.members[0].type_, "Option<PipelineSpecStep>");
+        assert_eq!(spec.members[1].type_, "Option<Vec<PipelineSpecStep>>");
+        assert_eq!(spec.members[1].docs.as_deref(), Some("Steps to run in order"));
+
+        // steps can contain steps
+        let steps = &structs[2];
+        assert_eq!(steps.members[0].type_, "String");
+        assert_eq!(steps.members[1].type_, "Option<Box<PipelineSpecStep>>");
+        assert_eq!(steps.members[2].type_, "Option<Vec<PipelineSpecStep>>");
+
+        let schema_str = r#"
+        definitions:
+          Expression:
+            properties:
+              not:
+                $ref: '#/definitions/Expression'
+              value:
+                type: string
+            required:
+            - not
+            type: object
+        properties:
+          spec:
+            properties:
+              match:
+                $ref: '#/definitions/Expression'
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let Output {
+            containers: structs,
+            warnings,
+        } = analyze(schema, "Pipeline", Cfg::default()).unwrap();
+        assert_eq!(structs[2].members[0].type_, "Option<Box<PipelineExpression>>");
+        assert_eq!(warnings, [
+            "required recursive member at #/definitions/Expression.not - using Option"
+        ]);
+    }
+
+    #[test]
+    fn nesting_depth_is_limited() {
+        init();
+        let mut schema = JSONSchemaProps {
+            type_: Some("string".into()),
+            ..JSONSchemaProps::default()
+        };
+        for _ in 0..70 {
+            schema = JSONSchemaProps {
+                type_: Some("object".into()),
+                properties: Some([("a".to_string(), schema)].into()),
+                ..JSONSchemaProps::default()
+            };
+        }
+        let err = analyze(schema, "Deep", Cfg::default()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "schema at {} is nested more than 64 levels deep - recursive schemas need a $ref",
+                ".a".repeat(65)
+            )
+        );
+    }
+
+    #[test]
+    fn nullable_members() {
+        init();
+        let schema_str = r#"
+        properties:
+          config:
+            nullable: true
+            x-kubernetes-preserve-unknown-fields: true
+          image:
+            nullable: true
+            type: string
+          ports:
+            items:
+              format: int32
+              type: integer
+            nullable: true
+            type: array
+          timeout:
+            format: duration
+            nullable: true
+            type: string
+        required:
+        - config
+        - image
+        - timeout
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let cfg = Cfg {
+            duration: crate::DurationType::Duration,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema, "Nullable", cfg).unwrap().containers;
+        let root = &structs[0];
+        // serde_json::Value can hold a null itself
+        assert_eq!(root.members[0].type_, "serde_json::Value");
+        assert!(root.members[0].serde_annot.is_empty());
+
+        // required nullable members accept null, and serialize None as null
+        assert_eq!(root.members[1].type_, "Option<String>");
+        assert!(root.members[1].serde_annot.is_empty());
+        assert_eq!(root.members[3].type_, "Option<Duration>");
+        assert_eq!(root.members[3].serde_annot, [
+            "with = \"kopium_duration::option\""
+        ]);
+
+        // optional members are options regardless
+        assert_eq!(root.members[2].type_, "Option<Vec<i32>>");
+        assert_eq!(root.members[2].serde_annot.len(), 2);
+    }
+
+    #[test]
+    fn struct_names_are_unique() {
+        init();
+        let schema_str = r#"
+        properties:
+          spec:
+            properties:
+              foo:
+                properties:
+                  bar:
+                    properties:
+                      size:
+                        type: integer
+                    type: object
+                type: object
+              fooBar:
+                properties:
+                  name:
+                    type: string
+                type: object
+              jobTemplate:
+                properties:
+                  metadata:
+                    properties:
+                      labels:
+                        additionalProperties:
+                          type: string
+                        type: object
+                    type: object
+                type: object
+              mode:
+                enum: [Fast, Slow]
+                type: string
+              modes:
+                items:
+                  properties:
+                    speed:
+                      type: integer
+                  type: object
+                type: array
+              template:
+                properties:
+                  metadata:
+                    properties:
+                      name:
+                        type: string
+                    type: object
+                type: object
+            type: object
+          specFoo:
+            properties:
+              replicas:
+                type: integer
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "App", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, [
+            "App",
+            "AppSpec",
+            "AppSpecFoo2",
+            "AppSpecFoo2Bar",
+            "AppSpecFooBar",
+            "AppSpecJobTemplate",
+            "AppSpecJobTemplateMetadata",
+            "AppSpecMode",
+            "AppSpecMode2",
+            "AppSpecTemplate",
+            "AppSpecTemplateMetadata",
+            "AppSpecFoo",
+        ]);
+        // parents refer to the struct generated for their own member
+        let types = |name: &str| {
+            let s = structs.iter().find(|s| s.name == name).unwrap();
+            s.members.iter().map(|m| m.type_.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(types("App"), ["Option<AppSpec>", "Option<AppSpecFoo>"]);
+        assert_eq!(types("AppSpec"), [
+            "Option<AppSpecFoo2>",
+            "Option<AppSpecFooBar>",
+            "Option<AppSpecJobTemplate>",
+            "Option<AppSpecMode>",
+            "Option<Vec<AppSpecMode2>>",
+            "Option<AppSpecTemplate>",
+        ]);
+        assert_eq!(types("AppSpecFoo2"), ["Option<AppSpecFoo2Bar>"]);
+        assert_eq!(types("AppSpecFoo2Bar"), ["Option<i64>"]);
+        assert_eq!(types("AppSpecFooBar"), ["Option<String>"]);
+        assert_eq!(types("AppSpecMode2"), ["Option<i64>"]);
+    }
+
+    #[test]
+    fn enum_names_are_unique() {
+        init();
+        let schema_str = r#"
+        properties:
+          pullPolicy:
+            enum: [Always, Never]
+            type: string
+          pull_policy:
+            enum: [Always, IfNotPresent]
+            type: string
+          sidecars:
+            additionalProperties:
+              properties:
+                pullPolicy:
+                  enum: [Always]
+                  type: string
+              type: object
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Pod", Cfg::default()).unwrap().containers;
+        let root = &structs[0];
+        assert_eq!(root.members[0].type_, "Option<PodPullPolicy>");
+        assert_eq!(root.members[1].type_, "Option<PodPullPolicy2>");
+        assert_eq!(structs[1].name, "PodPullPolicy");
+        assert_eq!(structs[1].members.len(), 2);
+        assert_eq!(structs[2].name, "PodPullPolicy2");
+        assert_eq!(structs[2].members[1].name, "IfNotPresent");
+
+        // enums within map values are referenced by the name they are generated with
+        let sidecars = &structs[3];
+        assert_eq!(sidecars.name, "PodSidecar");
+        assert_eq!(sidecars.members[0].type_, "Option<PodSidecarPullPolicy>");
+        assert_eq!(structs[4].name, "PodSidecarPullPolicy");
+        assert!(structs[4].is_enum);
+    }
+
+    #[test]
+    fn map_value_structs() {
+        init();
+        let schema_str = r#"
+        properties:
+          policies:
+            additionalProperties:
+              additionalProperties:
+                properties:
+                  effect:
+                    type: string
+                type: object
+              type: object
+            type: object
+          resources:
+            additionalProperties:
+              properties:
+                limit:
+                  type: string
+                scaling:
+                  properties:
+                    mode:
+                      enum: [Hard, Soft]
+                      type: string
+                  type: object
+              required:
+              - limit
+              type: object
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Quota", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, [
+            "Quota",
+            "QuotaPolicy",
+            "QuotaResource",
+            "QuotaResourceScaling",
+            "QuotaResourceScalingMode"
+        ]);
+        let root = &structs[0];
+        assert_eq!(
+            root.members[0].type_,
+            "Option<BTreeMap<String, BTreeMap<String, QuotaPolicy>>>"
+        );
+        assert_eq!(root.members[1].type_, "Option<BTreeMap<String, QuotaResource>>");
+
+        // required members of the value schema, and nested structs within values
+        let resource = &structs[2];
+        assert_eq!(resource.members[0].type_, "String");
+        assert_eq!(resource.members[1].type_, "Option<QuotaResourceScaling>");
+        assert_eq!(structs[3].members[0].type_, "Option<QuotaResourceScalingMode>");
+    }
+
+    #[test]
+    fn singular_names() {
+        use super::singular;
+        for (plural, expected) in [
+            ("Resources", "Resource"),
+            ("Policies", "Policy"),
+            ("Addresses", "Address"),
+            ("Patches", "Patch"),
+            ("Status", "Status"),
+            ("Analysis", "Analysis"),
+            ("Class", "Class"),
+            ("Info", "Info"),
+            ("S", "S"),
+            // from real CRDs
+            ("HostAliases", "HostAlias"),
+            ("Tls", "Tls"),
+            ("IngressTls", "IngressTls"),
+            ("Egress", "Egress"),
+            ("RemoteWrites", "RemoteWrite"),
+            ("Retries", "Retry"),
+            ("MatchExpressions", "MatchExpression"),
+            ("Prefixes", "Prefix"),
+            ("Caches", "Cache"),
+            ("Databases", "Database"),
+            ("Series", "Series"),
+            ("DnsNames", "DnsName"),
+            ("Dns", "Dns"),
+            ("Statuses", "Status"),
+            ("ContainerStatuses", "ContainerStatus"),
+            ("Cookies", "Cookie"),
+            ("Kubernetes", "Kubernetes"),
+            ("Data", "Data"),
+        ] {
+            assert_eq!(singular(plural), expected);
+        }
+    }
+
+    #[test]
+    fn repeated_words_are_dropped() {
+        init();
+        let schema_str = r#"
+        properties:
+          spec:
+            properties:
+              gateway:
+                properties:
+                  gatewayMode:
+                    enum: [Shared, Dedicated]
+                    type: string
+                  listeners:
+                    items:
+                      properties:
+                        port:
+                          type: integer
+                      type: object
+                    type: array
+                type: object
+              gatewayClass:
+                properties:
+                  name:
+                    type: string
+                type: object
+              template:
+                properties:
+                  template:
+                    properties:
+                      metadata:
+                        properties:
+                          name:
+                            type: string
+                        type: object
+                    type: object
+                type: object
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let structs = analyze(schema, "Gateway", Cfg::default()).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        // names that would clash with the root or a parent keep their repetition
+        assert_eq!(names, [
+            "Gateway",
+            "GatewaySpec",
+            "GatewaySpecGateway",
+            "GatewayMode",
+            "GatewayListener",
+            "GatewayClass",
+            "GatewaySpecTemplate",
+            "GatewaySpecTemplateTemplate",
+            "GatewayTemplateMetadata",
+        ]);
+        let types = |name: &str| {
+            let s = structs.iter().find(|s| s.name == name).unwrap();
+            s.members.iter().map(|m| m.type_.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(types("GatewaySpec"), [
+            "Option<GatewaySpecGateway>",
+            "Option<GatewayClass>",
+            "Option<GatewaySpecTemplate>",
+        ]);
+        assert_eq!(types("GatewaySpecGateway"), [
+            "Option<GatewayMode>",
+            "Option<Vec<GatewayListener>>",
+        ]);
+        assert_eq!(types("GatewaySpecTemplate"), [
+            "Option<GatewaySpecTemplateTemplate>"
+        ]);
+        assert_eq!(types("GatewaySpecTemplateTemplate"), [
+            "Option<GatewayTemplateMetadata>"
+        ]);
+    }
+
+    #[test]
+    fn acronym_names() {
+        use super::{item_suffix, type_name};
+        let cfg = Cfg {
+            acronyms: vec!["TLS".into(), "http".into(), "URL".into(), "OIDC".into()],
+            ..Cfg::default()
+        };
+        for (key, plain, acronyms) in [
+            ("tls", "Tls", "TLS"),
+            ("tlsConfig", "TlsConfig", "TLSConfig"),
+            ("httpGet", "HttpGet", "HTTPGet"),
+            ("HTTPGet", "HttpGet", "HTTPGet"),
+            ("http_get", "HttpGet", "HTTPGet"),
+            ("httpRoute", "HttpRoute", "HTTPRoute"),
+            ("oidc", "Oidc", "OIDC"),
+            ("ipam", "Ipam", "Ipam"),
+            ("urlMap", "UrlMap", "URLMap"),
+            ("curl", "Curl", "Curl"),
+            ("tlsTls", "TlsTls", "TLSTLS"),
+        ] {
+            assert_eq!(type_name(key, &Cfg::default()), plain);
+            assert_eq!(type_name(key, &cfg), acronyms);
+        }
+        // items and values are named after the singular of the key first
+        assert_eq!(item_suffix("urls", &cfg), "URL");
+        assert_eq!(item_suffix("tlsCertificates", &cfg), "TLSCertificate");
+    }
+
+    #[test]
+    fn full_path_names() {
+        init();
+        let schema_str = r#"
+        properties:
+          spec:
+            properties:
+              gateway:
+                properties:
+                  listeners:
+                    items:
+                      properties:
+                        port:
+                          type: integer
+                      type: object
+                    type: array
+                type: object
+              template:
+                properties:
+                  metadata:
+                    properties:
+                      name:
+                        type: string
+                    title: EmbeddedObjectMeta
+                    type: object
+                  routes:
+                    additionalProperties:
+                      properties:
+                        path:
+                          type: string
+                      type: object
+                    type: object
+                type: object
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let cfg = Cfg {
+            naming: crate::Naming::FullPath,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema, "Gateway", cfg).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        // no singular, title or dropped repetition
+        assert_eq!(names, [
+            "Gateway",
+            "GatewaySpec",
+            "GatewaySpecGateway",
+            "GatewaySpecGatewayListeners",
+            "GatewaySpecTemplate",
+            "GatewaySpecTemplateMetadata",
+            "GatewaySpecTemplateRoutes",
+        ]);
+        let types = |name: &str| {
+            let s = structs.iter().find(|s| s.name == name).unwrap();
+            s.members.iter().map(|m| m.type_.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(types("GatewaySpecGateway"), [
+            "Option<Vec<GatewaySpecGatewayListeners>>"
+        ]);
+        assert_eq!(types("GatewaySpecTemplate"), [
+            "Option<GatewaySpecTemplateMetadata>",
+            "Option<BTreeMap<String, GatewaySpecTemplateRoutes>>",
+        ]);
+    }
+
+    #[test]
+    fn schema_defaults_replace_options() {
+        init();
+        let schema_str = r#"
+        properties:
+          spec:
+            properties:
+              replicas:
+                default: 1
+                type: integer
+              revision:
+                default: 2
+                nullable: true
+                type: integer
+              note:
+                type: string
+            type: object
+        type: object
+"#;
+        let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
+        let members = |schema_defaults: bool| {
+            let cfg = Cfg {
+                schema_defaults,
+                ..Cfg::default()
+            };
+            let structs = analyze(schema.clone(), "Foo", cfg).unwrap().containers;
+            structs[1]
+                .members
+                .iter()
+                .map(|m| (m.type_.clone(), m.default.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(members(false), [
+            ("Option<String>".to_string(), None),
+            ("Option<i64>".to_string(), None),
+            ("Option<i64>".to_string(), None),
+        ]);
+        assert_eq!(members(true), [
+            ("Option<String>".to_string(), None),
+            ("i64".to_string(), Some(serde_json::json!(1))),
+            // null is a value of its own, which the default must not replace
+            ("Option<i64>".to_string(), None),
+        ]);
     }
 
     #[test]
-    fn enum_string() {
+    fn struct_docs_come_from_object_descriptions() {
         init();
         let schema_str = r#"
-      properties:
-        operator:
-          enum:
-          - In
-          - NotIn
-          - Exists
-          - DoesNotExist
-          type: string
-      required:
-      - operator
-      type: object
+        description: Prometheus defines a Prometheus deployment.
+        properties:
+          spec:
+            description: PrometheusSpec is the specification of the desired behavior of the Prometheus cluster.
+            properties:
+              storage:
+                description: Storage spec to specify how storage shall be used.
+                properties:
+                  disableMountSubPath:
+                    type: boolean
+                type: object
+              undocumented:
+                properties:
+                  name:
+                    type: string
+                type: object
+            type: object
+        type: object
 "#;
-
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        let structs = analyze(schema, "MatchExpressions", Cfg::default()).unwrap().0;
-        println!("got {:?}", structs);
-        let root = &structs[0];
-        assert_eq!(root.name, "MatchExpressions");
-        assert_eq!(root.level, 0);
-        assert_eq!(&root.members[0].name, "operator");
-        assert_eq!(&root.members[0].type_, "MatchExpressionsOperator");
-
-        // operator member
-        let op = &structs[1];
-        assert!(op.is_enum);
-        assert_eq!(op.name, "MatchExpressionsOperator");
-
-        // should have enum members:
-        assert_eq!(&op.members[0].name, "In");
-        assert_eq!(&op.members[0].type_, "");
-        assert_eq!(&op.members[1].name, "NotIn");
-        assert_eq!(&op.members[1].type_, "");
-        assert_eq!(&op.members[2].name, "Exists");
-        assert_eq!(&op.members[2].type_, "");
-        assert_eq!(&op.members[3].name, "DoesNotExist");
-        assert_eq!(&op.members[3].type_, "");
+        let structs = analyze(schema, "Prometheus", Cfg::default()).unwrap().containers;
+        let docs = |name: &str| {
+            let s = structs.iter().find(|s| s.name == name).unwrap();
+            s.docs.as_deref()
+        };
+        assert_eq!(
+            docs("Prometheus"),
+            Some("Prometheus defines a Prometheus deployment.")
+        );
+        assert_eq!(
+            docs("PrometheusSpec"),
+            Some("PrometheusSpec is the specification of the desired behavior of the Prometheus cluster.")
+        );
+        assert_eq!(
+            docs("PrometheusSpecStorage"),
+            Some("Storage spec to specify how storage shall be used.")
+        );
+        assert_eq!(docs("PrometheusSpecUndocumented"), None);
     }
 
     #[test]
-    fn enum_string_within_container() {
+    fn struct_prefix_replaces_the_kind() {
         init();
         let schema_str = r#"
-      description: Endpoint
-      properties:
-        relabelings:
-          items:
+        properties:
+          spec:
             properties:
-              action:
-                default: replace
-                enum:
-                - replace
-                - keep
-                - drop
-                - hashmod
-                - labelmap
-                - labeldrop
-                - labelkeep
+              options:
+                items:
+                  properties:
+                    name:
+                      type: string
+                  type: object
+                type: array
+              rule:
+                properties:
+                  name:
+                    type: string
+                type: object
+              template:
+                properties:
+                  name:
+                    type: string
+                title: Template
+                type: object
+            type: object
+          status:
+            properties:
+              phase:
                 type: string
-              modulus:
-                format: int64
-                type: integer
             type: object
-          type: array
-      type: object
-        "#;
-
+        type: object
+"#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        let structs = analyze(schema, "Endpoint", Cfg::default()).unwrap().0;
-        println!("got {:?}", structs);
-        let root = &structs[0];
-        assert_eq!(root.name, "Endpoint");
-        assert_eq!(root.level, 0);
-        assert!(!root.is_enum);
-        assert_eq!(&root.members[0].name, "relabelings");
-        assert_eq!(&root.members[0].type_, "Option<Vec<EndpointRelabelings>>");
+        let names = |prefix: Option<&str>| {
+            let cfg = Cfg {
+                struct_prefix: prefix.map(String::from),
+                ..Cfg::default()
+            };
+            let structs = analyze(schema.clone(), "Rule", cfg).unwrap().containers;
+            let types = structs[1]
+                .members
+                .iter()
+                .map(|m| m.type_.clone())
+                .collect::<Vec<_>>();
+            (structs.into_iter().map(|s| s.name).collect::<Vec<_>>(), types)
+        };
+        let (structs, types) = names(None);
+        assert_eq!(structs, [
+            "Rule",
+            "RuleSpec",
+            "RuleSpecOption",
+            "RuleSpecRule",
+            "RuleTemplate",
+            "RuleStatus",
+        ]);
+        assert_eq!(types, [
+            "Option<Vec<RuleSpecOption>>",
+            "Option<RuleSpecRule>",
+            "Option<RuleTemplate>",
+        ]);
 
-        let rel = &structs[1];
-        assert_eq!(rel.name, "EndpointRelabelings");
-        assert!(!rel.is_enum);
-        assert_eq!(&rel.members[0].name, "action");
-        assert_eq!(&rel.members[0].type_, "Option<EndpointRelabelingsAction>");
-        // TODO: verify rel.members[0].field_annot uses correct default
+        let (structs, types) = names(Some("Acme"));
+        assert_eq!(structs, [
+            "Acme",
+            "AcmeSpec",
+            "AcmeSpecOption",
+            "AcmeSpecRule",
+            "AcmeTemplate",
+            "AcmeStatus",
+        ]);
+        assert_eq!(types, [
+            "Option<Vec<AcmeSpecOption>>",
+            "Option<AcmeSpecRule>",
+            "Option<AcmeTemplate>",
+        ]);
 
-        // action enum member
-        let act = &structs[2];
-        assert_eq!(act.name, "EndpointRelabelingsAction");
-        assert!(act.is_enum);
+        // without a prefix, names must not clash with the struct kube generates or with prelude types
+        let (structs, types) = names(Some(""));
+        assert_eq!(structs, [
+            "",
+            "Spec",
+            "SpecOption2",
+            "SpecRule2",
+            "Template",
+            "Status",
+        ]);
+        assert_eq!(types, [
+            "Option<Vec<SpecOption2>>",
+            "Option<SpecRule2>",
+            "Option<Template>",
+        ]);
+    }
 
-        // should have enum members:
-        assert_eq!(&act.members[0].name, "replace");
-        assert_eq!(&act.members[0].type_, "");
-        assert_eq!(&act.members[1].name, "keep");
-        assert_eq!(&act.members[1].type_, "");
-        assert_eq!(&act.members[2].name, "drop");
-        assert_eq!(&act.members[2].type_, "");
-        assert_eq!(&act.members[3].name, "hashmod");
-        assert_eq!(&act.members[3].type_, "");
+    #[test]
+    fn names_without_repeated_words() {
+        use super::without_repeated_words;
+        for (name, expected) in [
+            ("GatewayGatewayListener", "GatewayListener"),
+            ("FooTemplateTemplate", "FooTemplate"),
+            ("FooBarFooBarBaz", "FooBarBaz"),
+            ("FooBarFooBaz", "FooBarFooBaz"),
+            ("DNSSEC", "DNSSEC"),
+            ("Foo", "Foo"),
+        ] {
+            assert_eq!(without_repeated_words(name), expected);
+        }
     }
 
     #[test]
-    #[ignore] // oneof support not done
-    fn enum_oneof() {
+    fn titles_name_structs() {
         init();
         let schema_str = r#"
-    description: "Auto-generated derived type for ServerSpec via `CustomResource`"
-    properties:
-      spec:
         properties:
-          podSelector:
-            oneOf:
-              - required:
-                  - matchExpressions
-              - required:
-                  - matchLabels
+          spec:
             properties:
-              matchExpressions:
+              jobTemplate:
+                properties:
+                  metadata:
+                    properties:
+                      labels:
+                        additionalProperties:
+                          type: string
+                        type: object
+                    title: EmbeddedObjectMeta
+                    type: object
+                type: object
+              selectors:
                 items:
                   properties:
                     key:
                       type: string
-                    operator:
-                      enum:
-                        - In
-                        - NotIn
-                        - Exists
-                        - DoesNotExists
-                      type: string
-                    values:
-                      items:
-                        type: string
-                      nullable: true
-                      type: array
-                  required:
-                    - key
-                    - operator
+                  title: LabelSelector
                   type: object
                 type: array
-              matchLabels:
+              template:
+                properties:
+                  metadata:
+                    properties:
+                      name:
+                        type: string
+                    title: EmbeddedObjectMeta
+                    type: object
+                type: object
+              volumes:
                 additionalProperties:
-                  type: string
+                  properties:
+                    size:
+                      type: string
+                  title: Volume Source
+                  type: object
                 type: object
+            title: WorkloadSpec
             type: object
-        required:
-          - podSelector
         type: object
-    required:
-      - spec
-    title: Server
-    type: object"#;
-
+"#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        let structs = analyze(schema, "ServerSpec", Cfg::default()).unwrap().0;
-        println!("got {:?}", structs);
-        let root = &structs[0];
-        assert_eq!(root.name, "ServerSpec");
-        assert_eq!(root.level, 0);
-
-        // should have a required selector
-        let member = &root.members[0];
-        assert_eq!(member.name, "pod_selector");
-        assert_eq!(member.type_, "ServerPodSelector");
-
-        // and this should be an enum
-        let ps = &structs[1]; // TODO: encode as struct?
-        assert_eq!(ps.name, "ServerPodSelector");
-        assert_eq!(ps.level, 1);
-
-        // should have enum members: TODO: encode inner type as type_?
-        assert_eq!(&ps.members[0].name, "MatchExpressions");
-        assert_eq!(&ps.members[0].type_, "Vec<ServerPodSelectorMatchExpressions");
-        assert_eq!(&ps.members[1].name, "MatchLabels");
-        assert_eq!(&ps.members[1].type_, "BTreeMap<String, String>");
-
-        // should have the inner struct match expressions
-        let me = &structs[2];
-        assert_eq!(me.name, "ServerPodSelectorMatchExpressions");
-        assert_eq!(me.level, 2);
-
-        // which should have 3 members
-        assert_eq!(&me.members[0].name, "key");
-        assert_eq!(&me.members[0].type_, "String");
-        assert_eq!(&me.members[1].name, "operator");
-        assert_eq!(&me.members[1].type_, "ServerPodSelectorMatchExpressionsOperator");
-        assert_eq!(&me.members[2].name, "values");
-        assert_eq!(&me.members[2].type_, " Option<Vec<String>>");
-
-        // last struct being the innermost enum operator:
-        let op = &structs[3];
-        assert_eq!(op.name, "ServerPodSelectorMatchExpressionsOperator");
-        assert_eq!(op.level, 3);
+        let structs = analyze(schema.clone(), "Workload", Cfg::default())
+            .unwrap()
+            .containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        // spec keeps its name, titles that are not identifiers are ignored
+        assert_eq!(names, [
+            "Workload",
+            "WorkloadSpec",
+            "WorkloadSpecJobTemplate",
+            "WorkloadEmbeddedObjectMeta",
+            "WorkloadLabelSelector",
+            "WorkloadSpecTemplate",
+            "WorkloadEmbeddedObjectMeta2",
+            "WorkloadSpecVolume"
+        ]);
+        let spec = &structs[1];
+        assert_eq!(spec.members[1].type_, "Option<Vec<WorkloadLabelSelector>>");
+        assert_eq!(
+            spec.members[3].type_,
+            "Option<BTreeMap<String, WorkloadSpecVolume>>"
+        );
+        assert_eq!(structs[2].members[0].type_, "Option<WorkloadEmbeddedObjectMeta>");
+        assert_eq!(structs[5].members[0].type_, "Option<WorkloadEmbeddedObjectMeta2>");
 
-        // with enum members:
-        assert_eq!(&op.members[0].name, "In");
-        assert_eq!(&op.members[1].name, "In");
-        assert_eq!(&op.members[2].name, "In");
-        assert_eq!(&op.members[3].name, "In");
+        let cfg = Cfg {
+            no_title_prefix: true,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema, "Workload", cfg).unwrap().containers;
+        assert_eq!(structs[3].name, "EmbeddedObjectMeta");
+        assert_eq!(structs[4].name, "LabelSelector");
+        assert_eq!(structs[2].members[0].type_, "Option<EmbeddedObjectMeta>");
     }
 
     #[test]
-    fn service_monitor_params() {
+    fn item_names_can_keep_the_key() {
         init();
         let schema_str = r#"
         properties:
-          endpoints:
+          groups:
             items:
-              description: Endpoint defines a scrapeable endpoint serving Prometheus
-                metrics.
               properties:
-                params:
-                  additionalProperties:
-                    items:
-                      type: string
-                    type: array
-                  description: Optional HTTP URL parameters
-                  type: object
+                rules:
+                  items:
+                    properties:
+                      expr:
+                        type: string
+                    type: object
+                  type: array
               type: object
             type: array
-        required:
-        - endpoints
+          resources:
+            additionalProperties:
+              properties:
+                limit:
+                  type: string
+              type: object
+            type: object
         type: object
 "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        let structs = analyze(schema, "ServiceMonitor", Cfg::default()).unwrap().0;
-        println!("got {:?}", structs);
-        let root = &structs[0];
-        assert_eq!(root.name, "ServiceMonitor");
-        assert_eq!(root.level, 0);
-
-        // should have a required endpoints member
-        let member = &root.members[0];
-        assert_eq!(member.name, "endpoints");
-        assert_eq!(member.type_, "Vec<ServiceMonitorEndpoints>");
+        let structs = analyze(schema.clone(), "Rule", Cfg::default())
+            .unwrap()
+            .containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Rule", "RuleGroup", "RuleGroupRule", "RuleResource"]);
+        assert_eq!(structs[0].members[0].type_, "Option<Vec<RuleGroup>>");
+        assert_eq!(structs[1].members[0].type_, "Option<Vec<RuleGroupRule>>");
 
-        // Should have a endpoints struct:
-        let eps = &structs[1];
-        assert_eq!(eps.name, "ServiceMonitorEndpoints");
-        assert_eq!(eps.level, 1);
-        // should have an params member:
-        let member = &eps.members[0];
-        assert_eq!(member.name, "params");
-        assert_eq!(member.type_, "Option<BTreeMap<String, String>>");
+        let cfg = Cfg {
+            no_singularize: true,
+            ..Cfg::default()
+        };
+        let structs = analyze(schema, "Rule", cfg).unwrap().containers;
+        let names = structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Rule", "RuleGroups", "RuleGroupsRules", "RuleResources"]);
+        assert_eq!(structs[0].members[0].type_, "Option<Vec<RuleGroups>>");
+        assert_eq!(
+            structs[0].members[1].type_,
+            "Option<BTreeMap<String, RuleResources>>"
+        );
+        assert_eq!(structs[1].members[0].type_, "Option<Vec<RuleGroupsRules>>");
     }
 
     #[test]
@@ -1070,7 +5389,9 @@ type: object
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
 
         //println!("schema: {}", serde_json::to_string_pretty(&schema).unwrap());
-        let structs = analyze(schema, "DestinationRule", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "DestinationRule", Cfg::default())
+            .unwrap()
+            .containers;
         //println!("{:#?}", structs);
 
         // this should produce the root struct struct
@@ -1105,7 +5426,7 @@ type: object
         "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
         println!("got schema {}", serde_yaml::to_string(&schema).unwrap());
-        let structs = analyze(schema, "StatusCode", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "StatusCode", Cfg::default()).unwrap().containers;
         println!("got {:?}", structs);
         let root = &structs[0];
         assert_eq!(root.name, "StatusCode");
@@ -1131,7 +5452,9 @@ type: object
         "#;
 
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        let structs = analyze(schema, "KustomizationSpec", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "KustomizationSpec", Cfg::default())
+            .unwrap()
+            .containers;
         println!("got {:?}", structs);
         let root = &structs[0];
         assert_eq!(root.name, "KustomizationSpec");
@@ -1173,7 +5496,9 @@ type: object
             type: object
         type: object"#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
-        let structs = analyze(schema, "AppProjectStatus", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "AppProjectStatus", Cfg::default())
+            .unwrap()
+            .containers;
         println!("got {:?}", structs);
         let root = &structs[0];
         assert_eq!(root.name, "AppProjectStatus");
@@ -1190,7 +5515,7 @@ type: object
         assert_eq!(&role.members[0].name, "items");
         let items = &structs[2];
         assert_eq!(items.level, 2);
-        assert_eq!(items.name, "AppProjectStatusJwtTokensByRoleItems");
+        assert_eq!(items.name, "AppProjectStatusJwtTokensByRoleItem");
         assert_eq!(&items.members[0].name, "exp");
         assert_eq!(&items.members[1].name, "iat");
         assert_eq!(&items.members[2].name, "id");
@@ -1207,7 +5532,7 @@ type: object
 "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
 
-        let structs = analyze(schema, "Agent", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "Agent", Cfg::default()).unwrap().containers;
 
         let root = &structs[0];
         assert_eq!(root.name, "Agent");
@@ -1237,7 +5562,7 @@ type: object
 "#;
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
 
-        let structs = analyze(schema, "Geoip", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "Geoip", Cfg::default()).unwrap().containers;
 
         assert_eq!(structs.len(), 1);
         assert_eq!(structs[0].members.len(), 1);
@@ -1275,7 +5600,7 @@ type: object
 
         let schema: JSONSchemaProps = serde_yaml::from_str(schema_str).unwrap();
 
-        let structs = analyze(schema, "Gateway", Cfg::default()).unwrap().0;
+        let structs = analyze(schema, "Gateway", Cfg::default()).unwrap().containers;
         assert_eq!(structs.len(), 1);
         assert_eq!(structs[0].members.len(), 1);
         assert_eq!(structs[0].members[0].type_, "Option<Vec<Condition>>");
@@ -0,0 +1,135 @@
+//! Describes analyzed containers as a json document, for generators building on kopium
+//!
+//! The fields of each description are declared in alphabetical order, which is the order of their keys.
+use crate::{render::emission_order, Analyzed, RenderOptions};
+use kube::ResourceExt;
+use serde::Serialize;
+
+/// A CRD version along with the structs and enums generated for it
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct VersionDescription {
+    /// Description of the root of the schema
+    pub docs: Option<String>,
+    pub group: String,
+    pub kind: String,
+    /// Name of the CRD, e.g. `servers.policy.linkerd.io`
+    pub name: String,
+    pub namespaced: bool,
+    pub plural: String,
+    /// Whether this is the storage version
+    pub storage: bool,
+    pub types: Vec<TypeDescription>,
+    pub version: String,
+}
+
+/// A generated struct or enum
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TypeDescription {
+    pub docs: Option<String>,
+    pub kind: TypeKind,
+    /// Fields of a struct
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<MemberDescription>>,
+    /// Name of the type as generated
+    pub name: String,
+    /// Variants of an enum
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variants: Option<Vec<VariantDescription>>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TypeKind {
+    Struct,
+    Enum,
+}
+
+/// A field of a generated struct
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MemberDescription {
+    pub docs: Option<String>,
+    /// Rust name of the field
+    pub name: String,
+    /// Whether the field is an `Option`
+    pub optional: bool,
+    /// Key of the field in the schema, or none for a flattened field
+    pub property: Option<String>,
+    /// Whether the schema requires the key, as the field has no default
+    pub required: bool,
+    /// Rust type of the field, naming structs and enums as they are generated
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// A variant of a generated enum
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct VariantDescription {
+    /// Rust name of the variant
+    pub name: String,
+    /// Type the variant holds, if any
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    /// Value of the variant in the schema
+    pub value: String,
+}
+
+/// Describe the structs and enums [`render`](crate::render) would write for a CRD version, with the names of the CRD
+///
+/// Types are named as they are emitted, and elided types are left out.
+pub fn describe(generated: &Analyzed, options: &RenderOptions) -> VersionDescription {
+    let Analyzed {
+        crd,
+        version,
+        containers: structs,
+    } = generated;
+    let prefix = options.struct_prefix(crd);
+    let types = emission_order(structs)
+        .into_iter()
+        .filter(|s| !s.is_root() && !options.elide.contains(&s.name))
+        .map(|s| {
+            let name = s.emitted_name(prefix, options.naming);
+            if s.is_enum {
+                let variants = s.members.iter().map(|m| VariantDescription {
+                    name: m.name.clone(),
+                    type_: Some(m.emitted_type(prefix, options.naming)).filter(|t| !t.is_empty()),
+                    value: m.wire_name(),
+                });
+                TypeDescription {
+                    docs: s.docs.clone(),
+                    kind: TypeKind::Enum,
+                    members: None,
+                    name,
+                    variants: Some(variants.collect()),
+                }
+            } else {
+                let members = s.members.iter().map(|m| MemberDescription {
+                    docs: m.docs.clone(),
+                    name: m.name.clone(),
+                    optional: m.type_.starts_with("Option<"),
+                    property: s.member_wire_name(m),
+                    required: m.is_required(),
+                    type_: m.emitted_type(prefix, options.naming),
+                });
+                TypeDescription {
+                    docs: s.docs.clone(),
+                    kind: TypeKind::Struct,
+                    members: Some(members.collect()),
+                    name,
+                    variants: None,
+                }
+            }
+        })
+        .collect();
+    let names = &crd.spec.names;
+    VersionDescription {
+        docs: structs.iter().find(|s| s.is_root()).and_then(|s| s.docs.clone()),
+        group: crd.spec.group.clone(),
+        kind: names.kind.clone(),
+        name: crd.name_any(),
+        namespaced: crd.spec.scope == "Namespaced",
+        plural: names.plural.clone(),
+        storage: version.storage,
+        types,
+        version: version.name.clone(),
+    }
+}
@@ -0,0 +1,136 @@
+//! Serde helpers for Go-style duration strings such as "1h30m", "500ms" or "0"
+//!
+//! Generated by kopium for fields with `format: duration` when using `--duration-as std::time::Duration`.
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+const UNITS: [(&str, u128); 8] = [
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("μs", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60_000_000_000),
+    ("h", 3_600_000_000_000),
+];
+
+pub fn serialize<S: Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&format(duration))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+    parse(&String::deserialize(d)?).map_err(D::Error::custom)
+}
+
+/// Helpers for optional durations
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => s.serialize_some(&super::format(duration)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|s| super::parse(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Parse a Go duration string, a sequence of decimal numbers with a unit such as "300ms" or "2h45m"
+///
+/// Negative durations are rejected, as they cannot be represented by a `Duration`.
+pub fn parse(input: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {:?}", input);
+    let mut rest = input.strip_prefix('+').unwrap_or(input);
+    if rest == "0" {
+        return Ok(Duration::ZERO);
+    }
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut nanos: u128 = 0;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        rest = tail;
+
+        let unit_nanos = UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, nanos)| *nanos)
+            .ok_or_else(|| format!("unknown unit {:?} in duration {:?}", unit, input))?;
+        let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+        if int.is_empty() && frac.is_empty() {
+            return Err(invalid());
+        }
+        let int = if int.is_empty() {
+            0
+        } else {
+            int.parse::<u128>().map_err(|_| invalid())?
+        };
+        nanos = int
+            .checked_mul(unit_nanos)
+            .and_then(|n| nanos.checked_add(n))
+            .ok_or_else(invalid)?;
+        if !frac.is_empty() {
+            // digits beyond nanosecond precision do not matter
+            let frac = &frac[..frac.len().min(18)];
+            let scale = 10u128.pow(frac.len() as u32);
+            let frac = frac.parse::<u128>().map_err(|_| invalid())?;
+            nanos = nanos.checked_add(frac * unit_nanos / scale).ok_or_else(invalid)?;
+        }
+    }
+    let secs = u64::try_from(nanos / 1_000_000_000).map_err(|_| invalid())?;
+    Ok(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/// Format a duration the way Go does, e.g. "1h30m0s", "1.5s" or "500ms"
+pub fn format(duration: &Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos == 0 {
+        return "0s".to_string();
+    }
+    if nanos < 1_000 {
+        return format!("{}ns", nanos);
+    }
+    if nanos < 1_000_000 {
+        return format!("{}µs", decimal(nanos, 1_000));
+    }
+    if nanos < 1_000_000_000 {
+        return format!("{}ms", decimal(nanos, 1_000_000));
+    }
+    let secs = duration.as_secs();
+    let (hours, minutes) = (secs / 3600, secs / 60 % 60);
+    let mut out = String::new();
+    if hours > 0 {
+        out.push_str(&format!("{}h", hours));
+    }
+    if hours > 0 || minutes > 0 {
+        out.push_str(&format!("{}m", minutes));
+    }
+    let seconds = u128::from(secs % 60) * 1_000_000_000 + u128::from(duration.subsec_nanos());
+    out.push_str(&format!("{}s", decimal(seconds, 1_000_000_000)));
+    out
+}
+
+/// Value divided by unit as a decimal without trailing zeros
+fn decimal(value: u128, unit: u128) -> String {
+    let (int, frac) = (value / unit, value % unit);
+    if frac == 0 {
+        return int.to_string();
+    }
+    let width = unit.ilog10() as usize;
+    let frac = format!("{:0width$}", frac, width = width);
+    format!("{}.{}", int, frac.trim_end_matches('0'))
+}
@@ -1,8 +1,60 @@
+//! Kubernetes OpenAPI unmangler
+//!
+//! Finds the structs and enums to generate for the schema of a `CustomResourceDefinition` version,
+//! and writes them out as Rust code with [`render`], which is all the `kopium` binary does on top of its flags.
+//!
+//! ```
+//! use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::JSONSchemaProps;
+//! use kopium::{analyze, Config, Output};
+//!
+//! let schema: JSONSchemaProps = serde_json::from_value(serde_json::json!({
+//!     "type": "object",
+//!     "properties": {
+//!         "spec": {
+//!             "type": "object",
+//!             "required": ["image"],
+//!             "properties": {
+//!                 "image": { "type": "string" },
+//!                 "minReplicas": { "type": "integer", "format": "int32" },
+//!             },
+//!         },
+//!     },
+//! }))?;
+//! let Output { containers: containers, warnings } = analyze(schema, "Workload", Config::default())?.rename();
+//! assert!(warnings.is_empty());
+//!
+//! let spec = containers.iter().find(|c| c.is_main_container()).unwrap();
+//! assert_eq!(spec.name, "WorkloadSpec");
+//! assert_eq!(spec.members[0].type_, "String");
+//! // members are renamed to snake case, and the struct keeps their names on the wire
+//! assert_eq!(spec.members[1].name, "min_replicas");
+//! assert_eq!(spec.serde_annot, ["rename_all = \"camelCase\""]);
+//! assert_eq!(spec.members[1].type_, "Option<i32>");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
 #[macro_use] extern crate log;
 
 mod analyzer;
 pub use analyzer::{analyze, Config};
 mod output;
-pub use output::{Container, MapType, Member, Output};
+pub use output::{
+    default_exclusions, eq_exclusions, hash_exclusions, Constraints, Container, DurationType, EmbeddedType,
+    MapType, Member, Naming, Output, Validation, Visibility, Warning, WarningKind,
+};
 mod derive;
 pub use derive::Derive;
+mod render;
+pub use render::{render, Analyzed, EnumImpl, RenderOptions};
+mod describe;
+pub use describe::{
+    describe, MemberDescription, TypeDescription, TypeKind, VariantDescription, VersionDescription,
+};
+// the generated helper module, compiled here for its unit tests
+#[cfg(test)] mod duration;
+
+// code generated with doc comments, whose examples must not run as doctests (see `cargo test --doc`)
+#[cfg(doctest)]
+#[allow(dead_code)]
+mod docs {
+    include!("../tests/generated/docs.rs");
+}
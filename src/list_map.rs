@@ -0,0 +1,96 @@
+//! Serde helpers for lists with `x-kubernetes-list-type: map`, represented as maps keyed by a field of their items
+//!
+//! Generated by kopium for such lists when using `--list-map-as-map`.
+//! The key field is pulled out of each item when deserializing, and put back in when serializing.
+//! Items are serialized in the order of their keys, which the apiserver accepts as the list is unordered.
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// Items of a list keyed by one of their fields, which the generated struct of the items leaves out
+pub trait Keyed {
+    /// Name of the key field on the wire
+    const KEY: &'static str;
+}
+
+pub fn serialize<T, S>(map: &BTreeMap<String, T>, s: S) -> Result<S::Ok, S::Error>
+where
+    T: Keyed + Serialize,
+    S: Serializer,
+{
+    to_items(map).map_err(serde::ser::Error::custom)?.serialize(s)
+}
+
+pub fn deserialize<'de, T, D>(d: D) -> Result<BTreeMap<String, T>, D::Error>
+where
+    T: Keyed + DeserializeOwned,
+    D: Deserializer<'de>,
+{
+    from_items(Vec::<serde_json::Value>::deserialize(d)?).map_err(serde::de::Error::custom)
+}
+
+/// Helpers for optional lists
+pub mod option {
+    use super::*;
+
+    pub fn serialize<T, S>(map: &Option<BTreeMap<String, T>>, s: S) -> Result<S::Ok, S::Error>
+    where
+        T: Keyed + Serialize,
+        S: Serializer,
+    {
+        match map {
+            Some(map) => s.serialize_some(&super::to_items(map).map_err(serde::ser::Error::custom)?),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(d: D) -> Result<Option<BTreeMap<String, T>>, D::Error>
+    where
+        T: Keyed + DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        Option::<Vec<serde_json::Value>>::deserialize(d)?
+            .map(|items| super::from_items(items).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// The wire form of the items, with their keys put back in
+fn to_items<T: Keyed + Serialize>(map: &BTreeMap<String, T>) -> Result<Vec<serde_json::Value>, String> {
+    let mut items = Vec::with_capacity(map.len());
+    for (key, item) in map {
+        let mut value = serde_json::to_value(item).map_err(|e| format!("list item {:?}: {}", key, e))?;
+        let Some(fields) = value.as_object_mut() else {
+            return Err(format!("list item {:?} does not serialize to an object", key));
+        };
+        fields.insert(T::KEY.to_string(), key.as_str().into());
+        items.push(value);
+    }
+    Ok(items)
+}
+
+/// Items keyed by their key field, which is removed from them
+///
+/// Duplicate keys are rejected, as the apiserver does.
+fn from_items<T: Keyed + DeserializeOwned>(
+    items: Vec<serde_json::Value>,
+) -> Result<BTreeMap<String, T>, String> {
+    let mut map = BTreeMap::new();
+    for mut value in items {
+        let key = match value.as_object_mut().and_then(|fields| fields.remove(T::KEY)) {
+            Some(serde_json::Value::String(key)) => key,
+            Some(other) => {
+                return Err(format!(
+                    "key {} of a list item is not a string: {}",
+                    T::KEY,
+                    other
+                ))
+            }
+            None => return Err(format!("list item is missing its key {}", T::KEY)),
+        };
+        let item = serde_json::from_value(value).map_err(|e| format!("list item {:?}: {}", key, e))?;
+        if map.insert(key.clone(), item).is_some() {
+            return Err(format!("duplicate list item with {} {:?}", T::KEY, key));
+        }
+    }
+    Ok(map)
+}
@@ -1,13 +1,25 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    cell::RefCell,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 #[macro_use] extern crate log;
-use anyhow::{anyhow, Context, Result};
-use clap::{CommandFactory, Parser, Subcommand};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser, Subcommand};
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
     CustomResourceDefinition, CustomResourceDefinitionVersion,
 };
-use kopium::{analyze, Config, Container, Derive, MapType};
-use kube::{api, core::Version, Api, Client, ResourceExt};
-use quote::format_ident;
+use kopium::{
+    analyze, describe, render, Analyzed, Config, Container, Derive, DurationType, EmbeddedType, EnumImpl,
+    MapType, Naming, Output, RenderOptions, Validation, Visibility, Warning,
+};
+use kube::{
+    api,
+    config::{KubeConfigOptions, Kubeconfig},
+    core::Version,
+    Api, Client, ResourceExt,
+};
 
 #[derive(Parser)]
 #[command(
@@ -16,22 +28,121 @@ use quote::format_ident;
     about = "Kubernetes OPenapI UnMangler",
 )]
 struct Kopium {
-    /// Give the name of the input CRD to use e.g. prometheusrules.monitoring.coreos.com
+    /// Give the names of the input CRDs to use e.g. prometheusrules.monitoring.coreos.com
     #[arg(conflicts_with("file"))]
-    crd: Option<String>,
+    crd: Vec<String>,
+
+    /// Generate every CRD in the cluster belonging to this API group e.g. networking.istio.io
+    #[arg(long, conflicts_with_all(["crd", "file"]))]
+    group: Option<String>,
+
+    /// Generate every CRD in the cluster into one file per CRD
+    ///
+    /// Requires --out-dir (or --output) to point to the directory to write into.
+    #[arg(long, conflicts_with_all(["crd", "file", "group"]))]
+    all: bool,
+
+    /// Write the generated code to this file instead of stdout
+    ///
+    /// The file is written atomically once generation has succeeded.
+    /// When generating with --all, this is the directory that receives one file per CRD and a mod.rs.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// Write one file per CRD kind into this directory along with a mod.rs
+    ///
+    /// Files are only rewritten when their contents change.
+    #[arg(long, conflicts_with("output"))]
+    out_dir: Option<PathBuf>,
+
+    /// List the structs that would be generated and their member counts, without generating code
+    ///
+    /// The listing is sorted so it can be diffed between releases of a CRD.
+    #[arg(long, conflicts_with_all(["output", "out_dir"]))]
+    dry_run: bool,
+
+    /// Format of the output
+    ///
+    /// `json` writes the structs and enums that would be generated for each CRD version, with the names of the CRD,
+    /// as a json document for other generators to build on. Its layout is kept stable between releases.
+    /// Struct members carry their Rust name and type, their property, their docs, whether they are an Option
+    /// and whether the property is required. Enum variants carry their Rust name, value and type, if any.
+    /// It is written to stdout or --output, so it cannot be used with --all, which writes Rust modules.
+    #[arg(long, value_enum, default_value_t, conflicts_with_all(["all", "out_dir", "dry_run"]))]
+    emit: Emit,
 
     /// Point to the location of a CRD to use on disk
     #[arg(long = "filename", short, conflicts_with("crd"))]
     file: Option<PathBuf>,
 
+    /// Path to the kubeconfig file to use instead of the inferred one
+    #[arg(long, conflicts_with("file"))]
+    kubeconfig: Option<PathBuf>,
+
+    /// Name of the kubeconfig context to use instead of the current context
+    #[arg(long, conflicts_with("file"))]
+    context: Option<String>,
+
+    /// Url of the apiserver to connect to, bypassing kubeconfig discovery
+    ///
+    /// Requires --token, and optionally takes --ca-cert.
+    #[arg(long, conflicts_with_all(["file", "kubeconfig", "context"]))]
+    server: Option<String>,
+
+    /// Bearer token to authenticate against the --server with
+    ///
+    /// Prefer passing it through KOPIUM_TOKEN, so it stays out of shell history.
+    /// It requires --server, while an exported KOPIUM_TOKEN is ignored without one.
+    /// It is never written into the generated code.
+    #[arg(long, env = "KOPIUM_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// Path to the certificate authority bundle for the --server
+    #[arg(long, conflicts_with_all(["file", "kubeconfig", "context"]))]
+    ca_cert: Option<PathBuf>,
+
     /// Use this CRD version if multiple versions are present
-    #[arg(long)]
+    ///
+    /// The version must be one of the names in the CRD's `spec.versions`.
+    #[arg(long, visible_alias = "crd-version")]
     api_version: Option<String>,
 
+    /// Generate every version of the CRD into its own module
+    ///
+    /// Each version is emitted as `pub mod <version> { ... }` with its own prelude.
+    /// Versions without a schema are skipped with a warning.
+    #[arg(long, conflicts_with("api_version"))]
+    all_versions: bool,
+
+    /// Increase logging verbosity (-v info, -vv debug, -vvv trace)
+    ///
+    /// RUST_LOG takes precedence when set.
+    #[arg(long, short, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only log errors, suppressing analysis warnings and the generation summary
+    #[arg(long, short, conflicts_with("verbose"), global = true)]
+    quiet: bool,
+
+    /// Exit with an error if analysis raised any warnings
+    ///
+    /// This includes notices such as maps inlined in place of a struct, along with lossy types.
+    /// Generation still runs to completion, and every warning is reported before exiting.
+    #[arg(long)]
+    deny_warnings: bool,
+
     /// Do not emit prelude
+    ///
+    /// Helpers that generated code refers to, such as the `kopium_duration` module, are still emitted.
     #[arg(long)]
     hide_prelude: bool,
 
+    /// Do not format the generated code
+    ///
+    /// Useful for debugging kopium, as the output is emitted exactly as it was generated.
+    #[arg(long)]
+    no_format: bool,
+
     /// Do not derive CustomResource nor set kube-derive attributes
     ///
     /// If this is set, it makes any kube-derive specific options such as `--schema` unnecessary
@@ -39,13 +150,39 @@ struct Kopium {
     hide_kube: bool,
 
     /// Emit doc comments from descriptions
-    #[arg(long, short)]
+    ///
+    /// Descriptions keep their line breaks, and long lines are wrapped.
+    #[arg(long, short, overrides_with = "no_docs")]
     docs: bool,
 
+    /// Do not emit doc comments, e.g. to leave them out of --auto
+    #[arg(long, overrides_with = "docs")]
+    no_docs: bool,
+
     /// Emit builder derives via the typed_builder crate
     #[arg(long, short)]
     builders: bool,
 
+    /// Mark fields whose description starts with "Deprecated:" as #[deprecated]
+    ///
+    /// This makes code using those fields warn, so it is opt-in.
+    #[arg(long)]
+    deprecated_attr: bool,
+
+    /// Derive Validate from this crate on structs, checking constraints of the schema such as minimum and pattern
+    ///
+    /// Constraints the crate cannot check are documented instead, such as patterns with validator.
+    /// Members of struct types are validated recursively.
+    #[arg(long, value_enum)]
+    validation: Option<ValidationArg>,
+
+    /// Generate a `validations()` function on structs returning their CEL rules along with their messages
+    ///
+    /// The rules come from `x-kubernetes-validations`, and are documented with --docs either way.
+    /// Rules of the whole object go on the type generated by kube.
+    #[arg(long)]
+    cel_stubs: bool,
+
     /// Schema mode to use for kube-derive
     ///
     /// The default is --schema=disabled and will compile without a schema,
@@ -56,6 +193,8 @@ struct Kopium {
     ///
     /// --schema=derived implies `--derive JsonSchema`. The resulting schema will compile without external user action.
     /// The crd via `CustomResourceExt::crd()` can be applied into Kubernetes directly.
+    /// Members whose types would derive a schema other than the declared one, such as preserved unknown fields,
+    /// take the declared schema from functions in a generated `schemas` module.
     #[arg(
         long,
         default_value = "disabled",
@@ -71,13 +210,16 @@ struct Kopium {
     ///    the custom resource definition: `--derive PartialEq`
     ///
     /// 2. Constraining the derivation to a singular struct or enum:
-    ///    `--derive IssuerAcmeSolversDns01CnameStrategy=PartialEq`
+    ///    `--derive IssuerAcmeSolverDns01CnameStrategy=PartialEq`
     ///
     /// 3. Constraining the derivation to only structs (@struct), enums (@enum) or *unit-only* enums (@enum:simple),
     ///    meaning enums where no variants are tuple or structs:
     ///    `--derive @struct=PartialEq`, `--derive @enum=PartialEq`, `--derive @enum:simple=PartialEq`
     ///
     /// See also: https://doc.rust-lang.org/reference/items/enumerations.html
+    ///
+    /// Default, Eq and Hash are skipped for objects that cannot implement them, such as structs holding floats
+    /// for Eq, along with the objects holding those. Run with -vv to see why an object was skipped.
     #[arg(long,
         short = 'D',
         value_parser = Derive::from_str,
@@ -107,7 +249,7 @@ struct Kopium {
     /// Relaxed interpretation
     ///
     /// This allows certain invalid openapi specs to be interpreted as arbitrary objects as used by argo workflows for example.
-    /// the output first.
+    /// Unsupported types fall back to serde_json::Value with a warning instead of aborting generation.
     #[arg(long)]
     relaxed: bool,
 
@@ -118,15 +260,144 @@ struct Kopium {
     #[arg(long)]
     no_condition: bool,
 
-    /// Type used to represent maps via additionalProperties
+    /// Use unsigned integers for integers declaring a non-negative minimum
+    ///
+    /// Integers with `minimum: 0` (or above) become u32 or u64 depending on their format.
+    /// This is opt-in, as serde rejects negative values the apiserver accepts if the minimum is only advisory.
+    #[arg(long)]
+    prefer_unsigned: bool,
+
+    /// Use chrono types for strings with a date-time or date format
+    ///
+    /// The generated code then requires the chrono crate with its serde feature.
+    #[arg(long)]
+    chrono: bool,
+
+    /// Type used for strings with a duration format
+    ///
+    /// std::time::Duration fields are (de)serialized from Go duration strings such as "1h30m"
+    /// through a `kopium_duration` module that is generated along with them.
+    #[arg(long, value_enum, default_value_t)]
+    duration_as: DurationTypeArg,
+
+    /// Use String instead of IntOrString for integer-or-string fields
+    ///
+    /// Integers in such fields then fail to deserialize, so this only keeps older generated code working.
+    #[arg(long = "coerce-intorstring-to-string")]
+    coerce_int_or_string: bool,
+
+    /// Give optional fields with a schema default that value when they are missing, instead of an Option
+    ///
+    /// Objects the apiserver has not defaulted yet (e.g. read from files) then deserialize with their defaults,
+    /// which come from functions in a generated `defaults` module. This changes the types of those fields.
+    #[arg(long)]
+    schema_defaults: bool,
+
+    /// Make deserialization fail on fields the schema does not declare, e.g. to catch typos in manifests
+    ///
+    /// Structs that keep unknown fields, from `x-kubernetes-preserve-unknown-fields`, accept them as before.
+    #[arg(long)]
+    deny_unknown_fields: bool,
+
+    /// Represent lists with x-kubernetes-list-type=map as maps from their key field to their items
+    ///
+    /// Only lists keyed by a single string field become a `BTreeMap<String, T>`, whose items leave out the key.
+    /// They are (de)serialized from the list through a `kopium_list_map` module that is generated along with them.
+    /// Schemas derived through schemars keep describing these fields as lists.
+    #[arg(long)]
+    list_map_as_map: bool,
+
+    /// Represent lists of unique scalars as BTreeSets
+    ///
+    /// Applies to lists with x-kubernetes-list-type=set or uniqueItems, whose items are strings, integers or booleans.
+    /// Sets are serialized in order, and cannot hold the duplicates the apiserver would reject.
+    #[arg(long)]
+    sets: bool,
+
+    /// Name structs for array items and map values after their exact key
+    ///
+    /// By default, kopium uses the singular of the key, e.g. `PrometheusRuleGroup` for the items of `groups`.
+    #[arg(long)]
+    no_singularize: bool,
+
+    /// Generate structurally identical structs and enums separately
+    ///
+    /// By default, kopium generates repeated schemas (e.g. label selectors) once, under the shortest of their names.
+    #[arg(long)]
+    no_dedup: bool,
+
+    /// Name structs for objects with a schema title after the title alone
+    ///
+    /// By default, nested objects with a title such as `EmbeddedObjectMeta` are named `{Kind}EmbeddedObjectMeta`.
+    #[arg(long)]
+    no_title_prefix: bool,
+
+    /// Keep these acronyms uppercase in struct and enum names, e.g. `--acronyms=TLS,URL` for `IssuerTLSConfig`
+    ///
+    /// Without a value, common Kubernetes acronyms such as DNS, HTTP, IP and TLS are used.
+    /// By default, every word in a name only starts with an uppercase letter, e.g. `IssuerTlsConfig`.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        num_args = 0..,
+        require_equals = true,
+        default_missing_value = COMMON_ACRONYMS
+    )]
+    acronyms: Vec<String>,
+
+    /// Strategy for naming generated structs and enums
+    ///
+    /// `full-path` names every struct after all property keys from the root, e.g. `PrometheusSpecAlertingAlertmanagers`
+    /// for the items of `.spec.alerting.alertmanagers`, keeping the `Spec` infix and the plural of the key.
+    /// Schema titles and repeated words are ignored, and repeated schemas are generated separately (as with --no-dedup),
+    /// so that names only change with the path they are generated for.
     #[arg(long, value_enum, default_value_t)]
-    map_type: MapType,
+    naming: NamingArg,
 
-    /// Automatically removes #[derive(Default)] from structs that contain fields for which a default can not be automatically derived.
+    /// Prefix generated struct and enum names with this instead of the kind
     ///
-    /// This option only has an effect if `--derive Default` is set.
+    /// May be empty, e.g. `--struct-prefix ""` for a `Spec` and `Status` pair.
+    /// The kube attributes still carry the kind, which also names the struct generated by kube.
     #[arg(long)]
+    struct_prefix: Option<String>,
+
+    /// Visibility of generated structs, enums and fields
+    ///
+    /// With `pub-crate`, the spec and a typed status stay `pub` unless using --hide-kube,
+    /// as kube gives the type it generates from them the visibility of the spec.
+    #[arg(long, value_enum, default_value_t)]
+    visibility: VisibilityArg,
+
+    /// Type used for objects embedding a Kubernetes resource (x-kubernetes-embedded-resource)
+    #[arg(long, value_enum, default_value_t)]
+    embedded_as: EmbeddedTypeArg,
+
+    /// Type used to represent maps via additionalProperties
+    ///
+    /// `IndexMap` keeps keys in the order they were read, avoiding reordered diffs when round-tripping,
+    /// but needs the indexmap crate with its `serde` feature (and schemars with `indexmap2` to derive JsonSchema).
+    #[arg(long, value_enum, default_value_t)]
+    map_type: MapTypeArg,
+
+    /// Implement these traits on generated enums, based on their schema values
+    ///
+    /// `display` writes the value as it appears in the schema,
+    /// and `fromstr` parses it case-sensitively, failing with a generated ParseEnumError.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    enum_impls: Vec<EnumImplArg>,
+
+    /// Deprecated, and kept for compatibility, as `--derive Default` is only applied to structs and enums where it is sound
+    ///
+    /// Setting it logs a warning.
+    #[arg(long, hide = true)]
     smart_derive_elision: bool,
+
+    #[arg(skip)]
+    diagnostics: Diagnostics,
+
+    /// Whether --token was taken from KOPIUM_TOKEN rather than given on the command line
+    #[arg(skip)]
+    token_from_env: bool,
 }
 
 #[derive(Clone, Copy, Debug, Subcommand)]
@@ -134,16 +405,162 @@ struct Kopium {
 enum Command {
     #[command(about = "List available CRDs", hide = true)]
     ListCrds,
-    #[command(about = "Generate completions", hide = true)]
+    #[command(about = "Generate shell completions and write them to stdout")]
     Completions {
         #[arg(help = "The shell to generate completions for")]
         shell: clap_complete::Shell,
     },
 }
 
+/// Formats of the generated output
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum Emit {
+    #[default]
+    Rust,
+    Json,
+}
+
+// Values of the options taking a library enum, mapped onto it with From
+
+/// Traits that can be implemented on generated enums
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum EnumImplArg {
+    Display,
+    #[value(name = "fromstr")]
+    FromStr,
+}
+
+impl From<EnumImplArg> for EnumImpl {
+    fn from(arg: EnumImplArg) -> Self {
+        match arg {
+            EnumImplArg::Display => EnumImpl::Display,
+            EnumImplArg::FromStr => EnumImpl::FromStr,
+        }
+    }
+}
+
+/// Type used for additionalProperties maps
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug)]
+#[clap(rename_all = "PascalCase")]
+#[allow(clippy::enum_variant_names)] // the variants are the names of the types
+enum MapTypeArg {
+    #[default]
+    BTreeMap,
+    HashMap,
+    /// Keeps the order of the keys, needing the indexmap crate with its serde feature
+    IndexMap,
+}
+
+impl From<MapTypeArg> for MapType {
+    fn from(arg: MapTypeArg) -> Self {
+        match arg {
+            MapTypeArg::BTreeMap => MapType::BTreeMap,
+            MapTypeArg::HashMap => MapType::HashMap,
+            MapTypeArg::IndexMap => MapType::IndexMap,
+        }
+    }
+}
+
+/// Type used for strings with a duration format
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq)]
+enum DurationTypeArg {
+    #[default]
+    #[value(name = "String")]
+    String,
+    /// std::time::Duration (de)serialized from Go duration strings
+    #[value(name = "std::time::Duration")]
+    Duration,
+}
+
+impl From<DurationTypeArg> for DurationType {
+    fn from(arg: DurationTypeArg) -> Self {
+        match arg {
+            DurationTypeArg::String => DurationType::String,
+            DurationTypeArg::Duration => DurationType::Duration,
+        }
+    }
+}
+
+/// Type used for objects embedding a Kubernetes resource
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq)]
+#[clap(rename_all = "PascalCase")]
+enum EmbeddedTypeArg {
+    /// k8s_openapi's RawExtension, holding the resource as arbitrary json
+    #[default]
+    RawExtension,
+    /// kube's DynamicObject, with typed metadata
+    DynamicObject,
+}
+
+impl From<EmbeddedTypeArg> for EmbeddedType {
+    fn from(arg: EmbeddedTypeArg) -> Self {
+        match arg {
+            EmbeddedTypeArg::RawExtension => EmbeddedType::RawExtension,
+            EmbeddedTypeArg::DynamicObject => EmbeddedType::DynamicObject,
+        }
+    }
+}
+
+/// Strategy for naming the generated structs and enums
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq)]
+enum NamingArg {
+    /// Short names, e.g. singular for list items and taken from schema titles
+    #[default]
+    Short,
+    /// Names joining every property key from the root, which stay the same as other parts of the schema change
+    FullPath,
+}
+
+impl From<NamingArg> for Naming {
+    fn from(arg: NamingArg) -> Self {
+        match arg {
+            NamingArg::Short => Naming::Short,
+            NamingArg::FullPath => Naming::FullPath,
+        }
+    }
+}
+
+/// Visibility of the generated structs, enums and fields
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq)]
+enum VisibilityArg {
+    #[default]
+    Pub,
+    PubCrate,
+}
+
+impl From<VisibilityArg> for Visibility {
+    fn from(arg: VisibilityArg) -> Self {
+        match arg {
+            VisibilityArg::Pub => Visibility::Pub,
+            VisibilityArg::PubCrate => Visibility::PubCrate,
+        }
+    }
+}
+
+/// Crate deriving `Validate` on generated structs, to check the constraints of their schemas
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ValidationArg {
+    /// The garde crate, which needs its regex feature for patterns
+    Garde,
+    /// The validator crate, which cannot check patterns
+    Validator,
+}
+
+impl From<ValidationArg> for Validation {
+    fn from(arg: ValidationArg) -> Self {
+        match arg {
+            ValidationArg::Garde => Validation::Garde,
+            ValidationArg::Validator => Validation::Validator,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
+    let matches = Kopium::command().get_matches();
+    let mut args = Kopium::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    args.token_from_env = matches.value_source("token") == Some(ValueSource::EnvVariable);
+    init_logger(args.verbose, args.quiet);
     // Ignore SIGPIPE errors to avoid having to use let _ = write! everywhere
     // See https://github.com/rust-lang/rust/issues/46016
     #[cfg(unix)]
@@ -151,9 +568,11 @@ async fn main() -> Result<()> {
         libc::signal(libc::SIGPIPE, libc::SIG_DFL);
     }
 
-    let mut args = Kopium::parse();
+    if args.smart_derive_elision {
+        warn!("--smart-derive-elision is deprecated and has no effect, as Default is only derived where it is sound");
+    }
     if args.auto {
-        args.docs = true;
+        args.docs = !args.no_docs;
         args.schema = "derived".into();
     }
     if args.schema == "derived" {
@@ -164,7 +583,34 @@ async fn main() -> Result<()> {
         }
     }
 
-    args.dispatch().await
+    let res = args.dispatch().await;
+    let warnings = args.diagnostics.report();
+    res?;
+    if args.deny_warnings && warnings > 0 {
+        bail!(
+            "{} warnings raised during analysis with --deny-warnings",
+            warnings
+        );
+    }
+    Ok(())
+}
+
+fn log_filter(verbose: u8, quiet: bool) -> log::LevelFilter {
+    match verbose {
+        _ if quiet => log::LevelFilter::Error,
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    }
+}
+
+fn init_logger(verbose: u8, quiet: bool) {
+    env_logger::Builder::new()
+        .filter_level(log_filter(verbose, quiet))
+        // RUST_LOG overrides the verbosity flag if set
+        .parse_env(env_logger::Env::default())
+        .init();
 }
 
 fn get_stdin_data() -> Result<String> {
@@ -175,150 +621,436 @@ fn get_stdin_data() -> Result<String> {
     Ok(input)
 }
 
+fn parse_crds(data: &str, source: &str) -> Result<Vec<CustomResourceDefinition>> {
+    use serde::Deserialize;
+    if data.trim().is_empty() {
+        bail!("{} is empty", source);
+    }
+    // json is a subset of yaml, but sniffing lets us report errors from the more plausible parser
+    let documents: Vec<serde_yaml::Value> = if data.trim_start().starts_with('{') {
+        vec![serde_json::from_str(data).with_context(|| format!("{} is not valid json", source))?]
+    } else {
+        serde_yaml::Deserializer::from_str(data)
+            .map(serde_yaml::Value::deserialize)
+            .collect::<Result<_, _>>()
+            .with_context(|| format!("{} is not valid yaml", source))?
+    };
+
+    let mut crds = vec![];
+    let mut skipped = 0;
+    for doc in documents {
+        if doc.is_null() {
+            continue; // empty document between separators
+        }
+        match doc.get("kind").and_then(|k| k.as_str()) {
+            Some("CustomResourceDefinition") => {
+                let crd = serde_yaml::from_value(doc)
+                    .with_context(|| format!("{} is not a CustomResourceDefinition", source))?;
+                crds.push(crd);
+            }
+            Some(kind) if kind.ends_with("List") => {
+                bail!(
+                    "{} contains a {}, expected a single CustomResourceDefinition",
+                    source,
+                    kind
+                );
+            }
+            _ => skipped += 1,
+        }
+    }
+    debug!("skipped {} non-CRD documents in {}", skipped, source);
+    if crds.is_empty() {
+        bail!("{} does not contain a CustomResourceDefinition", source);
+    }
+    Ok(crds)
+}
+
 impl Kopium {
     async fn dispatch(&self) -> Result<()> {
-        if let Some(name) = self.crd.as_deref() {
-            let api = Client::try_default()
-                .await
-                .map(Api::<CustomResourceDefinition>::all)?;
-            let crd = api.get(name).await?;
-            self.generate(crd).await
+        if !self.crd.is_empty() {
+            let api = Api::<CustomResourceDefinition>::all(self.client().await?);
+            let mut crds = vec![];
+            for name in &self.crd {
+                match api.get(name).await {
+                    Ok(crd) => crds.push(crd),
+                    Err(kube::Error::Api(e)) if e.code == 404 => {
+                        // only list on the error path so the happy path stays a single GET
+                        let lp = api::ListParams::default();
+                        let names = api
+                            .list(&lp)
+                            .await?
+                            .items
+                            .iter()
+                            .map(|c| c.name_any())
+                            .collect::<Vec<_>>();
+                        let suggestions = similar_names(name, &names);
+                        if suggestions.is_empty() {
+                            bail!("CRD '{}' not found", name);
+                        }
+                        bail!(
+                            "CRD '{}' not found\ndid you mean: {}?",
+                            name,
+                            suggestions.join(", ")
+                        );
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            self.emit(&crds)
+        } else if self.all {
+            let api = Api::<CustomResourceDefinition>::all(self.client().await?);
+            let lp = api::ListParams::default();
+            let crds = api.list(&lp).await?.items;
+            if self.dry_run {
+                return self.emit(&crds);
+            }
+            let Some(dir) = self.out_dir.as_deref().or(self.output.as_deref()) else {
+                bail!("--all requires --out-dir to write into");
+            };
+            self.generate_dir(dir, &crds)
+        } else if let Some(group) = self.group.as_deref() {
+            let api = Api::<CustomResourceDefinition>::all(self.client().await?);
+            let lp = api::ListParams::default();
+            let crds = api
+                .list(&lp)
+                .await?
+                .items
+                .into_iter()
+                .filter(|crd| crd.spec.group == group)
+                .collect::<Vec<_>>();
+            if crds.is_empty() {
+                bail!("No CRDs found in group '{}'", group);
+            }
+            self.emit(&crds)
         } else if let Some(f) = self.file.as_deref() {
             // no cluster access needed in this case
-            let data = if f.to_string_lossy() == "-" {
-                get_stdin_data().with_context(|| "Failed to read from stdin".to_string())?
+            let (data, source) = if f.to_string_lossy() == "-" {
+                if std::io::stdin().is_terminal() {
+                    eprintln!("kopium: reading CRD from stdin (end input with Ctrl-D)");
+                }
+                let data = get_stdin_data().with_context(|| "Failed to read from stdin".to_string())?;
+                (data, "stdin".to_string())
             } else {
-                std::fs::read_to_string(f).with_context(|| format!("Failed to read {}", f.display()))?
+                if !f.exists() {
+                    bail!("File {} not found", f.display());
+                }
+                let data =
+                    std::fs::read_to_string(f).with_context(|| format!("Failed to read {}", f.display()))?;
+                (data, f.display().to_string())
             };
 
-            let crd: CustomResourceDefinition = serde_yaml::from_str(&data)?;
-            self.generate(crd).await
+            let crds = parse_crds(&data, &source)?;
+            self.emit(&crds)
         } else if let Some(command) = self.command {
             match command {
                 Command::ListCrds => {
-                    let api = Client::try_default()
-                        .await
-                        .map(Api::<CustomResourceDefinition>::all)?;
+                    let api = Api::<CustomResourceDefinition>::all(self.client().await?);
                     self.list_crds(api).await
                 }
-                Command::Completions { shell } => self.completions(shell),
+                Command::Completions { shell } => self.completions(&mut std::io::stdout(), shell),
             }
         } else {
             self.help()
         }
     }
 
-    async fn generate(&self, crd: CustomResourceDefinition) -> Result<()> {
-        let version = self.api_version.as_deref();
-        let version = find_crd_version(&crd, version)?;
+    /// Client from --kubeconfig / --context, or the usual env/in-cluster inference if neither is set
+    async fn client(&self) -> Result<Client> {
+        // a token from an exported KOPIUM_TOKEN is left unused without a server
+        let token = self
+            .token
+            .as_deref()
+            .filter(|_| self.server.is_some() || !self.token_from_env);
+        if let Some(kubeconfig) = static_kubeconfig(self.server.as_deref(), token, self.ca_cert.as_deref())? {
+            let config =
+                kube::Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default()).await?;
+            return Ok(Client::try_from(config)?);
+        }
+        if self.kubeconfig.is_none() && self.context.is_none() {
+            return Ok(Client::try_default().await?);
+        }
+        let kubeconfig = if let Some(path) = self.kubeconfig.as_deref() {
+            Kubeconfig::read_from(path).with_context(|| format!("Failed to read {}", path.display()))?
+        } else {
+            Kubeconfig::read()?
+        };
+        if let Some(context) = self.context.as_deref() {
+            if !kubeconfig.contexts.iter().any(|c| c.name == context) {
+                let available = kubeconfig
+                    .contexts
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<_>>();
+                bail!(
+                    "Context '{}' not found in kubeconfig\navailable contexts are '{}'",
+                    context,
+                    available.join(", ")
+                );
+            }
+        }
+        let options = KubeConfigOptions {
+            context: self.context.clone(),
+            ..KubeConfigOptions::default()
+        };
+        let config = kube::Config::from_custom_kubeconfig(kubeconfig, &options).await?;
+        Ok(Client::try_from(config)?)
+    }
+
+    /// Generate into --out-dir or --output if set, or stdout otherwise
+    fn emit(&self, crds: &[CustomResourceDefinition]) -> Result<()> {
+        if self.dry_run {
+            self.dry_run(&mut std::io::stdout(), crds)
+        } else if let Some(dir) = self.out_dir.as_deref() {
+            self.generate_dir(dir, crds)
+        } else if let Some(path) = self.output.as_deref() {
+            let mut buf = vec![];
+            self.generate(&mut buf, crds)?;
+            write_atomic(path, &buf)
+        } else {
+            self.generate(&mut std::io::stdout(), crds)
+        }
+    }
+
+    /// Generate code for the CRDs, formatted unless --no-format is set
+    fn generate(&self, w: &mut impl Write, crds: &[CustomResourceDefinition]) -> Result<()> {
+        if self.emit == Emit::Json {
+            return self.print_json(w, crds);
+        }
+        let mut buf = vec![];
+        self.render(&mut buf, crds)?;
+        let code = String::from_utf8(buf)?;
+        // comments are not preserved by the formatter, so the header goes out separately
+        self.print_generation_warning(w)?;
+        if self.no_format {
+            w.write_all(code.as_bytes())?;
+        } else {
+            w.write_all(format_code(&code).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn render(&self, w: &mut impl Write, crds: &[CustomResourceDefinition]) -> Result<()> {
+        let options = self.render_options();
+        if self.all_versions {
+            if crds.len() > 1 {
+                bail!("--all-versions only supports generating a single CRD");
+            }
+            for generated in self.analyze_versions(crds) {
+                let generated = generated?;
+                writeln!(w, "pub mod {} {{", generated.version.name)?;
+                w.write_all(render(&[generated], &options)?.as_bytes())?;
+                writeln!(w, "}}")?;
+            }
+            return Ok(());
+        }
+
+        let generated = self.analyze_versions(crds).collect::<Result<Vec<_>>>()?;
+        w.write_all(render(&generated, &options)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Generate one self-contained file per CRD into `dir` along with a `mod.rs`
+    ///
+    /// Failures on individual CRDs are reported, but do not abort generation of the remaining CRDs.
+    fn generate_dir(&self, dir: &Path, crds: &[CustomResourceDefinition]) -> Result<()> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let mut modules = vec![];
+        let mut failures = vec![];
+        for (crd, module) in crds.iter().zip(module_names(crds)) {
+            let mut buf = vec![];
+            if let Err(e) = self.generate(&mut buf, std::slice::from_ref(crd)) {
+                error!("Failed to generate {}: {:#}", crd.name_any(), e);
+                failures.push(crd.name_any());
+                continue;
+            }
+            write_if_changed(&dir.join(format!("{}.rs", module)), &buf)?;
+            modules.push((module, &crd.spec.names.kind));
+        }
+
+        let mut mod_rs =
+            String::from("// WARNING: generated by kopium - manual changes will be overwritten\n");
+        for (module, _) in &modules {
+            mod_rs.push_str(&format!("pub mod {};\n", module));
+        }
+        // re-export root types unless they would be ambiguous
+        if !self.hide_kube && !self.all_versions {
+            for (module, kind) in &modules {
+                if modules.iter().filter(|(_, k)| k == kind).count() == 1 {
+                    mod_rs.push_str(&format!("pub use self::{}::{};\n", module, kind));
+                }
+            }
+        }
+        write_if_changed(&dir.join("mod.rs"), mod_rs.as_bytes())?;
+
+        if !failures.is_empty() {
+            bail!(
+                "Failed to generate {} CRDs: {}",
+                failures.len(),
+                failures.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// List the structs that would be generated for each CRD version, sorted by name
+    fn dry_run(&self, w: &mut impl Write, crds: &[CustomResourceDefinition]) -> Result<()> {
+        let options = self.render_options();
+        let mut crds = crds.iter().collect::<Vec<_>>();
+        crds.sort_by_key(|crd| crd.name_any());
+        for generated in self.analyze_versions(crds) {
+            let description = describe(&generated?, &options);
+            let storage = if description.storage { " (storage)" } else { "" };
+            writeln!(w, "{} {}{}", description.name, description.version, storage)?;
+            let mut listed = description
+                .types
+                .iter()
+                .map(|t| {
+                    let members =
+                        t.members.as_ref().map_or(0, Vec::len) + t.variants.as_ref().map_or(0, Vec::len);
+                    (&t.name, members)
+                })
+                .collect::<Vec<_>>();
+            listed.sort();
+            for (name, members) in listed {
+                writeln!(w, "    {} ({} members)", name, members)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write what would be generated for each CRD version as json, see --emit
+    fn print_json(&self, w: &mut impl Write, crds: &[CustomResourceDefinition]) -> Result<()> {
+        let options = self.render_options();
+        let generated = self
+            .analyze_versions(crds)
+            .map(|generated| Ok(describe(&generated?, &options)))
+            .collect::<Result<Vec<_>>>()?;
+        serde_json::to_writer_pretty(&mut *w, &generated)?;
+        writeln!(w)?;
+        Ok(())
+    }
+
+    /// Analyze the versions to generate of each CRD, reporting their summaries and warnings along the way
+    ///
+    /// These are all versions with a schema with --all-versions, and the one picked by [`find_crd_version`] otherwise.
+    fn analyze_versions<'a>(
+        &'a self,
+        crds: impl IntoIterator<Item = &'a CustomResourceDefinition> + 'a,
+    ) -> impl Iterator<Item = Result<Analyzed<'a>>> + 'a {
+        crds.into_iter()
+            .flat_map(move |crd| {
+                let versions = if self.all_versions {
+                    schema_versions(crd).into_iter().map(Ok).collect()
+                } else {
+                    vec![find_crd_version(crd, self.api_version.as_deref())]
+                };
+                versions
+                    .into_iter()
+                    .map(move |version| version.map(|version| (crd, version)))
+            })
+            .map(move |picked| {
+                let (crd, version) = picked?;
+                let Output { containers, warnings } = self.analyze_version(crd, version)?;
+                self.print_summary(crd, version, &containers, &warnings);
+                self.diagnostics.record(&crd.name_any(), warnings);
+                Ok(Analyzed {
+                    crd,
+                    version,
+                    containers,
+                })
+            })
+    }
+
+    fn analyze_version(
+        &self,
+        crd: &CustomResourceDefinition,
+        version: &CustomResourceDefinitionVersion,
+    ) -> Result<Output> {
         let data = version
             .schema
             .as_ref()
             .and_then(|schema| schema.open_api_v3_schema.clone());
-        let version_name = version.name.clone();
-
         let kind = &crd.spec.names.kind;
-        let plural = &crd.spec.names.plural;
-        let group = &crd.spec.group;
-        let scope = &crd.spec.scope;
-
-        self.print_generation_warning();
 
         let Some(schema) = data else {
-            anyhow::bail!("no schema found for crd");
+            bail!(
+                "no schema found for crd {} version {}",
+                crd.name_any(),
+                version.name
+            );
         };
         log::debug!("schema: {}", serde_json::to_string_pretty(&schema)?);
         let cfg = Config {
             no_condition: self.no_condition,
-            map: self.map_type,
+            map: self.map_type.into(),
             relaxed: self.relaxed,
+            prefer_unsigned: self.prefer_unsigned,
+            chrono: self.chrono,
+            duration: self.duration_as.into(),
+            embedded: self.embedded_as.into(),
+            coerce_int_or_string: self.coerce_int_or_string,
+            no_singularize: self.no_singularize,
+            no_title_prefix: self.no_title_prefix,
+            acronyms: self.acronyms.clone(),
+            naming: self.naming.into(),
+            struct_prefix: self.struct_prefix.clone(),
+            schema_defaults: self.schema_defaults,
+            constraints: self.validation.is_some(),
+            list_map_as_map: self.list_map_as_map,
+            sets: self.sets,
+            schemas: self.derive.iter().any(|d| d.derived_trait == "JsonSchema"),
         };
-        let structs = analyze(schema, kind, cfg)?
+        let output = analyze(schema, kind, cfg)?
+            .dedup(!self.no_dedup && self.naming == NamingArg::Short)
             .rename()
+            .deny_unknown_fields(self.deny_unknown_fields)
+            .default_fns()
+            .schema_fns(&self.derive)
             .builder_fields(self.builders)
-            .0;
+            .deprecated_fields(self.deprecated_attr)
+            .validation_fields(self.validation.map(Into::into));
+        Ok(output)
+    }
 
-        if !self.hide_prelude {
-            self.print_prelude(&structs);
+    /// Options of the library renderer, from the flags for code generation
+    fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            docs: self.docs,
+            hide_prelude: self.hide_prelude,
+            hide_kube: self.hide_kube,
+            builders: self.builders,
+            validation: self.validation.map(Into::into),
+            cel_stubs: self.cel_stubs,
+            schema: self.schema.clone(),
+            derive: self.derive.clone(),
+            elide: self.elide.clone(),
+            no_condition: self.no_condition,
+            naming: self.naming.into(),
+            struct_prefix: self.struct_prefix.clone(),
+            visibility: self.visibility.into(),
+            enum_impls: self.enum_impls.iter().copied().map(Into::into).collect(),
         }
+    }
 
-        for s in &structs {
-            if s.level == 0 {
-                continue; // ignoring root struct
-            }
-            if self.elide.contains(&s.name) {
-                debug!("eliding {} from the output", s.name);
-                continue;
-            }
-            self.print_docstr(&s.docs, "");
-            if s.is_main_container() {
-                self.print_derives(s, &structs);
-                //root struct gets kube derives unless opted out
-                if !self.hide_kube {
-                    println!(
-                        r#"#[kube(group = "{}", version = "{}", kind = "{}", plural = "{}")]"#,
-                        group, version_name, kind, plural
-                    );
-                    if scope == "Namespaced" {
-                        println!(r#"#[kube(namespaced)]"#);
-                    }
-                    if version.subresources.as_ref().is_some_and(|c| c.status.is_some())
-                        && self.has_status_resource(&structs)
-                    {
-                        println!(r#"#[kube(status = "{}Status")]"#, kind);
-                    }
-                    if self.schema != "derived" {
-                        println!(r#"#[kube(schema = "{}")]"#, self.schema);
-                    }
-                    for derive in &self.derive {
-                        if derive.derived_trait == "JsonSchema" {
-                            continue;
-                        }
-                        if derive.derived_trait == "Default"
-                            && self.smart_derive_elision
-                            && !s.can_derive_default(&structs)
-                        {
-                            continue;
-                        }
-                        println!(r#"#[kube(derive="{}")]"#, derive.derived_trait);
-                    }
-                }
-                if s.is_enum {
-                    println!("pub enum {} {{", s.name);
-                } else {
-                    println!("pub struct {} {{", s.name);
-                }
-            } else {
-                self.print_derives(s, &structs);
-                let spec_trimmed_name = s.name.as_str().replace(&format!("{}Spec", kind), kind);
-                if s.is_enum {
-                    println!("pub enum {} {{", spec_trimmed_name);
-                } else {
-                    println!("pub struct {} {{", spec_trimmed_name);
-                }
-            }
-            for m in &s.members {
-                self.print_docstr(&m.docs, "    ");
-                if !m.serde_annot.is_empty() {
-                    println!("    #[serde({})]", m.serde_annot.join(", "));
-                }
-                let name = format_ident!("{}", m.name);
-                for annot in &m.extra_annot {
-                    println!("    {}", annot);
-                }
-                let spec_trimmed_type = m.type_.as_str().replace(&format!("{}Spec", kind), kind);
-                if s.is_enum {
-                    // NB: only supporting plain enumerations atm, not oneOf
-                    println!("    {},", name);
-                } else {
-                    println!("    pub {}: {},", name, spec_trimmed_type);
-                }
-            }
-            println!("}}");
-            println!();
+    /// Print what was generated for a CRD version to stderr, unless --quiet is set
+    fn print_summary(
+        &self,
+        crd: &CustomResourceDefinition,
+        version: &CustomResourceDefinitionVersion,
+        structs: &[Container],
+        warnings: &[Warning],
+    ) {
+        if self.quiet {
+            return;
         }
-
-        Ok(())
+        let storage = if version.storage { " (storage)" } else { "" };
+        eprintln!(
+            "kopium: {}: {}, version {}{}",
+            crd.name_any(),
+            Summary::new(structs, warnings),
+            version.name,
+            storage
+        );
     }
 
     async fn list_crds(&self, api: Api<CustomResourceDefinition>) -> Result<()> {
@@ -329,9 +1061,9 @@ impl Kopium {
         Ok(())
     }
 
-    fn completions(&self, shell: clap_complete::Shell) -> Result<()> {
+    fn completions(&self, w: &mut impl Write, shell: clap_complete::Shell) -> Result<()> {
         let mut command = Self::command();
-        clap_complete::generate(shell, &mut command, "kopium", &mut std::io::stdout());
+        clap_complete::generate(shell, &mut command, "kopium", w);
         Ok(())
     }
 
@@ -340,118 +1072,359 @@ impl Kopium {
         Ok(())
     }
 
-    fn print_docstr(&self, doc: &Option<String>, indent: &str) {
-        // print doc strings if requested in arguments
-        if self.docs {
-            if let Some(d) = doc {
-                println!("{}/// {}", indent, d.replace('\n', &format!("\n{}/// ", indent)));
-                // TODO: maybe logic to split doc strings by sentence / length here
+    fn print_generation_warning(&self, w: &mut impl Write) -> Result<()> {
+        writeln!(
+            w,
+            "// WARNING: generated by kopium - manual changes will be overwritten"
+        )?;
+        writeln!(
+            w,
+            "// kopium command: kopium {}",
+            redact_secrets(std::env::args().skip(1))
+        )?;
+        writeln!(w, "// kopium version: {}", clap::crate_version!())?;
+        if matches!(self.map_type, MapTypeArg::IndexMap) {
+            let schemars = if self
+                .derive
+                .iter()
+                .any(|derive| derive.derived_trait == "JsonSchema")
+            {
+                ", and schemars with its indexmap2 feature"
+            } else {
+                ""
+            };
+            writeln!(w, "// requires: indexmap with its serde feature{}", schemars)?;
+        }
+        writeln!(w)?;
+        Ok(())
+    }
+}
+
+/// The version of a CRD to generate, either the requested one or a default
+///
+/// The default is the served storage version with a schema, as that is the schema the cluster persists,
+/// and otherwise the last served version with a schema, which is commonly the newest.
+/// Served versions without a schema are skipped with a warning.
+fn find_crd_version<'a>(
+    crd: &'a CustomResourceDefinition,
+    version: Option<&str>,
+) -> Result<&'a CustomResourceDefinitionVersion> {
+    let versions = &crd.spec.versions;
+    if let Some(version) = version {
+        // pick specified version
+        versions.iter().find(|v| v.name == version).ok_or_else(|| {
+            anyhow!(
+                "Version '{}' not found in CRD '{}'\navailable versions are '{}'",
+                version,
+                crd.name_any(),
+                all_versions(crd)
+            )
+        })
+    } else {
+        // only consider versions the apiserver actually serves, and that we can generate code from
+        let mut served = vec![];
+        for v in versions {
+            if !v.served {
+                info!(
+                    "Skipping version '{}' of CRD '{}' as it is not served",
+                    v.name,
+                    crd.name_any()
+                );
+            } else if !has_schema(v) {
+                warn!(
+                    "Skipping version '{}' of CRD '{}' as it has no schema",
+                    v.name,
+                    crd.name_any()
+                );
+            } else {
+                served.push(v);
             }
         }
+        if let Some(storage) = served.iter().copied().find(|v| v.storage) {
+            // pick the storage version as that is the schema the cluster persists
+            info!(
+                "Using storage version '{}' of CRD '{}'",
+                storage.name,
+                crd.name_any()
+            );
+            Ok(storage)
+        } else if let Some(last_served) = served.last().copied() {
+            // no served storage version marked; fall back to the last served version
+            warn!(
+                "CRD '{}' has no served storage version, using last served version '{}'",
+                crd.name_any(),
+                last_served.name
+            );
+            Ok(last_served)
+        } else {
+            Err(anyhow!(
+                "CRD '{}' has no served versions with a schema\nversions seen are '{}'",
+                crd.name_any(),
+                all_versions(crd)
+            ))
+        }
     }
+}
 
-    fn print_derives(&self, s: &Container, containers: &[Container]) {
-        let mut derives = vec!["Serialize", "Deserialize", "Clone", "Debug"];
+/// The versions of a CRD to generate with --all-versions, which are all versions with a schema
+///
+/// Versions without a schema are skipped with a warning.
+fn schema_versions(crd: &CustomResourceDefinition) -> Vec<&CustomResourceDefinitionVersion> {
+    let (versions, skipped): (Vec<_>, Vec<_>) = crd.spec.versions.iter().partition(|v| has_schema(v));
+    for v in skipped {
+        warn!(
+            "Skipping version '{}' of CRD '{}' as it has no schema",
+            v.name,
+            crd.name_any()
+        );
+    }
+    versions
+}
 
-        if s.is_main_container() && !self.hide_kube {
-            // CustomResource first for root struct
-            derives.insert(0, "CustomResource");
-        }
+fn has_schema(version: &CustomResourceDefinitionVersion) -> bool {
+    version
+        .schema
+        .as_ref()
+        .is_some_and(|schema| schema.open_api_v3_schema.is_some())
+}
 
-        // TypedBuilder does not work with enums
-        if self.builders && !s.is_enum {
-            derives.push("TypedBuilder");
+/// Acronyms kept uppercase by `--acronyms` without a value
+const COMMON_ACRONYMS: &str = "API,CIDR,CPU,DNS,GRPC,HTTP,HTTPS,ID,IP,JSON,JWT,OIDC,SSH,TCP,TLS,UDP,URI,URL";
+
+/// Flags taking secrets, whose values are kept out of the generated header
+const SECRET_FLAGS: [&str; 1] = ["--token"];
+
+/// Arguments joined for the generated header, with the values of secret flags redacted
+fn redact_secrets(args: impl Iterator<Item = String>) -> String {
+    let mut redacted = vec![];
+    let mut secret_next = false;
+    for arg in args {
+        if secret_next {
+            redacted.push("<redacted>".to_string());
+            secret_next = false;
+        } else if let Some((flag, _)) = arg.split_once('=').filter(|(f, _)| SECRET_FLAGS.contains(f)) {
+            redacted.push(format!("{flag}=<redacted>"));
+        } else {
+            secret_next = SECRET_FLAGS.contains(&arg.as_str());
+            redacted.push(arg);
         }
+    }
+    redacted.join(" ")
+}
 
-        for derive in &self.derive {
-            if derive.derived_trait == "Default"
-                && ((self.smart_derive_elision && !s.can_derive_default(containers)) || !s.is_enum)
-            {
-                continue;
-            }
+/// Counts of what was generated for a single CRD version
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    structs: usize,
+    enums: usize,
+    fields: usize,
+    warnings: usize,
+    /// Fields whose schema could not be represented, coerced to a String or falling back to an untyped value
+    coerced: usize,
+    /// Fields using a map in place of a generated struct
+    inlined_maps: usize,
+}
 
-            if derive.is_applicable_to(s) && !derives.contains(&derive.derived_trait.as_str()) {
-                derives.push(&derive.derived_trait)
+impl Summary {
+    fn new(structs: &[Container], warnings: &[Warning]) -> Self {
+        let mut summary = Summary {
+            warnings: warnings.len(),
+            coerced: warnings.iter().filter(|w| w.is_lossy()).count(),
+            ..Summary::default()
+        };
+        // root containers are never emitted
+        for s in structs.iter().filter(|s| !s.is_root()) {
+            if s.is_enum {
+                summary.enums += 1;
+                continue;
             }
+            summary.structs += 1;
+            summary.fields += s.members.len();
+            summary.inlined_maps += s
+                .members
+                .iter()
+                .filter(|m| {
+                    ["BTreeMap", "HashMap", "IndexMap"]
+                        .iter()
+                        .any(|t| m.type_.contains(t))
+                })
+                .count();
         }
+        summary
+    }
+}
 
-        println!("#[derive({})]", derives.join(", "));
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "generated {} structs, {} enums, {} fields, {} warnings",
+            self.structs, self.enums, self.fields, self.warnings
+        )?;
+        write!(
+            f,
+            " ({} fields coerced, {} maps inlined)",
+            self.coerced, self.inlined_maps
+        )
     }
+}
 
-    fn has_status_resource(&self, results: &[Container]) -> bool {
-        results
-            .iter()
-            .any(|o| o.is_status_container() && !o.members.is_empty())
+/// Analysis warnings raised during a run, reported together once generation is done
+#[derive(Default)]
+struct Diagnostics(RefCell<Vec<Warning>>);
+
+impl Diagnostics {
+    /// Record the warnings raised while analyzing a CRD, prefixed by its name
+    fn record(&self, crd: &str, warnings: Vec<Warning>) {
+        let mut recorded = self.0.borrow_mut();
+        recorded.extend(warnings.into_iter().map(|w| Warning {
+            message: format!("{}: {}", crd, w.message),
+            ..w
+        }));
     }
 
-    fn print_prelude(&self, results: &[Container]) {
-        println!("#[allow(unused_imports)]");
-        println!("mod prelude {{");
-        if !self.hide_kube {
-            println!("    pub use kube::CustomResource;");
-        }
-        if self.builders {
-            println!("    pub use typed_builder::TypedBuilder;");
-        }
-        if self
-            .derive
-            .iter()
-            .any(|derive| derive.derived_trait == "JsonSchema")
-        {
-            println!("    pub use schemars::JsonSchema;");
-        }
-        println!("    pub use serde::{{Serialize, Deserialize}};");
-        if results.iter().any(|o| o.uses_btreemaps()) {
-            println!("    pub use std::collections::BTreeMap;");
-        }
-        if results.iter().any(|o| o.uses_hashmaps()) {
-            println!("    pub use std::collections::HashMap;");
-        }
-        if results.iter().any(|o| o.uses_datetime()) {
-            println!("    pub use chrono::{{DateTime, Utc}};");
-        }
-        if results.iter().any(|o| o.uses_date()) {
-            println!("    pub use chrono::naive::NaiveDate;");
+    /// Log each distinct warning once, with a count if it was raised repeatedly
+    ///
+    /// Returns the number of distinct warnings, which fail a run with --deny-warnings.
+    fn report(&self) -> usize {
+        let warnings = self.0.borrow();
+        let summary = dedup_warnings(&warnings);
+        for (warning, count) in &summary {
+            if *count > 1 {
+                warn!("{} (x{})", warning, count);
+            } else {
+                warn!("{}", warning);
+            }
         }
-        if results.iter().any(|o| o.uses_int_or_string()) {
-            println!("    pub use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;");
+        summary.len()
+    }
+}
+
+/// Distinct warnings in the order they were first raised, along with how often they were raised
+fn dedup_warnings(warnings: &[Warning]) -> Vec<(&Warning, usize)> {
+    let mut counts: Vec<(&Warning, usize)> = vec![];
+    for warning in warnings {
+        if let Some((_, count)) = counts.iter_mut().find(|(w, _)| *w == warning) {
+            *count += 1;
+        } else {
+            counts.push((warning, 1));
         }
-        if results.iter().any(|o| o.contains_conditions()) && !self.no_condition {
-            println!("    pub use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;");
+    }
+    counts
+}
+
+/// Pretty-print generated code
+///
+/// If the code does not parse, it is returned unformatted, as that indicates a codegen bug.
+fn format_code(code: &str) -> String {
+    match syn::parse_file(code) {
+        Ok(file) => prettyplease::unparse(&file),
+        Err(e) => {
+            error!(
+                "generated code failed to parse ({}), emitting unformatted output. This is a kopium bug",
+                e
+            );
+            code.to_string()
         }
-        println!("}}");
-        println!("use self::prelude::*;\n");
     }
+}
+
+/// Write data to a temporary file next to `path` and rename it into place
+///
+/// This avoids truncating an existing file if anything fails halfway through.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", path.display()))?;
+    let tmp = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    std::fs::write(&tmp, data).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
 
-    fn print_generation_warning(&self) {
-        println!("// WARNING: generated by kopium - manual changes will be overwritten");
-        let args = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
-        println!("// kopium command: kopium {}", args);
-        println!("// kopium version: {}", clap::crate_version!());
-        println!();
+/// Write data to `path` only if its contents differ, to avoid invalidating incremental builds
+fn write_if_changed(path: &Path, data: &[u8]) -> Result<()> {
+    if std::fs::read(path).is_ok_and(|existing| existing == data) {
+        debug!("{} is unchanged", path.display());
+        return Ok(());
     }
+    write_atomic(path, data)
 }
 
-fn find_crd_version<'a>(
-    crd: &'a CustomResourceDefinition,
-    version: Option<&str>,
-) -> Result<&'a CustomResourceDefinitionVersion> {
-    let mut iter = crd.spec.versions.iter();
-    if let Some(version) = version {
-        // pick specified version
-        iter.find(|v| v.name == version).ok_or_else(|| {
-            anyhow!(
-                "Version '{}' not found in CRD '{}'\navailable versions are '{}'",
-                version,
-                crd.name_any(),
-                all_versions(crd)
-            )
+/// Kubeconfig built from an explicit --server, --token and optional --ca-cert
+///
+/// Returns None when none of the flags are set, and fails early if only some of them are.
+fn static_kubeconfig(
+    server: Option<&str>,
+    token: Option<&str>,
+    ca_cert: Option<&Path>,
+) -> Result<Option<Kubeconfig>> {
+    let (server, token) = match (server, token) {
+        (Some(server), Some(token)) => (server, token),
+        (None, None) if ca_cert.is_none() => return Ok(None),
+        (None, None) => bail!("--ca-cert requires --server and --token"),
+        (None, Some(_)) => bail!("--token requires a --server to connect to"),
+        (Some(_), None) => bail!("--server requires a --token to authenticate with"),
+    };
+    let mut cluster = serde_json::json!({ "server": server });
+    if let Some(ca) = ca_cert {
+        cluster["certificate-authority"] = ca.display().to_string().into();
+    }
+    let kubeconfig = serde_json::json!({
+        "clusters": [{ "name": "kopium", "cluster": cluster }],
+        "users": [{ "name": "kopium", "user": { "token": token } }],
+        "contexts": [{ "name": "kopium", "context": { "cluster": "kopium", "user": "kopium" } }],
+        "current-context": "kopium",
+    });
+    Ok(Some(serde_json::from_value(kubeconfig)?))
+}
+
+/// Up to five candidates that look like plausible corrections of a mistyped name
+fn similar_names(name: &str, candidates: &[String]) -> Vec<String> {
+    let threshold = std::cmp::max(2, name.len() / 10);
+    let mut scored = candidates
+        .iter()
+        .filter_map(|c| {
+            let distance = edit_distance(name, c);
+            let prefix = c.starts_with(name) || name.starts_with(c.as_str());
+            (distance <= threshold || prefix).then_some((distance, c))
         })
-    } else {
-        // pick version with highest version priority
-        iter.max_by_key(|v| Version::parse(&v.name).priority())
-            .ok_or_else(|| anyhow!("CRD '{}' has no versions", crd.name_any()))
+        .collect::<Vec<_>>();
+    scored.sort();
+    scored.into_iter().take(5).map(|(_, c)| c.clone()).collect()
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
     }
+    prev[b.len()]
+}
+
+/// Module (file) names for a set of CRDs
+///
+/// Uses the lowercased kind, and appends the group when two CRDs share a kind.
+fn module_names(crds: &[CustomResourceDefinition]) -> Vec<String> {
+    crds.iter()
+        .map(|crd| {
+            let kind = &crd.spec.names.kind;
+            let module = kind.to_ascii_lowercase();
+            if crds.iter().filter(|c| &c.spec.names.kind == kind).count() > 1 {
+                let group = crd.spec.group.replace(['.', '-'], "_");
+                format!("{}_{}", module, group)
+            } else {
+                module
+            }
+        })
+        .collect()
 }
 
 fn all_versions(crd: &CustomResourceDefinition) -> String {
@@ -464,3 +1437,2105 @@ fn all_versions(crd: &CustomResourceDefinition) -> String {
     vers.sort_by_cached_key(|v| std::cmp::Reverse(Version::parse(v).priority()));
     vers.join(", ")
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        all_versions, dedup_warnings, find_crd_version, format_code, log_filter, module_names, parse_crds,
+        redact_secrets, similar_names, static_kubeconfig, write_atomic, Diagnostics, Summary,
+    };
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+    use kopium::{Warning, WarningKind};
+
+    fn crd_with_versions(versions: &str) -> CustomResourceDefinition {
+        let crd_str = format!(
+            r#"
+apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: prometheuses.monitoring.coreos.com
+spec:
+  group: monitoring.coreos.com
+  names:
+    kind: Prometheus
+    plural: prometheuses
+  scope: Namespaced
+  versions:
+{versions}"#
+        );
+        serde_yaml::from_str(&crd_str).unwrap()
+    }
+
+    const MULTI_VERSIONS: &str = r#"
+  - name: v1alpha1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+  - name: v1beta1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+"#;
+
+    #[test]
+    fn find_explicit_version() {
+        let crd = crd_with_versions(MULTI_VERSIONS);
+        let version = find_crd_version(&crd, Some("v1beta1")).unwrap();
+        assert_eq!(version.name, "v1beta1");
+        assert!(version.schema.as_ref().unwrap().open_api_v3_schema.is_some());
+    }
+
+    #[test]
+    fn all_versions_get_a_module_each() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1alpha1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              replicas:
+                type: integer
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              replicas:
+                type: integer
+                format: int32
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q", "--all-versions", "-f", "crd.yaml"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        let file = syn::parse_file(&code).unwrap();
+        let modules = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Mod(m) => Some(m),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let names = modules.iter().map(|m| m.ident.to_string()).collect::<Vec<_>>();
+        assert_eq!(names, ["v1alpha1", "v1"]);
+        // each version is self-contained, with its own prelude
+        for module in &modules {
+            let (_, items) = module.content.as_ref().unwrap();
+            assert!(items
+                .iter()
+                .any(|item| matches!(item, syn::Item::Mod(m) if m.ident == "prelude")));
+        }
+        assert!(code.contains("version = \"v1alpha1\""));
+        assert!(code.contains("version = \"v1\""));
+        assert!(code.contains("pub replicas: Option<i64>,"));
+        assert!(code.contains("pub replicas: Option<i32>,"));
+
+        // a module per version of several CRDs would repeat the version names
+        let mut buf = vec![];
+        let err = kopium.generate(&mut buf, &[crd.clone(), crd]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--all-versions only supports generating a single CRD"
+        );
+    }
+
+    #[test]
+    fn all_versions_skip_versions_without_schema() {
+        use super::{schema_versions, Kopium};
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1alpha1
+    served: false
+    storage: false
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              replicas:
+                type: integer
+"#,
+        );
+        let versions = schema_versions(&crd);
+        assert_eq!(versions.iter().map(|v| v.name.as_str()).collect::<Vec<_>>(), [
+            "v1"
+        ]);
+
+        let kopium = Kopium::parse_from(["kopium", "-q", "--all-versions", "-f", "crd.yaml"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(code.contains("pub mod v1 {"));
+        assert!(!code.contains("v1alpha1"));
+    }
+
+    #[test]
+    fn default_version_is_storage_version() {
+        let crd = crd_with_versions(MULTI_VERSIONS);
+        let version = find_crd_version(&crd, None).unwrap();
+        assert_eq!(version.name, "v1");
+        assert!(version.storage);
+    }
+
+    #[test]
+    fn default_version_falls_back_to_last_served() {
+        let crd = crd_with_versions(
+            r#"
+  - name: v1beta1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+  - name: v1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+  - name: v1alpha1
+    served: false
+    storage: false
+"#,
+        );
+        let version = find_crd_version(&crd, None).unwrap();
+        assert_eq!(version.name, "v1");
+    }
+
+    #[test]
+    fn default_version_skips_unserved_versions() {
+        let crd = crd_with_versions(
+            r#"
+  - name: v1alpha1
+    served: false
+    storage: true
+  - name: v1beta1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+  - name: v1
+    served: false
+    storage: false
+"#,
+        );
+        let version = find_crd_version(&crd, None).unwrap();
+        assert_eq!(version.name, "v1beta1");
+    }
+
+    #[test]
+    fn default_version_without_storage_schema_is_last_served_with_schema() {
+        let crd = crd_with_versions(
+            r#"
+  - name: v1alpha1
+    served: true
+    storage: true
+  - name: v1beta1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+  - name: v1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+  - name: v2
+    served: true
+    storage: false
+"#,
+        );
+        // not the first one with a schema, as the storage preference falls back to the last served version
+        let version = find_crd_version(&crd, None).unwrap();
+        assert_eq!(version.name, "v1");
+    }
+
+    #[test]
+    fn default_version_skips_versions_without_schema() {
+        let crd = crd_with_versions(
+            r#"
+  - name: v1alpha1
+    served: true
+    storage: true
+  - name: v1
+    served: true
+    storage: false
+    schema:
+      openAPIV3Schema:
+        type: object
+"#,
+        );
+        let version = find_crd_version(&crd, None).unwrap();
+        assert_eq!(version.name, "v1");
+
+        let crd = crd_with_versions(
+            r#"
+  - name: v1alpha1
+    served: true
+    storage: true
+  - name: v1
+    served: true
+    storage: false
+    schema: {}
+"#,
+        );
+        let err = find_crd_version(&crd, None).unwrap_err().to_string();
+        assert!(err.contains("has no served versions with a schema"));
+    }
+
+    #[test]
+    fn no_served_versions_is_an_error() {
+        let crd = crd_with_versions(
+            r#"
+  - name: v1alpha1
+    served: false
+    storage: false
+  - name: v1
+    served: false
+    storage: true
+"#,
+        );
+        let err = find_crd_version(&crd, None).unwrap_err().to_string();
+        assert!(err.contains("'prometheuses.monitoring.coreos.com' has no served versions"));
+        assert!(err.contains("v1, v1alpha1"));
+    }
+
+    #[test]
+    fn parse_crds_rejects_other_kinds() {
+        let deploy = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: foo
+spec: {}
+"#;
+        let err = parse_crds(deploy, "deploy.yaml").unwrap_err().to_string();
+        assert_eq!(err, "deploy.yaml does not contain a CustomResourceDefinition");
+
+        let list = r#"
+apiVersion: v1
+kind: List
+items: []
+"#;
+        let err = parse_crds(list, "stdin").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "stdin contains a List, expected a single CustomResourceDefinition"
+        );
+        let err = parse_crds(" \n", "stdin").unwrap_err().to_string();
+        assert_eq!(err, "stdin is empty");
+
+        let crd = crd_with_versions(MULTI_VERSIONS);
+        let data = serde_yaml::to_string(&crd).unwrap();
+        let crds = parse_crds(&data, "crd.yaml").unwrap();
+        assert_eq!(crds.len(), 1);
+        assert_eq!(crds[0].spec.names.kind, "Prometheus");
+    }
+
+    #[test]
+    fn parse_crds_accepts_json_and_yaml() {
+        let yaml = parse_crds(include_str!("../tests/mv-crd.yaml"), "mv-crd.yaml").unwrap();
+        let json = parse_crds(include_str!("../tests/mv-crd.json"), "mv-crd.json").unwrap();
+        assert_eq!(yaml, json);
+
+        let err = parse_crds("{\"kind\": ", "crd.json").unwrap_err().to_string();
+        assert_eq!(err, "crd.json is not valid json");
+    }
+
+    #[test]
+    fn parse_crds_from_multi_document_stream() {
+        let stream = format!(
+            "{}\n---\n{}\n---\n{}\n---\n",
+            r#"
+apiVersion: v1
+kind: Service
+metadata:
+  name: foo
+"#,
+            include_str!("../tests/mv-crd.yaml"),
+            serde_yaml::to_string(&crd_with_versions(MULTI_VERSIONS)).unwrap()
+        );
+        let crds = parse_crds(&stream, "stream.yaml").unwrap();
+        assert_eq!(crds.len(), 2);
+        assert_eq!(crds[0].spec.names.kind, "MultiVersion");
+        assert_eq!(crds[1].spec.names.kind, "Prometheus");
+    }
+
+    #[test]
+    fn module_names_disambiguate_kinds() {
+        let crd = |kind: &str, group: &str| {
+            let mut crd = crd_with_versions(MULTI_VERSIONS);
+            crd.spec.names.kind = kind.to_string();
+            crd.spec.group = group.to_string();
+            crd
+        };
+        let crds = [
+            crd("PrometheusRule", "monitoring.coreos.com"),
+            crd("Gateway", "gateway.networking.k8s.io"),
+            crd("Gateway", "networking.istio.io"),
+        ];
+        assert_eq!(module_names(&crds), vec![
+            "prometheusrule",
+            "gateway_gateway_networking_k8s_io",
+            "gateway_networking_istio_io"
+        ]);
+    }
+
+    #[test]
+    fn suggests_similar_crd_names() {
+        let names = [
+            "prometheusrules.monitoring.coreos.com",
+            "prometheuses.monitoring.coreos.com",
+            "servicemonitors.monitoring.coreos.com",
+            "certificates.cert-manager.io",
+        ]
+        .map(String::from);
+        assert_eq!(
+            similar_names("prometheusrule.monitoring.coreos.com", &names),
+            vec!["prometheusrules.monitoring.coreos.com"]
+        );
+        assert_eq!(similar_names("prometheus", &names), vec![
+            "prometheuses.monitoring.coreos.com",
+            "prometheusrules.monitoring.coreos.com"
+        ]);
+        assert!(similar_names("issuers.cert-manager.io", &names).is_empty());
+    }
+
+    #[test]
+    fn static_kubeconfig_requires_server_and_token() {
+        use std::path::Path;
+        assert!(static_kubeconfig(None, None, None).unwrap().is_none());
+        let err = static_kubeconfig(Some("https://k8s:6443"), None, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--server requires a --token to authenticate with"
+        );
+        let err = static_kubeconfig(None, Some("abc"), None).unwrap_err();
+        assert_eq!(err.to_string(), "--token requires a --server to connect to");
+        let err = static_kubeconfig(None, None, Some(Path::new("ca.crt"))).unwrap_err();
+        assert_eq!(err.to_string(), "--ca-cert requires --server and --token");
+
+        let kubeconfig = static_kubeconfig(Some("https://k8s:6443"), Some("abc"), Some(Path::new("ca.crt")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(kubeconfig.current_context.as_deref(), Some("kopium"));
+        let json = serde_json::to_value(&kubeconfig).unwrap();
+        assert_eq!(json["clusters"][0]["cluster"]["server"], "https://k8s:6443");
+        assert_eq!(json["clusters"][0]["cluster"]["certificate-authority"], "ca.crt");
+        assert_eq!(json["contexts"][0]["context"]["user"], "kopium");
+    }
+
+    #[test]
+    fn generated_header_redacts_tokens() {
+        let args = |line: &str| redact_secrets(line.split(' ').map(String::from));
+        assert_eq!(
+            args("--server https://k8s:6443 --token abc mycrd"),
+            "--server https://k8s:6443 --token <redacted> mycrd"
+        );
+        assert_eq!(args("--token=abc mycrd"), "--token=<redacted> mycrd");
+        assert_eq!(args("-f crd.yaml --docs"), "-f crd.yaml --docs");
+    }
+
+    #[test]
+    fn write_atomic_replaces_files() {
+        let dir = std::env::temp_dir().join(format!("kopium-write-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("types.rs");
+        write_atomic(&path, b"pub struct A {}\n").unwrap();
+        write_atomic(&path, b"pub struct B {}\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "pub struct B {}\n");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn generate_dir_writes_module_per_crd() {
+        use super::Kopium;
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("kopium-out-dir-{}", std::process::id()));
+        let kopium = Kopium::parse_from(["kopium", "--out-dir", dir.to_str().unwrap()]);
+        let stream = format!(
+            "{}\n---\n{}",
+            include_str!("../tests/mv-crd.yaml"),
+            serde_yaml::to_string(&crd_with_versions(MULTI_VERSIONS)).unwrap()
+        );
+        let crds = parse_crds(&stream, "stream.yaml").unwrap();
+        kopium.generate_dir(&dir, &crds).unwrap();
+
+        let mut files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec!["mod.rs", "multiversion.rs", "prometheus.rs"]);
+        let mod_rs = std::fs::read_to_string(dir.join("mod.rs")).unwrap();
+        assert!(mod_rs.contains("pub mod multiversion;\npub mod prometheus;\n"));
+        assert!(mod_rs.contains("pub use self::multiversion::MultiVersion;\n"));
+        assert!(mod_rs.contains("pub use self::prometheus::Prometheus;\n"));
+        let generated = std::fs::read_to_string(dir.join("multiversion.rs")).unwrap();
+        assert!(generated.contains("pub struct MultiVersionSpec {"));
+
+        // unchanged files are not rewritten
+        let modified = std::fs::metadata(dir.join("mod.rs")).unwrap().modified().unwrap();
+        kopium.generate_dir(&dir, &crds).unwrap();
+        let remodified = std::fs::metadata(dir.join("mod.rs")).unwrap().modified().unwrap();
+        assert_eq!(modified, remodified);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn format_code_is_stable() {
+        let raw = r#"#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FooSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub replicas: i64,
+}
+
+"#;
+        let formatted = format_code(raw);
+        assert_eq!(
+            formatted,
+            r#"#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FooSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub replicas: i64,
+}
+"#
+        );
+        assert_eq!(format_code(&formatted), formatted);
+        // invalid code is passed through untouched
+        assert_eq!(format_code("pub struct {"), "pub struct {");
+    }
+
+    #[test]
+    fn verbosity_maps_to_log_filter() {
+        use super::Kopium;
+        use clap::Parser;
+        use log::LevelFilter;
+        let verbosity = |args: &[&str]| {
+            let kopium = Kopium::parse_from(std::iter::once("kopium").chain(args.iter().copied()));
+            log_filter(kopium.verbose, kopium.quiet)
+        };
+        assert_eq!(verbosity(&["-f", "crd.yaml"]), LevelFilter::Warn);
+        assert_eq!(verbosity(&["-v", "-f", "crd.yaml"]), LevelFilter::Info);
+        assert_eq!(verbosity(&["-vv", "-f", "crd.yaml"]), LevelFilter::Debug);
+        assert_eq!(verbosity(&["-f", "crd.yaml", "-vvv"]), LevelFilter::Trace);
+        assert_eq!(verbosity(&["-vvvv"]), LevelFilter::Trace);
+        assert_eq!(verbosity(&["--quiet"]), LevelFilter::Error);
+        assert!(Kopium::try_parse_from(["kopium", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn warnings_are_summarized_once() {
+        let warnings = ["b", "a", "b", "b"].map(|m| warning(WarningKind::Untyped, m));
+        let summary = dedup_warnings(&warnings)
+            .into_iter()
+            .map(|(w, count)| (w.message.as_str(), count))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![("b", 3), ("a", 1)]);
+    }
+
+    fn warning(kind: WarningKind, message: &str) -> Warning {
+        Warning {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn diagnostics_count_distinct_warnings() {
+        let diagnostics = Diagnostics::default();
+        assert_eq!(diagnostics.report(), 0);
+        let untyped = || vec![warning(WarningKind::Untyped, "unknown type x at .a")];
+        diagnostics.record("foos.example.com", untyped());
+        diagnostics.record("bars.example.com", untyped());
+        diagnostics.record("foos.example.com", untyped());
+        assert_eq!(*diagnostics.0.borrow(), vec![
+            "foos.example.com: unknown type x at .a",
+            "bars.example.com: unknown type x at .a",
+            "foos.example.com: unknown type x at .a",
+        ]);
+        assert_eq!(diagnostics.report(), 2);
+
+        // notices fail a run with --deny-warnings too
+        let notice = warning(
+            WarningKind::Notice,
+            "not generating type Labels at .labels - using map",
+        );
+        diagnostics.record("foos.example.com", vec![notice]);
+        assert_eq!(diagnostics.0.borrow().len(), 4);
+        assert_eq!(diagnostics.report(), 3);
+
+        use super::Kopium;
+        use clap::Parser;
+        let kopium = Kopium::parse_from(["kopium", "--deny-warnings", "-f", "crd.yaml"]);
+        assert!(kopium.deny_warnings);
+    }
+
+    #[test]
+    fn acronyms_default_to_common_ones() {
+        use super::Kopium;
+        use clap::Parser;
+        let kopium = Kopium::parse_from(["kopium", "-f", "crd.yaml"]);
+        assert!(kopium.acronyms.is_empty());
+        let kopium = Kopium::parse_from(["kopium", "--acronyms", "crds.example.com"]);
+        assert!(kopium.acronyms.contains(&"TLS".to_string()));
+        assert_eq!(kopium.crd, ["crds.example.com"]);
+        let kopium = Kopium::parse_from(["kopium", "--acronyms=tls,url", "-f", "crd.yaml"]);
+        assert_eq!(kopium.acronyms, ["tls", "url"]);
+    }
+
+    #[test]
+    fn kube_status_and_singular_follow_the_output() {
+        use super::Kopium;
+        use clap::Parser;
+        let crds = parse_crds(include_str!("../tests/kube-crd.yaml"), "kube-crd.yaml").unwrap();
+        let generate = |args: &[&str], crds: &[CustomResourceDefinition]| {
+            let kopium = Kopium::parse_from(["kopium", "-q"].iter().chain(args));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, crds).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&[], &crds);
+        assert!(code.contains(r#"#[kube(status = "WorkloadStatus")]"#));
+        // the status is not typed when it is left out of the output
+        let code = generate(&["--elide", "WorkloadStatus"], &crds);
+        assert!(!code.contains("#[kube(status"));
+
+        // only a singular that kube would not infer is kept
+        assert!(!code.contains("singular"));
+        let mut crd = crds[0].clone();
+        crd.spec.names.singular = Some("wkld".into());
+        assert!(generate(&[], &[crd]).contains(r#"#[kube(singular = "wkld")]"#));
+    }
+
+    #[test]
+    fn json_output_tells_optional_and_required_members_apart() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            required:
+            - image
+            - replicas
+            properties:
+              image:
+                type: string
+              replicas:
+                type: integer
+                format: int32
+                nullable: true
+              podIP:
+                type: string
+              logLevel:
+                type: string
+                default: info
+            x-kubernetes-preserve-unknown-fields: true
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q", "--emit", "json", "--schema-defaults"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        let summary = json[0]["types"][0]["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| {
+                (
+                    m["name"].as_str().unwrap(),
+                    m["property"].as_str(),
+                    m["optional"].as_bool().unwrap(),
+                    m["required"].as_bool().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(summary, [
+            ("image", Some("image"), false, true),
+            ("log_level", Some("logLevel"), false, false),
+            ("pod_ip", Some("podIP"), true, false),
+            // null is written back, but the property must be there
+            ("replicas", Some("replicas"), true, true),
+            // unknown fields have no property of their own
+            ("extra", None, false, false),
+        ]);
+
+        // --all writes a Rust module per CRD, with or without --out-dir
+        for args in [["--all", "-o", "gen"], ["--all", "--out-dir", "gen"]] {
+            assert!(Kopium::try_parse_from(["kopium"].into_iter().chain(args)).is_ok());
+            let args = ["kopium", "--emit", "json"].into_iter().chain(args);
+            assert!(Kopium::try_parse_from(args).is_err());
+        }
+        assert_eq!(json[0]["types"][0]["name"], "PrometheusSpec");
+        assert_eq!(json[0]["kind"], "Prometheus");
+    }
+
+    #[test]
+    fn write_errors_are_returned() {
+        use super::Kopium;
+        use clap::Parser;
+        // a reader that went away, as with `kopium ... | head` on platforms without SIGPIPE
+        struct ClosedPipe;
+        impl std::io::Write for ClosedPipe {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let crds = parse_crds(include_str!("../tests/kube-crd.yaml"), "kube-crd.yaml").unwrap();
+        for args in [&["kopium", "-q"][..], &["kopium", "-q", "--no-format"]] {
+            let err = Kopium::parse_from(args)
+                .generate(&mut ClosedPipe, &crds)
+                .unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<std::io::Error>().unwrap().kind(),
+                std::io::ErrorKind::BrokenPipe
+            );
+        }
+        let kopium = Kopium::parse_from(["kopium", "-q"]);
+        assert!(kopium.dry_run(&mut ClosedPipe, &crds).is_err());
+    }
+
+    #[test]
+    fn unformatted_items_parse() {
+        use super::Kopium;
+        use clap::Parser;
+        let crds = parse_crds(include_str!("../tests/kube-crd.yaml"), "kube-crd.yaml").unwrap();
+        let kopium = Kopium::parse_from([
+            "kopium",
+            "-q",
+            "--no-format",
+            "--docs",
+            "--builders",
+            "--derive",
+            "PartialEq",
+            "--visibility",
+            "pub-crate",
+        ]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, &crds).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        // items are written out as is, so their attributes must be well formed without the formatter
+        syn::parse_file(&code).unwrap();
+        assert!(code.contains(
+            "#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, TypedBuilder, PartialEq)]\n"
+        ));
+        assert!(code.contains("#[kube(derive=\"PartialEq\")]\npub struct WorkloadSpec {\n"));
+    }
+
+    #[test]
+    fn visibility_applies_to_everything_without_kube() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = parse_crds(
+            include_str!("../tests/visibility-crd.yaml"),
+            "visibility-crd.yaml",
+        )
+        .unwrap();
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(["kopium", "-q"].iter().chain(args));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, &crd).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["--visibility", "pub-crate", "--hide-kube"]);
+        assert!(code.contains("pub(crate) struct TenantSpec {"));
+        assert!(code.contains("pub(crate) struct TenantStatus {"));
+        assert!(!code
+            .lines()
+            .any(|l| l.trim_start().starts_with("pub ") && !l.contains("pub use ")));
+        // everything is pub by default
+        let code = generate(&[]);
+        assert!(code.contains("pub enum TenantTier {"));
+        assert!(code.contains("pub struct TenantQuota {"));
+        assert!(code.contains("    pub cpu: Option<String>,"));
+        assert!(!code.contains("pub(crate)"));
+    }
+
+    #[test]
+    fn scale_subresource_becomes_kube_attribute() {
+        use super::Kopium;
+        use clap::Parser;
+        let schema = r#"
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              replicas:
+                type: integer
+"#;
+        let generate = |subresources: &str| {
+            let crd = crd_with_versions(&format!(
+                "\n  - name: v1\n    served: true\n    storage: true{}{}",
+                subresources, schema
+            ));
+            let kopium = Kopium::parse_from(["kopium", "-q"]);
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(
+            r#"
+    subresources:
+      scale:
+        specReplicasPath: .spec.replicas
+        statusReplicasPath: .status.replicas"#,
+        );
+        // formatting may break the attribute over lines
+        assert!(code.contains(
+            r##"scale = r#"{"specReplicasPath":".spec.replicas","statusReplicasPath":".status.replicas"}"#"##
+        ));
+        let code = generate(
+            r#"
+    subresources:
+      status: {}"#,
+        );
+        assert!(!code.contains("scale"));
+        assert!(!generate("").contains("scale"));
+    }
+
+    #[test]
+    fn summary_counts_generated_items() {
+        use super::Kopium;
+        use clap::Parser;
+        let kopium = Kopium::parse_from(["kopium", "-f", "server-crd.yaml"]);
+        let crds = parse_crds(include_str!("../tests/server-crd.yaml"), "server-crd.yaml").unwrap();
+        let version = find_crd_version(&crds[0], None).unwrap();
+        let kopium::Output {
+            containers: structs,
+            warnings,
+        } = kopium.analyze_version(&crds[0], version).unwrap();
+        let summary = Summary::new(&structs, &warnings);
+        assert_eq!(summary, Summary {
+            structs: 3,
+            enums: 2,
+            fields: 8,
+            warnings: 1,
+            coerced: 0,
+            inlined_maps: 1,
+        });
+        assert_eq!(
+            summary.to_string(),
+            "generated 3 structs, 2 enums, 8 fields, 1 warnings (0 fields coerced, 1 maps inlined)"
+        );
+        assert_eq!(version.name, "v1beta1");
+
+        // coercions count from the kind of warning they raise, whatever its message
+        let kopium = Kopium::parse_from([
+            "kopium",
+            "--coerce-intorstring-to-string",
+            "-f",
+            "server-crd.yaml",
+        ]);
+        let kopium::Output {
+            containers: structs,
+            warnings,
+        } = kopium.analyze_version(&crds[0], version).unwrap();
+        assert_eq!(warnings[0], kopium::Warning {
+            kind: kopium::WarningKind::Coerced,
+            message: "int-or-string at .spec.port - using String".into(),
+        });
+        let summary = Summary::new(&structs, &warnings);
+        assert_eq!((summary.warnings, summary.coerced), (2, 1));
+    }
+
+    #[test]
+    fn completions_for_every_shell() {
+        use super::{Command, Kopium};
+        use clap::{Parser, ValueEnum};
+        use clap_complete::Shell;
+        for shell in Shell::value_variants() {
+            let kopium = Kopium::parse_from(["kopium", "completions", &shell.to_string()]);
+            let Some(Command::Completions { shell: parsed }) = kopium.command else {
+                panic!("completions {} did not parse as a subcommand", shell);
+            };
+            assert_eq!(parsed, *shell);
+            let mut buf = vec![];
+            kopium.completions(&mut buf, parsed).unwrap();
+            let script = String::from_utf8(buf).unwrap();
+            assert!(script.contains("kopium"), "empty completions for {}", shell);
+        }
+
+        // plain crd invocations still parse as positionals
+        let kopium = Kopium::parse_from(["kopium", "prometheusrules.monitoring.coreos.com", "--docs"]);
+        assert!(kopium.command.is_none());
+        assert_eq!(kopium.crd, vec!["prometheusrules.monitoring.coreos.com"]);
+        assert!(kopium.docs);
+    }
+
+    #[test]
+    fn dry_run_lists_sorted_structs() {
+        use super::Kopium;
+        use clap::Parser;
+        let kopium = Kopium::parse_from(["kopium", "--dry-run", "-q", "-f", "server-crd.yaml"]);
+        let mut crds = parse_crds(include_str!("../tests/server-crd.yaml"), "server-crd.yaml").unwrap();
+        crds.extend(parse_crds(include_str!("../tests/mv-crd.yaml"), "mv-crd.yaml").unwrap());
+        let mut buf = vec![];
+        kopium.dry_run(&mut buf, &crds).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r#"multiversions.clux.dev v1 (storage)
+    MultiVersionSpec (1 members)
+servers.policy.linkerd.io v1beta1 (storage)
+    ServerPodSelector (2 members)
+    ServerPodSelectorMatchExpression (3 members)
+    ServerPodSelectorMatchExpressionOperator (4 members)
+    ServerProxyProtocol (6 members)
+    ServerSpec (3 members)
+"#
+        );
+        assert!(Kopium::try_parse_from(["kopium", "--dry-run", "-o", "out.rs", "-f", "crd.yaml"]).is_err());
+    }
+
+    #[test]
+    fn chrono_prelude_only_when_used() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              since:
+                type: string
+                format: date-time
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(std::iter::once("kopium").chain(args.iter().copied()));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["--chrono", "-q"]);
+        assert!(code.contains("pub use chrono::{DateTime, Utc};"));
+        assert!(code.contains("pub since: Option<DateTime<Utc>>,"));
+        let code = generate(&["-q"]);
+        assert!(!code.contains("chrono"));
+        assert!(code.contains("pub since: Option<String>,"));
+    }
+
+    #[test]
+    fn byte_strings_use_bytestring() {
+        use super::Kopium;
+        use clap::Parser;
+        let kopium = Kopium::parse_from(["kopium", "-q", "-f", "blob-crd.yaml"]);
+        let crds = parse_crds(include_str!("../tests/blob-crd.yaml"), "blob-crd.yaml").unwrap();
+        let mut buf = vec![];
+        kopium.generate(&mut buf, &crds).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(syn::parse_file(&code).is_ok());
+        assert!(code.contains("pub use k8s_openapi::ByteString;"));
+        assert!(code.contains("pub data: ByteString,"));
+        assert!(code.contains("pub checksums: Option<Vec<ByteString>>,"));
+        assert!(code.contains("pub payload: ByteString,"));
+    }
+
+    #[test]
+    fn scalar_maps_use_scalar_values() {
+        use super::Kopium;
+        use clap::Parser;
+        let kopium = Kopium::parse_from(["kopium", "-q", "-f", "blob-crd.yaml"]);
+        let crds = parse_crds(include_str!("../tests/blob-crd.yaml"), "blob-crd.yaml").unwrap();
+        let mut buf = vec![];
+        kopium.generate(&mut buf, &crds).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(syn::parse_file(&code).is_ok());
+        assert!(code.contains("pub chunk_sizes: Option<BTreeMap<String, i64>>,"));
+        assert!(code.contains("pub replicas: Option<BTreeMap<String, i32>>,"));
+        assert!(code.contains("pub verified: Option<BTreeMap<String, bool>>,"));
+    }
+
+    #[test]
+    fn repeated_schemas_are_generated_once() {
+        use super::Kopium;
+        use clap::Parser;
+        let selector = r#"
+                type: object
+                properties:
+                  matchLabels:
+                    type: object
+                    additionalProperties:
+                      type: string
+                  matchExpressions:
+                    type: array
+                    items:
+                      type: object
+                      properties:
+                        key:
+                          type: string
+                        operator:
+                          type: string
+                          enum: [In, NotIn]"#;
+        let crd = crd_with_versions(&format!(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              podSelector:{selector}
+              serviceSelector:{selector}
+          status:
+            type: object
+            properties:
+              selector:{selector}
+"#
+        ));
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(args);
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["kopium", "-q"]);
+        assert!(syn::parse_file(&code).is_ok());
+        assert_eq!(code.matches("pub struct").count(), 4);
+        assert_eq!(code.matches("pub enum").count(), 1);
+        assert!(code.contains("pub pod_selector: Option<PrometheusPodSelector>,"));
+        assert!(code.contains("pub service_selector: Option<PrometheusPodSelector>,"));
+        assert!(code.contains("pub selector: Option<PrometheusPodSelector>,"));
+        assert!(code.contains("pub match_expressions: Option<Vec<PrometheusPodSelectorMatchExpression>>,"));
+        assert!(code.contains("pub operator: Option<PrometheusPodSelectorMatchExpressionOperator>,"));
+
+        let code = generate(&["kopium", "-q", "--no-dedup"]);
+        assert_eq!(code.matches("pub struct").count(), 8);
+        assert_eq!(code.matches("pub enum").count(), 3);
+        assert!(code.contains("pub service_selector: Option<PrometheusServiceSelector>,"));
+    }
+
+    #[test]
+    fn colliding_struct_names_are_kept_apart() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              rule:
+                type: object
+                properties:
+                  name:
+                    type: string
+              status:
+                type: object
+                properties:
+                  phase:
+                    type: string
+              rules:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    priority:
+                      type: integer
+          status:
+            type: object
+            properties:
+              rule:
+                type: object
+                properties:
+                  observed:
+                    type: boolean
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(syn::parse_file(&code).is_ok());
+        assert_eq!(code.matches("pub struct PrometheusRule {").count(), 1);
+        assert!(code.contains("pub rule: Option<PrometheusRule>,"));
+        assert!(code.contains("pub rules: Option<Vec<PrometheusRule2>>,"));
+        assert!(code.contains("pub struct PrometheusRule2 {\n    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub priority: Option<i64>,"));
+        assert!(code.contains("pub rule: Option<PrometheusStatusRule>,"));
+        // structs below the spec are emitted without the Spec infix, which must not clash either
+        assert_eq!(code.matches("pub struct PrometheusStatus {").count(), 1);
+        assert!(code.contains("pub status: Option<PrometheusStatus2>,"));
+    }
+
+    #[test]
+    fn struct_prefix_keeps_the_kind_for_kube() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    subresources:
+      status: {}
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              tls:
+                type: object
+                properties:
+                  specification:
+                    type: object
+                    properties:
+                      name:
+                        type: string
+          status:
+            type: object
+            properties:
+              phase:
+                type: string
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(["kopium", "-q"].iter().chain(args));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            let code = String::from_utf8(buf).unwrap();
+            assert!(syn::parse_file(&code).is_ok());
+            code
+        };
+        let code = generate(&[]);
+        assert!(code.contains(r#"kind = "Prometheus""#));
+        assert!(code.contains(r#"#[kube(status = "PrometheusStatus")]"#));
+        assert!(code.contains("pub struct PrometheusSpec {"));
+        assert!(code.contains("pub tls: Option<PrometheusTls>,"));
+        assert!(code.contains("pub specification: Option<PrometheusTlsSpecification>,"));
+        assert!(code.contains("pub struct PrometheusTlsSpecification {"));
+
+        let code = generate(&["--struct-prefix", "Acme"]);
+        assert!(code.contains(r#"kind = "Prometheus""#));
+        assert!(code.contains(r#"#[kube(status = "AcmeStatus")]"#));
+        assert!(code.contains("pub struct AcmeSpec {"));
+        assert!(code.contains("pub tls: Option<AcmeTls>,"));
+        assert!(code.contains("pub struct AcmeTlsSpecification {"));
+        assert!(code.contains("pub struct AcmeStatus {"));
+
+        let code = generate(&["--struct-prefix", ""]);
+        assert!(code.contains(r#"kind = "Prometheus""#));
+        assert!(code.contains(r#"#[kube(status = "Status")]"#));
+        assert!(code.contains("pub struct Spec {"));
+        assert!(code.contains("pub tls: Option<Tls>,"));
+        // only the Spec infix is dropped, not the start of another word
+        assert!(code.contains("pub specification: Option<TlsSpecification>,"));
+        assert!(code.contains("pub struct TlsSpecification {"));
+        assert!(code.contains("pub struct Status {"));
+    }
+
+    #[test]
+    fn docs_keep_line_breaks_and_wrap_long_lines() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              retention:
+                description: "How long to retain samples in storage. Supported units: y, w, d, h, m, s, ms. Example: `30d`. Defaults to 24h.\n\nOverrides the global retention."
+                type: string
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(["kopium", "-q"].iter().chain(args));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["--docs"]);
+        assert!(code.contains(concat!(
+            "    /// How long to retain samples in storage. Supported units: y, w, d, h, m, s, ms. Example:\n",
+            "    /// `30d`. Defaults to 24h.\n",
+            "    ///\n",
+            "    /// Overrides the global retention.\n",
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n",
+            "    pub retention: Option<String>,",
+        )));
+        assert!(code.lines().all(|l| l.len() <= 100 || !l.contains("///")));
+        assert!(!generate(&[]).contains("///"));
+        assert!(!generate(&["--docs", "--no-docs"]).contains("///"));
+        assert!(generate(&["--no-docs", "--docs"]).contains("///"));
+    }
+
+    #[test]
+    fn struct_docs_sit_above_their_derives() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        description: Prometheus defines a Prometheus deployment.
+        type: object
+        properties:
+          spec:
+            description: PrometheusSpec is the specification of the desired behavior of the Prometheus cluster.
+            type: object
+            properties:
+              storage:
+                description: Storage spec to specify how storage shall be used.
+                type: object
+                properties:
+                  disableMountSubPath:
+                    type: boolean
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q", "--docs"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(syn::parse_file(&code).is_ok());
+        assert!(code.contains(concat!(
+            "/// PrometheusSpec is the specification of the desired behavior of the Prometheus cluster.\n",
+            "#[derive(CustomResource, ",
+        )));
+        assert!(code.contains(r#"#[kube(doc = "Prometheus defines a Prometheus deployment.")]"#));
+        assert!(code.contains(concat!(
+            "/// Storage spec to specify how storage shall be used.\n",
+            "#[derive(Serialize, Deserialize, Clone, Debug, Default)]\n",
+            "#[serde(rename_all = \"camelCase\")]\n",
+            "pub struct PrometheusStorage {",
+        )));
+    }
+
+    #[test]
+    fn full_path_names_keep_the_spec_infix() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              rule:
+                type: object
+                properties:
+                  name:
+                    type: string
+              status:
+                type: object
+                properties:
+                  phase:
+                    type: string
+              rules:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    priority:
+                      type: integer
+          status:
+            type: object
+            properties:
+              phase:
+                type: string
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q", "--naming", "full-path"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(syn::parse_file(&code).is_ok());
+        assert!(code.contains("pub rule: Option<PrometheusSpecRule>,"));
+        assert!(code.contains("pub rules: Option<Vec<PrometheusSpecRules>>,"));
+        assert!(code.contains("pub status: Option<PrometheusSpecStatus>,"));
+        assert!(code.contains("pub struct PrometheusSpecRule {"));
+        assert!(code.contains("pub struct PrometheusSpecRules {"));
+        assert!(code.contains("pub struct PrometheusSpecStatus {"));
+        // the status is identical to the one in the spec, but keeps its own name
+        assert!(code.contains("pub struct PrometheusStatus {"));
+    }
+
+    #[test]
+    fn enums_precede_the_structs_using_them() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              containers:
+                type: array
+                items:
+                  type: object
+                  required:
+                  - imagePullPolicy
+                  properties:
+                    imagePullPolicy:
+                      type: string
+                      enum: [Always, IfNotPresent, Never]
+              restartPolicy:
+                type: string
+                enum: [Always, OnFailure]
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        let position = |item: &str| code.find(item).unwrap_or_else(|| panic!("{} missing", item));
+        assert!(position("pub enum PrometheusRestartPolicy {") < position("pub struct PrometheusSpec {"));
+        assert!(
+            position("pub struct PrometheusSpec {")
+                < position("pub enum PrometheusContainerImagePullPolicy {")
+        );
+        assert!(
+            position("pub enum PrometheusContainerImagePullPolicy {")
+                < position("pub struct PrometheusContainer {")
+        );
+        assert!(code.contains("pub image_pull_policy: PrometheusContainerImagePullPolicy,"));
+        assert!(code.contains("pub restart_policy: Option<PrometheusRestartPolicy>,"));
+    }
+
+    #[test]
+    fn integer_enums_serialize_as_numbers() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            required:
+            - replicas
+            properties:
+              replicas:
+                type: integer
+                enum: [1, 3, 5]
+              offset:
+                type: integer
+                enum: [-1, 1]
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(syn::parse_file(&code).is_ok());
+        assert!(code.contains("#[derive(Clone, Debug)]\npub enum PrometheusReplicas {"));
+        assert!(code.contains("    Value1 = 1,\n    Value3 = 3,\n    Value5 = 5,\n}"));
+        assert!(code.contains("    ValueMinus1 = -1,\n    Value1 = 1,\n}"));
+        assert!(code.contains("impl Serialize for PrometheusReplicas {"));
+        assert!(code.contains("impl<'de> Deserialize<'de> for PrometheusOffset {"));
+        assert!(code.contains("-1 => Ok(Self::ValueMinus1),"));
+        assert!(code.contains("pub replicas: PrometheusReplicas,"));
+        assert!(code.contains("pub offset: Option<PrometheusOffset>,"));
+    }
+
+    #[test]
+    fn enums_with_defaults_derive_default() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              pullPolicy:
+                type: string
+                default: IfNotPresent
+                enum: [Always, IfNotPresent]
+              restartPolicy:
+                type: string
+                enum: [Always, Never]
+"#,
+        );
+        for args in [&["kopium", "-q"][..], &["kopium", "-q", "--derive", "Default"]] {
+            let kopium = Kopium::parse_from(args);
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            let code = String::from_utf8(buf).unwrap();
+            assert!(code.contains(
+                "#[derive(Serialize, Deserialize, Clone, Debug, Default)]\npub enum PrometheusPullPolicy {\n    Always,\n    #[default]\n    IfNotPresent,"
+            ));
+            assert!(code.contains(
+                "#[derive(Serialize, Deserialize, Clone, Debug)]\npub enum PrometheusRestartPolicy {"
+            ));
+        }
+    }
+
+    #[test]
+    fn derived_default_skips_structs_requiring_values() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              storage:
+                type: object
+                properties:
+                  volume:
+                    type: object
+                    properties:
+                      size:
+                        type: integer
+                    required: [size]
+                required: [volume]
+              retention:
+                type: object
+                properties:
+                  days:
+                    type: integer
+                  keep:
+                    type: object
+                    properties:
+                      last:
+                        type: integer
+                required: [keep]
+            required: [storage]
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q", "--derive", "Default"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(code.contains("#[derive(CustomResource, Serialize, Deserialize, Clone, Debug)]\n"));
+        assert!(!code.contains(r#"#[kube(derive = "Default")]"#));
+        assert!(
+            code.contains("#[derive(Serialize, Deserialize, Clone, Debug)]\npub struct PrometheusStorage {")
+        );
+        assert!(code.contains(
+            "#[derive(Serialize, Deserialize, Clone, Debug)]\npub struct PrometheusStorageVolume {"
+        ));
+        // a required struct that is defaultable itself does not stand in the way
+        assert!(code.contains(
+            "#[derive(Serialize, Deserialize, Clone, Debug, Default)]\npub struct PrometheusRetention {"
+        ));
+        assert!(code.contains(
+            "#[derive(Serialize, Deserialize, Clone, Debug, Default)]\npub struct PrometheusRetentionKeep {"
+        ));
+    }
+
+    #[test]
+    fn eq_and_hash_skip_float_subtrees() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              scaling:
+                type: object
+                properties:
+                  target:
+                    type: object
+                    properties:
+                      ratio:
+                        type: number
+              selector:
+                type: object
+                properties:
+                  app:
+                    type: string
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q", "-D", "PartialEq", "-D", "Eq", "-D", "Hash"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(code.contains(r#"#[kube(derive = "PartialEq")]"#));
+        assert!(!code.contains(r#"#[kube(derive = "Eq")]"#));
+        let derives = |name: &str| {
+            let end = code.find(&format!("pub struct {} {{", name)).unwrap();
+            let start = code[..end].rfind("#[derive(").unwrap();
+            code[start..end].trim().to_string()
+        };
+        assert_eq!(
+            derives("PrometheusScaling"),
+            "#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]"
+        );
+        assert_eq!(
+            derives("PrometheusScalingTarget"),
+            "#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]"
+        );
+        assert_eq!(
+            derives("PrometheusSelector"),
+            "#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]"
+        );
+    }
+
+    #[test]
+    fn enum_impls_round_trip_every_variant() {
+        use super::Kopium;
+        use clap::Parser;
+        use std::collections::BTreeMap;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              balancer:
+                type: string
+                enum: [round-robin, IPv4, 100m, "", None, 'a"b', Round_Robin]
+              statusCode:
+                type: integer
+                enum: [301, 302]
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q", "--enum-impls", "display,fromstr"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let file = syn::parse_file(&String::from_utf8(buf).unwrap()).unwrap();
+
+        // collect the (variant, value) pairs from the match arms of each impl
+        let arms = |trait_name: &str, ty: &str| {
+            let imp = file
+                .items
+                .iter()
+                .find_map(|item| match item {
+                    syn::Item::Impl(imp)
+                        if matches!(&*imp.self_ty, syn::Type::Path(p) if p.path.is_ident(ty))
+                            && imp.trait_.as_ref().is_some_and(|(_, path, _)| {
+                                path.segments.last().unwrap().ident == trait_name
+                            }) =>
+                    {
+                        Some(imp)
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| panic!("no {} impl for {}", trait_name, ty));
+            let body = imp
+                .items
+                .iter()
+                .find_map(|item| match item {
+                    syn::ImplItem::Fn(f) => Some(&f.block.stmts),
+                    _ => None,
+                })
+                .unwrap();
+            let matched = match &body[0] {
+                // Display: f.write_str(match self { .. })
+                syn::Stmt::Expr(syn::Expr::MethodCall(call), _) => &call.args[0],
+                // FromStr: match s { .. }
+                syn::Stmt::Expr(expr, _) => expr,
+                stmt => panic!("unexpected statement {}", quote::quote!(#stmt)),
+            };
+            let syn::Expr::Match(matched) = matched else {
+                panic!("no match in {} impl for {}", trait_name, ty)
+            };
+            let variant = |expr: &syn::Expr| match expr {
+                syn::Expr::Path(p) => p.path.segments.last().unwrap().ident.to_string(),
+                syn::Expr::Call(call) => match &call.args[0] {
+                    syn::Expr::Path(p) => p.path.segments.last().unwrap().ident.to_string(),
+                    arg => panic!("unexpected argument {}", quote::quote!(#arg)),
+                },
+                expr => panic!("unexpected expression {}", quote::quote!(#expr)),
+            };
+            let mut pairs = BTreeMap::new();
+            for arm in &matched.arms {
+                match (&arm.pat, &*arm.body) {
+                    // Display: Self::Variant => "value"
+                    (
+                        syn::Pat::Path(p),
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(value),
+                            ..
+                        }),
+                    ) => {
+                        pairs.insert(variant(&syn::Expr::Path(p.clone())), value.value());
+                    }
+                    // FromStr: "value" => Ok(Self::Variant)
+                    (
+                        syn::Pat::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(value),
+                            ..
+                        }),
+                        body,
+                    ) => {
+                        pairs.insert(variant(body), value.value());
+                    }
+                    (syn::Pat::Wild(_), _) => {}
+                    (pat, _) => panic!("unexpected pattern {}", quote::quote!(#pat)),
+                }
+            }
+            pairs
+        };
+        for (ty, values) in [
+            ("PrometheusBalancer", vec![
+                "round-robin",
+                "IPv4",
+                "100m",
+                "",
+                "None",
+                "a\"b",
+                "Round_Robin",
+            ]),
+            ("PrometheusStatusCode", vec!["301", "302"]),
+        ] {
+            let display = arms("Display", ty);
+            let from_str = arms("FromStr", ty);
+            assert_eq!(display.len(), values.len(), "{:?}", display);
+            // FromStr then Display yields the schema value for every variant
+            assert_eq!(display, from_str);
+            let mut displayed = display.values().cloned().collect::<Vec<_>>();
+            displayed.sort();
+            let mut expected = values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+            expected.sort();
+            assert_eq!(displayed, expected);
+        }
+        assert!(file
+            .items
+            .iter()
+            .any(|item| matches!(item, syn::Item::Struct(s) if s.ident == "ParseEnumError")));
+    }
+
+    #[test]
+    fn one_of_unions_are_untagged_enums() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              port:
+                oneOf:
+                - type: integer
+                - type: string
+              source:
+                oneOf:
+                - properties:
+                    configMap:
+                      type: string
+                  required: [configMap]
+                - properties:
+                    secret:
+                      type: string
+                  required: [secret]
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(code.contains(
+            "#[serde(untagged)]\npub enum PrometheusPort {\n    Integer(i64),\n    String(String),\n}"
+        ));
+        assert!(code.contains(
+            "#[serde(untagged)]\npub enum PrometheusSource {\n    ConfigMap(PrometheusSourceConfigMap),\n    Secret(PrometheusSourceSecret),\n}"
+        ));
+        assert!(code.contains("pub struct PrometheusSourceConfigMap {"));
+        assert!(code.contains("pub source: Option<PrometheusSource>,"));
+    }
+
+    #[test]
+    fn duration_fields_use_generated_helpers() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            required:
+            - renewBefore
+            properties:
+              renewBefore:
+                type: string
+                format: duration
+              timeout:
+                type: string
+                format: duration
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(std::iter::once("kopium").chain(args.iter().copied()));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["-q", "--duration-as", "std::time::Duration"]);
+        assert!(code.contains("pub use std::time::Duration;"));
+        assert!(code.contains("mod kopium_duration {"));
+        assert!(code.contains("#[serde(rename_all = \"camelCase\")]"));
+        assert!(code.contains("#[serde(with = \"kopium_duration\")]"));
+        assert!(code.contains("pub renew_before: Duration,"));
+        assert!(code.contains("with = \"kopium_duration::option\""));
+        assert!(code.contains("pub timeout: Option<Duration>,"));
+
+        let code = generate(&["-q"]);
+        assert!(!code.contains("kopium_duration"));
+        assert!(code.contains("pub renew_before: String,"));
+
+        // the helpers are not part of the prelude, which users then bring themselves
+        let code = generate(&["-q", "--hide-prelude", "--duration-as", "std::time::Duration"]);
+        assert!(!code.contains("mod prelude"));
+        assert!(code.contains("mod kopium_duration {"));
+        assert!(code.contains("#[serde(with = \"kopium_duration\")]"));
+        syn::parse_file(&code).unwrap();
+    }
+
+    #[test]
+    fn unique_lists_become_sets() {
+        use super::Kopium;
+        use clap::Parser;
+        use std::collections::BTreeSet;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            required:
+            - finalizers
+            properties:
+              finalizers:
+                type: array
+                x-kubernetes-list-type: set
+                items:
+                  type: string
+              ports:
+                type: array
+                uniqueItems: true
+                items:
+                  type: integer
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(std::iter::once("kopium").chain(args.iter().copied()));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["-q", "--sets", "--builders"]);
+        assert!(code.contains("pub use std::collections::BTreeSet;"));
+        assert!(code.contains("    #[builder(default)]\n    pub finalizers: BTreeSet<String>,"));
+        assert!(code.contains(
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    #[builder(default, setter(strip_option))]\n    pub ports: Option<BTreeSet<i64>>,"
+        ));
+        let code = generate(&["-q"]);
+        assert!(!code.contains("BTreeSet"));
+        assert!(code.contains("pub finalizers: Vec<String>,"));
+
+        // sets serialize in order regardless of the order they were read in
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Finalized {
+            finalizers: BTreeSet<String>,
+        }
+        let read = |json| serde_json::from_str::<Finalized>(json).unwrap();
+        let one = read(r#"{"finalizers": ["b.dev/cleanup", "a.dev/cleanup"]}"#);
+        let other = read(r#"{"finalizers": ["a.dev/cleanup", "b.dev/cleanup"]}"#);
+        let json = serde_json::to_string(&one).unwrap();
+        assert_eq!(json, r#"{"finalizers":["a.dev/cleanup","b.dev/cleanup"]}"#);
+        assert_eq!(json, serde_json::to_string(&other).unwrap());
+    }
+
+    #[test]
+    fn nullable_fields_round_trip_explicit_nulls() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            required:
+            - args
+            - image
+            - replicas
+            properties:
+              args:
+                type: array
+                nullable: true
+                items:
+                  type: string
+              image:
+                type: string
+                nullable: true
+              replicas:
+                type: integer
+                format: int32
+                nullable: true
+              selector:
+                type: object
+                nullable: true
+                additionalProperties:
+                  type: string
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        // no skip_serializing_if on required fields, so that null is written back
+        assert!(code.contains(
+            "    pub args: Option<Vec<String>>,\n    pub image: Option<String>,\n    pub replicas: Option<i32>,\n"
+        ));
+        assert!(code.contains(
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub selector: Option<BTreeMap<String, String>>,\n"
+        ));
+
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct PrometheusSpec {
+            args: Option<Vec<String>>,
+            image: Option<String>,
+            replicas: Option<i32>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            selector: Option<std::collections::BTreeMap<String, String>>,
+        }
+        let json = r#"{"args":null,"image":null,"replicas":3}"#;
+        let spec: PrometheusSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(spec, PrometheusSpec {
+            args: None,
+            image: None,
+            replicas: Some(3),
+            selector: None,
+        });
+        assert_eq!(serde_json::to_string(&spec).unwrap(), json);
+    }
+
+    #[test]
+    fn index_maps_keep_key_order() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              env:
+                type: object
+                additionalProperties:
+                  type: string
+              overrides:
+                type: array
+                items:
+                  type: object
+                  additionalProperties:
+                    type: integer
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(["kopium", "-q"].iter().chain(args));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        // never the default, as it adds a dependency
+        assert!(!generate(&[]).contains("IndexMap"));
+        let code = generate(&["--map-type", "IndexMap"]);
+        assert!(code.contains("// requires: indexmap with its serde feature\n"));
+        assert!(code.contains("    pub use indexmap::IndexMap;\n"));
+        assert!(code.contains("    pub env: Option<IndexMap<String, String>>,\n"));
+        assert!(code.contains("    pub overrides: Option<Vec<IndexMap<String, i64>>>,\n"));
+        assert!(!code.contains("BTreeMap"));
+        let code = generate(&["--map-type", "IndexMap", "-D", "JsonSchema"]);
+        assert!(code.contains(
+            "// requires: indexmap with its serde feature, and schemars with its indexmap2 feature\n"
+        ));
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct PrometheusSpec {
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            env: Option<indexmap::IndexMap<String, String>>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            overrides: Option<Vec<indexmap::IndexMap<String, i64>>>,
+        }
+        let json = r#"{"env":{"ZONE":"b","APP":"x","MODE":"c"},"overrides":[{"z":1,"a":2}]}"#;
+        let mut spec: PrometheusSpec = serde_json::from_str(json).unwrap();
+        let keys = spec.env.as_ref().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["ZONE", "APP", "MODE"]);
+        assert_eq!(serde_json::to_string(&spec).unwrap(), json);
+        // inserted keys go last
+        spec.env.as_mut().unwrap().insert("DEBUG".into(), "1".into());
+        assert!(serde_json::to_string(&spec)
+            .unwrap()
+            .contains(r#""MODE":"c","DEBUG":"1"}"#));
+    }
+
+    #[test]
+    fn unknown_fields_survive_a_round_trip() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            x-kubernetes-preserve-unknown-fields: true
+            properties:
+              extra:
+                type: string
+              replicas:
+                type: integer
+                format: int32
+"#,
+        );
+        let kopium = Kopium::parse_from(["kopium", "-q"]);
+        let mut buf = vec![];
+        kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+        let code = String::from_utf8(buf).unwrap();
+        assert!(code.contains("    pub extra: Option<String>,\n"));
+        assert!(
+            code.contains("    #[serde(flatten)]\n    pub extra_: BTreeMap<String, serde_json::Value>,\n")
+        );
+
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct PrometheusSpec {
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            extra: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            replicas: Option<i32>,
+            #[serde(flatten)]
+            extra_: std::collections::BTreeMap<String, serde_json::Value>,
+        }
+        let json = r#"{"extra":"declared","replicas":2,"vendor":{"tier":"gold"},"zone":null}"#;
+        let spec: PrometheusSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(spec.extra.as_deref(), Some("declared"));
+        assert_eq!(spec.extra_.len(), 2);
+        assert_eq!(spec.extra_["vendor"]["tier"], "gold");
+        assert_eq!(serde_json::to_string(&spec).unwrap(), json);
+    }
+
+    #[test]
+    fn embedded_resources_import_their_type() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              template:
+                type: object
+                x-kubernetes-embedded-resource: true
+                x-kubernetes-preserve-unknown-fields: true
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(std::iter::once("kopium").chain(args.iter().copied()));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["-q"]);
+        assert!(code.contains("pub use k8s_openapi::apimachinery::pkg::runtime::RawExtension;"));
+        assert!(code.contains("pub template: Option<RawExtension>,"));
+        assert!(!code.contains("DynamicObject"));
+
+        let code = generate(&["-q", "--embedded-as", "DynamicObject"]);
+        assert!(code.contains("pub use kube::core::DynamicObject;"));
+        assert!(code.contains("pub template: Option<DynamicObject>,"));
+        assert!(!code.contains("RawExtension"));
+    }
+
+    #[test]
+    fn int_or_string_can_be_coerced_to_string() {
+        use super::Kopium;
+        use clap::Parser;
+        let crd = crd_with_versions(
+            r#"
+  - name: v1
+    served: true
+    storage: true
+    schema:
+      openAPIV3Schema:
+        type: object
+        properties:
+          spec:
+            type: object
+            properties:
+              maxUnavailable:
+                x-kubernetes-int-or-string: true
+"#,
+        );
+        let generate = |args: &[&str]| {
+            let kopium = Kopium::parse_from(std::iter::once("kopium").chain(args.iter().copied()));
+            let mut buf = vec![];
+            kopium.generate(&mut buf, std::slice::from_ref(&crd)).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let code = generate(&["-q"]);
+        assert!(code.contains("pub use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;"));
+        assert!(code.contains("pub max_unavailable: Option<IntOrString>,"));
+
+        let code = generate(&["-q", "--coerce-intorstring-to-string"]);
+        assert!(!code.contains("IntOrString"));
+        assert!(code.contains("pub max_unavailable: Option<String>,"));
+    }
+
+    #[test]
+    fn missing_version_lists_available_versions() {
+        let crd = crd_with_versions(MULTI_VERSIONS);
+        let err = find_crd_version(&crd, Some("v2")).unwrap_err().to_string();
+        assert!(err.contains("Version 'v2' not found"));
+        assert!(err.contains("v1, v1beta1, v1alpha1"));
+        assert_eq!(all_versions(&crd), "v1, v1beta1, v1alpha1");
+    }
+}
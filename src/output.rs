@@ -1,9 +1,61 @@
-use std::cell::OnceCell;
+use std::{collections::BTreeMap, fmt};
 
 use heck::{ToPascalCase, ToSnakeCase};
 
-/// All found containers
-pub struct Output(pub Vec<Container>);
+use crate::Derive;
+
+/// All found containers, along with the warnings raised while finding them
+pub struct Output {
+    /// The structs and enums found, starting with the root
+    pub containers: Vec<Container>,
+    /// Messages about schemas that could not be represented faithfully
+    pub warnings: Vec<Warning>,
+}
+
+/// A message raised while analyzing a schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    /// How the generated code departs from the schema
+    pub kind: WarningKind,
+    /// What was found, along with the schema path it was found at
+    pub message: String,
+}
+
+/// How the generated code departs from the schema a [`Warning`] was raised for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The schema is still described faithfully, e.g. a map inlined rather than given a struct
+    Notice,
+    /// A value was typed as a plain `String`, e.g. a date without a format
+    Coerced,
+    /// A schema without a usable type fell back to `serde_json::Value` or an untyped map
+    Untyped,
+    /// A number or integer format is unknown and fell back to `f64` or `i64`
+    UnknownFormat,
+}
+
+impl Warning {
+    pub(crate) fn new(kind: WarningKind, message: String) -> Self {
+        Warning { kind, message }
+    }
+
+    /// Whether the generated code loses information the schema has
+    pub fn is_lossy(&self) -> bool {
+        self.kind != WarningKind::Notice
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl PartialEq<&str> for Warning {
+    fn eq(&self, other: &&str) -> bool {
+        self.message == *other
+    }
+}
 
 /// Output container found by analyzer
 #[derive(Default, Debug)]
@@ -18,11 +70,22 @@ pub struct Container {
     pub docs: Option<String>,
     /// Whether this container is an enum
     pub is_enum: bool,
-    pub supports_derive_default: OnceCell<bool>,
+    /// Serde annotations for the container, such as `rename_all = "camelCase"`
+    ///
+    /// The `rename_all` attribute is only set if `Container::rename` is called.
+    pub serde_annot: Vec<String>,
+    /// CEL rules validating the object from `x-kubernetes-validations`, along with their messages
+    ///
+    /// These are also documented in `docs`.
+    pub cel_rules: Vec<(String, String)>,
+    /// Field keying the items of a list represented as a map, which is left out of the struct
+    ///
+    /// This is the key field of `x-kubernetes-list-map-keys`, which the `kopium_list_map` module pulls out of the items.
+    pub map_key: Option<String>,
 }
 
 /// Output member belonging to an Container
-#[derive(Default, Debug)]
+#[derive(Default, Debug, PartialEq)]
 pub struct Member {
     /// The raw, unsanitized name of the member
     ///
@@ -45,6 +108,41 @@ pub struct Member {
     pub extra_annot: Vec<String>,
     /// Documentation properties extracted from the property
     pub docs: Option<String>,
+    /// Value of an integer enum variant, which is (de)serialized as this number
+    pub discriminant: Option<i64>,
+    /// Schema default the member takes when it is missing, with `Config::schema_defaults`
+    ///
+    /// The `default = "defaults::fn"` attribute is only set if `Output::default_fns` is called.
+    pub default: Option<serde_json::Value>,
+    /// Validation constraints declared by the schema of the member, with `Config::constraints`
+    ///
+    /// These are only turned into attributes if `Output::validation_fields` is called.
+    pub constraints: Option<Constraints>,
+    /// Schema of the member as declared, if its type derives a different one, with `Config::schemas`
+    ///
+    /// The `schemars(schema_with = "schemas::fn")` attribute is only set if `Output::schema_fns` is called.
+    pub schema: Option<serde_json::Value>,
+}
+
+/// Validation constraints of a schema, which serde does not check
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Constraints {
+    pub minimum: Option<f64>,
+    /// Whether the value must be greater than the minimum
+    pub exclusive_minimum: bool,
+    pub maximum: Option<f64>,
+    /// Whether the value must be less than the maximum
+    pub exclusive_maximum: bool,
+    /// Minimum number of characters of a string
+    pub min_length: Option<i64>,
+    /// Maximum number of characters of a string
+    pub max_length: Option<i64>,
+    /// Regular expression a string must match
+    pub pattern: Option<String>,
+    /// Minimum number of items of an array
+    pub min_items: Option<i64>,
+    /// Maximum number of items of an array
+    pub max_items: Option<i64>,
 }
 
 impl Container {
@@ -52,10 +150,18 @@ impl Container {
         self.members.iter().any(|m| m.type_.contains("BTreeMap"))
     }
 
+    pub fn uses_btreesets(&self) -> bool {
+        self.members.iter().any(|m| m.type_.contains("BTreeSet"))
+    }
+
     pub fn uses_hashmaps(&self) -> bool {
         self.members.iter().any(|m| m.type_.contains("HashMap"))
     }
 
+    pub fn uses_indexmaps(&self) -> bool {
+        self.members.iter().any(|m| m.type_.contains("IndexMap"))
+    }
+
     pub fn uses_datetime(&self) -> bool {
         self.members.iter().any(|m| m.type_.contains("DateTime"))
     }
@@ -64,6 +170,40 @@ impl Container {
         self.members.iter().any(|m| m.type_.contains("NaiveDate"))
     }
 
+    pub fn uses_byte_string(&self) -> bool {
+        self.members.iter().any(|m| m.type_.contains("ByteString"))
+    }
+
+    pub fn uses_duration(&self) -> bool {
+        self.members
+            .iter()
+            .any(|m| m.serde_annot.iter().any(|a| a.contains("kopium_duration")))
+    }
+
+    pub fn uses_list_maps(&self) -> bool {
+        self.members
+            .iter()
+            .any(|m| m.serde_annot.iter().any(|a| a.contains("kopium_list_map")))
+    }
+
+    /// Whether this is an enum with variants wrapping types, (de)serialized as whichever variant fits
+    pub fn is_untagged_enum(&self) -> bool {
+        self.is_enum && self.members.iter().any(|m| !m.type_.is_empty())
+    }
+
+    /// Whether this is an enum over integer values rather than strings
+    pub fn is_int_enum(&self) -> bool {
+        self.is_enum && self.members.iter().any(|m| m.discriminant.is_some())
+    }
+
+    pub fn uses_raw_extension(&self) -> bool {
+        self.members.iter().any(|m| m.type_.contains("RawExtension"))
+    }
+
+    pub fn uses_dynamic_object(&self) -> bool {
+        self.members.iter().any(|m| m.type_.contains("DynamicObject"))
+    }
+
     pub fn uses_int_or_string(&self) -> bool {
         self.members.iter().any(|m| m.type_.contains("IntOrString"))
     }
@@ -84,110 +224,358 @@ impl Container {
         self.members.iter().any(|m| m.type_.contains("Vec<Condition>"))
     }
 
-    /// Checks if default is implemented for all props, and if not, returns false
+    /// Whether every member of the struct can be left out, being an Option, a collection or schema defaulted
     ///
-    /// Behavior for --smart-derive-elision.
-    pub fn can_derive_default(&self, containers: &[Container]) -> bool {
-        if self.is_enum {
-            // Need to drop Default from enum as this cannot be derived.
-            // Enum defaults need to either be manually derived
-            // or we can insert enum defaults
-            return false;
-        }
-
-        if let Some(can_derive) = self.supports_derive_default.get() {
-            // only run recursive check against every struct once
-            return *can_derive;
-        }
-
-        for m in &self.members {
-            // If the type contains a <, it's a container type. All kopium containers (Map, Vec, Option) has impl Default.
-            // If the first character is lowercase, assume it's a built-in type and skip the check.
-            if !m.type_.contains('<')
-                && m.type_ != "String"
-                && m.type_ != "IntOrString"
-                && m.type_ != "NaiveDate"
-                && m.type_ != "DateTime"
-                && m.type_.chars().next().unwrap_or_default().is_uppercase()
-                && containers
-                    .iter()
-                    .find(|c| c.name == m.type_)
-                    .is_some_and(|c| !c.can_derive_default(containers))
-            {
-                self.supports_derive_default.set(false).unwrap();
-                return false;
-            }
+    /// Such structs implement Default, which is derived unless schema defaults need an impl of their own.
+    pub fn is_defaultable(&self) -> bool {
+        let collections = [
+            "Option<",
+            "Vec<",
+            "BTreeSet<",
+            "BTreeMap<",
+            "HashMap<",
+            "IndexMap<",
+        ];
+        !self.is_enum
+            && self
+                .members
+                .iter()
+                .all(|m| m.default.is_some() || collections.iter().any(|c| m.type_.starts_with(c)))
+    }
+
+    /// Whether the struct implements Default with its schema defaults, rather than deriving it
+    pub fn has_default_impl(&self) -> bool {
+        self.is_defaultable() && self.members.iter().any(|m| m.default.is_some())
+    }
+
+    /// Whether the enum can derive Default, as one of its variants is the schema default
+    ///
+    /// Structs deriving Default are decided by [`Container::is_defaultable`] instead.
+    pub fn can_derive_default(&self) -> bool {
+        self.is_enum
+            && self
+                .members
+                .iter()
+                .any(|m| m.extra_annot.iter().any(|a| a == "#[default]"))
+    }
+}
+
+impl Member {
+    /// Name of the function of the `defaults` module returning the schema default of the member
+    pub fn default_fn(&self) -> Option<&str> {
+        self.serde_annot
+            .iter()
+            .find_map(|a| a.strip_prefix("default = \"defaults::"))
+            .and_then(|a| a.strip_suffix('"'))
+    }
+
+    /// Name of the function of the `schemas` module returning the declared schema of the member
+    pub fn schema_fn(&self) -> Option<&str> {
+        self.extra_annot
+            .iter()
+            .find_map(|a| a.strip_prefix("#[schemars(schema_with = \"schemas::"))
+            .and_then(|a| a.strip_suffix("\")]"))
+    }
+
+    /// The name of the member or enum variant as it appears in serialized form
+    pub fn wire_name(&self) -> String {
+        if let Some(value) = self.discriminant {
+            return value.to_string();
         }
+        self.serde_annot
+            .iter()
+            .find_map(|a| a.strip_prefix("rename = "))
+            .and_then(|lit| syn::parse_str::<syn::LitStr>(lit).ok())
+            .map_or_else(|| self.name.clone(), |lit| lit.value())
+    }
+
+    /// Whether the property must be present on the wire, being neither optional, schema defaulted nor flattened
+    pub fn is_required(&self) -> bool {
+        self.default.is_none() && !self.serde_annot.iter().any(|a| a == "default" || a == "flatten")
+    }
 
-        // No members prevented us from deriving default.
-        self.supports_derive_default.set(true).unwrap();
-        true
+    /// The type of the member as generated, naming structs as they are emitted (see [`Container::emitted_name`])
+    pub fn emitted_type(&self, prefix: &str, naming: Naming) -> String {
+        match naming {
+            Naming::Short => trim_spec_infix(&self.type_, prefix),
+            Naming::FullPath => self.type_.clone(),
+        }
     }
 }
 
 impl Container {
     /// Rename all struct members to rust conventions
+    ///
+    /// Fields already named in snake_case keep their name (escaped if need be), and fields converted
+    /// to the same name (e.g. `fooBar` next to `foo_bar`) are disambiguated instead.
     pub fn rename(&mut self) {
         let mut seen = vec![]; // track names we output to avoid generating duplicates
+        let is_snake_case = |name: &str| name.to_snake_case() == name;
+        let kept = self
+            .members
+            .iter()
+            .filter(|m| !self.is_enum && is_snake_case(&m.name))
+            .filter_map(|m| sanitize_ident(&m.name, IdentKind::Member))
+            .collect::<Vec<_>>();
         for (i, m) in self.members.iter_mut().enumerate() {
+            if m.serde_annot.iter().any(|a| a == "flatten") {
+                // flattened fields have no name on the wire, and analysis picked a free field name
+                seen.push(m.name.clone());
+                continue;
+            }
             let mut new_name = if self.is_enum {
-                // There are no rust keywords that start uppercase,
-                // making this name always a valid identifier except if it contains
-                // or starts with an invalid character.
-                //
-                // `` -> `KopiumEmpty`
-                // `mod` -> `Mod`
-                // `301` -> `301` -> `r#301` -> `r#_301`
-                // `!=` -> `!=` -> `r#!=` -> `r#_!=` -> `KopiumVariant{i}`
-                let name = if m.name.is_empty() {
-                    "KopiumEmpty".to_owned()
-                } else if m.name == "-" {
-                    "KopiumDash".to_owned()
-                } else if m.name == "_" {
-                    "KopiumUnderscore".to_owned()
-                } else {
-                    m.name.to_pascal_case()
-                };
-
-                Container::try_escape_name(name).unwrap_or_else(|| format!("KopiumVariant{i}"))
-            } else if m.name == "-" {
-                "kopium_dash".to_owned()
-            } else if m.name == "_" {
-                "kopium_underscore".to_owned()
+                sanitize_ident(&m.name, IdentKind::Variant).unwrap_or_else(|| format!("KopiumVariant{i}"))
             } else {
-                Container::try_escape_name(m.name.to_snake_case())
-                    .unwrap_or_else(|| panic!("invalid field name '{}' could not be escaped", m.name))
+                sanitize_ident(&m.name, IdentKind::Member).unwrap_or_else(|| format!("kopium_field{i}"))
             };
             // The new, Rust correct name MIGHT clash with existing names in degenerate cases
             // such as those in https://github.com/kube-rs/kopium/issues/165
-            // so if duplicates are seen, we suffix an "X" to disamgiguate (repeatedly if needed)
-            while seen.contains(&new_name) {
-                let disambiguation_suffix = if self.is_enum { "X" } else { "_x" };
-                new_name = format!("{new_name}{disambiguation_suffix}"); // force disambiguate
+            // so if duplicates are seen, we suffix an index (variants) or "_x" (fields) to disambiguate
+            if self.is_enum && seen.contains(&new_name) {
+                // keep numbers apart, e.g. `V1` and `V1_2` rather than `V12`
+                let separator = if new_name.ends_with(|c: char| c.is_ascii_digit()) {
+                    "_"
+                } else {
+                    ""
+                };
+                let base = new_name.clone();
+                let mut index = 2;
+                while seen.contains(&new_name) {
+                    new_name = format!("{base}{separator}{index}");
+                    index += 1;
+                }
+            }
+            let own = !self.is_enum && is_snake_case(&m.name);
+            let taken = |name: &String| seen.contains(name) || (!own && kept.contains(name));
+            while taken(&new_name) {
+                new_name = format!("{}_x", new_name.trim_end_matches('_')); // force disambiguate
             }
             seen.push(new_name.clone());
 
             if new_name != m.name {
-                m.serde_annot.push(format!("rename = \"{}\"", m.name));
+                // debug formatting escapes quotes and backslashes into a valid string literal
+                m.serde_annot.push(format!("rename = {:?}", m.name));
                 m.name = new_name;
             }
         }
+        if !self.is_enum {
+            self.rename_all();
+        }
+    }
+
+    /// The property of a struct member, or the value of an enum variant, as it appears in serialized form
+    ///
+    /// Unlike [`Member::wire_name`], this takes a `rename_all` on the struct into account.
+    /// Flattened members have no property of their own.
+    pub fn member_wire_name(&self, m: &Member) -> Option<String> {
+        if m.serde_annot.iter().any(|a| a == "flatten") {
+            return None;
+        }
+        let renamed = m.serde_annot.iter().any(|a| a.starts_with("rename = "));
+        if !renamed
+            && self
+                .serde_annot
+                .iter()
+                .any(|a| a == r#"rename_all = "camelCase""#)
+        {
+            return Some(serde_camel_case(&m.name));
+        }
+        Some(m.wire_name())
+    }
+
+    /// Name the container is emitted under, given the struct prefix it was analyzed with
+    ///
+    /// Containers below the spec drop the `Spec` infix from their name, e.g. `FooSpecTls` becomes `FooTls`,
+    /// unless they are named after their full path.
+    pub fn emitted_name(&self, prefix: &str, naming: Naming) -> String {
+        if self.is_main_container() || naming == Naming::FullPath {
+            self.name.clone()
+        } else {
+            trim_spec_infix(&self.name, prefix)
+        }
+    }
+
+    /// Rename camelCase fields through a `rename_all = "camelCase"` on the struct, where that takes fewer annotations
+    ///
+    /// Fields that serde would not turn back into their property (e.g. `foo_bar` or `kebab-case`) get a `rename` instead,
+    /// as do properties that are not plain camelCase words (e.g. `5xxRate`), so their wire name is spelled out.
+    fn rename_all(&mut self) {
+        let renames = |m: &Member| m.serde_annot.iter().position(|a| a.starts_with("rename = "));
+        let is_camel_case = |wire: &str| {
+            wire.starts_with(|c: char| c.is_ascii_lowercase())
+                && wire.chars().all(|c| c.is_ascii_alphanumeric())
+        };
+        let fits = |m: &Member| {
+            let wire = m.wire_name();
+            m.serde_annot.iter().any(|a| a == "flatten")
+                || (is_camel_case(&wire) && serde_camel_case(&m.name) == wire)
+        };
+        let renamed = self.members.iter().filter(|m| renames(m).is_some()).count();
+        let misfits = self.members.iter().filter(|m| !fits(m)).count();
+        if misfits >= renamed {
+            return;
+        }
+        for m in &mut self.members {
+            match renames(m) {
+                Some(i) if fits(m) => {
+                    m.serde_annot.remove(i);
+                }
+                None if !fits(m) => m.serde_annot.push(format!("rename = {:?}", m.name)),
+                _ => {}
+            }
+        }
+        self.serde_annot.push(r#"rename_all = "camelCase""#.to_string());
     }
 
     /// Add builder annotations
     pub fn builder_fields(&mut self) {
         for m in &mut self.members {
-            if m.type_.starts_with("Option<") {
+            if m.type_ == "String" {
+                m.extra_annot.push("#[builder(setter(into))]".to_string());
+            } else if m.type_ == "Option<String>" {
+                m.extra_annot
+                    .push("#[builder(default, setter(into, strip_option))]".to_string());
+            } else if m.type_.starts_with("Option<") {
                 m.extra_annot
                     .push("#[builder(default, setter(strip_option))]".to_string());
-            } else if m.type_.starts_with("Vec<") || m.type_.starts_with("BTreeMap<") {
+            } else if ["Vec<", "BTreeSet<", "BTreeMap<"]
+                .iter()
+                .any(|c| m.type_.starts_with(c))
+            {
                 m.extra_annot.push("#[builder(default)]".to_string());
             }
         }
     }
 
+    /// Reject fields the struct does not declare, unless it keeps them in a flattened member
+    pub fn deny_unknown_fields(&mut self) {
+        let keeps_unknown = self
+            .members
+            .iter()
+            .any(|m| m.serde_annot.iter().any(|a| a == "flatten"));
+        if !self.is_enum && !keeps_unknown {
+            self.serde_annot.push("deny_unknown_fields".to_string());
+        }
+    }
+
+    /// Mark fields documented as deprecated with `#[deprecated]`, with the rest of the sentence as the note
+    pub fn deprecated_fields(&mut self) {
+        if self.is_enum {
+            return;
+        }
+        for m in &mut self.members {
+            match m.docs.as_deref().and_then(deprecation_note) {
+                Some(note) if note.is_empty() => m.extra_annot.push("#[deprecated]".to_string()),
+                Some(note) => m.extra_annot.push(format!("#[deprecated(note = {:?})]", note)),
+                None => {}
+            }
+        }
+    }
+
+    /// Add validation attributes checking the constraints of members
+    ///
+    /// Members of the `structs` passed are validated recursively.
+    /// Constraints the backend cannot check are documented instead.
+    pub fn validation_fields(&mut self, validation: Validation, structs: &[String]) {
+        if self.is_enum {
+            return;
+        }
+        for m in &mut self.members {
+            let mut rules = vec![];
+            let mut unmapped = vec![];
+            if structs.iter().any(|s| s == nested_type(&m.type_, validation)) {
+                rules.push(validation.nested_rule().to_string());
+            }
+            if let Some(c) = &m.constraints {
+                constraint_rules(c, &m.type_, validation, &mut rules, &mut unmapped);
+            }
+            if rules.is_empty() && validation == Validation::Garde {
+                // garde requires opting out of validation explicitly
+                rules.push("skip".to_string());
+            }
+            if !rules.is_empty() {
+                m.extra_annot
+                    .push(format!("#[{}({})]", validation.attribute(), rules.join(", ")));
+            }
+            if !unmapped.is_empty() {
+                let unmapped = unmapped.join("\n");
+                m.docs = Some(match m.docs.take() {
+                    Some(d) => format!("{}\n\n{}", d, unmapped),
+                    None => unmapped,
+                });
+            }
+        }
+    }
+
+    /// Sanitizes an ascii enum value into a PascalCase variant name, if it can be made a valid identifier
+    ///
+    /// Invalid characters separate words, leading digits get a `V` prefix,
+    /// and values without any usable characters get a descriptive `Kopium` name:
+    ///
+    /// `round-robin` -> `RoundRobin`
+    /// `100m` -> `V100m`
+    /// `1.5` -> `V1_5`
+    /// `` -> `KopiumEmpty`
+    /// `Self` -> `KopiumSelf`
+    /// `!=` -> None
+    fn variant_name(value: &str) -> Option<String> {
+        match value {
+            "" => return Some("KopiumEmpty".to_owned()),
+            "-" => return Some("KopiumDash".to_owned()),
+            "_" => return Some("KopiumUnderscore".to_owned()),
+            _ => {}
+        }
+        let mut name = String::new();
+        for word in value
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+        {
+            let word = word.to_pascal_case();
+            if name.ends_with(|c: char| c.is_ascii_digit()) && word.starts_with(|c: char| c.is_ascii_digit())
+            {
+                name.push('_');
+            }
+            name.push_str(&word);
+        }
+        if name.is_empty() {
+            return None;
+        }
+        if name.starts_with(|c: char| c.is_numeric()) {
+            name.insert(0, 'V');
+        }
+        // `Self` is the only keyword starting uppercase
+        [name.clone(), format!("Kopium{name}")]
+            .into_iter()
+            .find(|n| syn::parse_str::<syn::Ident>(n).is_ok())
+    }
+
+    /// Snake cased field name for an ascii property, if it can be made a valid identifier
+    ///
+    /// `storageClassName` -> `storage_class_name`
+    /// `kubernetes.io/hostname` -> `kubernetes_io_hostname`
+    /// `type` -> `r#type`
+    /// `self` -> `self_`
+    /// `5xxRate` -> `r#_5xx_rate` (a leading digit gets an underscore, and a rename keeps the property)
+    /// `-` -> `kopium_dash`
+    /// `+` -> None
+    fn field_name(property: &str) -> Option<String> {
+        match property {
+            "-" => return Some("kopium_dash".to_owned()),
+            "_" => return Some("kopium_underscore".to_owned()),
+            _ => {}
+        }
+        Container::try_escape_name(property.to_snake_case())
+    }
+
     /// Tries to escape a field or variant name into a valid Rust identifier.
+    ///
+    /// Keywords become raw identifiers, except for those that cannot be raw (`self`, `super`, `crate`),
+    /// which get an underscore suffixed instead.
     fn try_escape_name(name: String) -> Option<String> {
+        // reserved from the 2024 edition on, which syn parses as an identifier
+        if name == "gen" {
+            return Some("r#gen".to_owned());
+        }
         if syn::parse_str::<syn::Ident>(&name).is_ok() {
             return Some(name);
         }
@@ -197,6 +585,11 @@ impl Container {
             return Some(escaped_name);
         }
 
+        let escaped_name = format!("{name}_");
+        if syn::parse_str::<syn::Ident>(&escaped_name).is_ok() {
+            return Some(escaped_name);
+        }
+
         let escaped_name = format!("r#_{name}");
         if syn::parse_str::<syn::Ident>(&escaped_name).is_ok() {
             return Some(escaped_name);
@@ -214,7 +607,7 @@ impl Output {
     ///
     /// It is unsound to skip this step. Some CRDs use kebab-cased members is invalid in Rust.
     pub fn rename(mut self) -> Self {
-        for c in &mut self.0 {
+        for c in &mut self.containers {
             c.rename();
         }
         self
@@ -224,29 +617,626 @@ impl Output {
     ///
     /// Adds #[builder(default, setter(strip_option))] to all option types.
     /// Adds #[builder(default)] to required vec and btreemaps.
+    /// Adds setter(into) to strings, so that they can be set from a &str.
     pub fn builder_fields(mut self, builders: bool) -> Self {
         if builders {
-            for c in &mut self.0 {
+            for c in &mut self.containers {
                 c.builder_fields()
             }
         }
         self
     }
+
+    /// Point members with a schema default at a function returning it
+    ///
+    /// Adds #[serde(default = "defaults::foo_spec_replicas")], naming a function of the `defaults` module
+    /// after the container and the member, which the printer generates from the same attribute.
+    pub fn default_fns(mut self) -> Self {
+        let mut seen = vec![];
+        for c in &mut self.containers {
+            let container = c.name.to_snake_case();
+            for m in c.members.iter_mut().filter(|m| m.default.is_some()) {
+                let mut name = format!("{}_{}", container, m.name.trim_start_matches("r#"));
+                while seen.contains(&name) {
+                    name.push_str("_x");
+                }
+                m.serde_annot.push(format!("default = \"defaults::{}\"", name));
+                seen.push(name);
+            }
+        }
+        self
+    }
+
+    /// Point members that derive a schema different from the declared one at a function returning it
+    ///
+    /// Adds #[schemars(schema_with = "schemas::foo_spec_config")] to such members of structs deriving JsonSchema,
+    /// naming a function of the `schemas` module which the printer generates from the same attribute.
+    pub fn schema_fns(mut self, derives: &[Derive]) -> Self {
+        let mut seen = vec![];
+        for c in &mut self.containers {
+            let derives_schema = derives
+                .iter()
+                .any(|d| d.derived_trait == "JsonSchema" && d.is_applicable_to(c));
+            if c.is_enum || !derives_schema {
+                continue;
+            }
+            let container = c.name.to_snake_case();
+            for m in c.members.iter_mut().filter(|m| m.schema.is_some()) {
+                let mut name = format!("{}_{}", container, m.name.trim_start_matches("r#"));
+                while seen.contains(&name) {
+                    name.push_str("_x");
+                }
+                m.extra_annot
+                    .push(format!("#[schemars(schema_with = \"schemas::{}\")]", name));
+                seen.push(name);
+            }
+        }
+        self
+    }
+
+    /// Reject unknown fields when deserializing all output structs
+    ///
+    /// Adds #[serde(deny_unknown_fields)] to structs, except those collecting unknown fields in a flattened map.
+    pub fn deny_unknown_fields(mut self, deny: bool) -> Self {
+        if deny {
+            for c in &mut self.containers {
+                c.deny_unknown_fields()
+            }
+        }
+        self
+    }
+
+    /// Mark deprecated members of all output structs
+    ///
+    /// Adds #[deprecated(note = "...")] to fields whose description starts with a deprecation.
+    pub fn deprecated_fields(mut self, deprecated: bool) -> Self {
+        if deprecated {
+            for c in &mut self.containers {
+                c.deprecated_fields()
+            }
+        }
+        self
+    }
+
+    /// Add validation attributes to all output structs
+    ///
+    /// Adds #[garde(range(min = 1))], #[validate(length(max = 10))] and the like to members with constraints,
+    /// and recurses into members of struct types.
+    pub fn validation_fields(mut self, validation: Option<Validation>) -> Self {
+        if let Some(validation) = validation {
+            let structs = self
+                .containers
+                .iter()
+                .filter(|c| !c.is_enum)
+                .map(|c| c.name.clone())
+                .collect::<Vec<_>>();
+            for c in &mut self.containers {
+                c.validation_fields(validation, &structs)
+            }
+        }
+        self
+    }
+
+    /// Collapse structurally identical containers into one
+    ///
+    /// Big CRDs repeat the same schema (e.g. a label selector) in many places, generating a container
+    /// for each. Of containers with identical members, the one with the shortest name (or the first of those)
+    /// is kept, and references to the others are pointed at it. The root and top level containers are always kept.
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        if !dedup {
+            return self;
+        }
+        // names are compared as emitted, where names below the spec drop their `Spec` infix
+        let kind = self
+            .containers
+            .iter()
+            .find(|c| c.is_root())
+            .map(|c| c.name.clone())
+            .unwrap_or_default();
+        let spec = format!("{}Spec", kind);
+        let emitted_len = |name: &str| {
+            name.strip_prefix(&spec)
+                .map_or(name.len(), |rest| kind.len() + rest.len())
+        };
+        // parents can only turn out identical once their children were collapsed
+        loop {
+            let nested = self.containers.iter().filter(|c| c.level > 1).collect::<Vec<_>>();
+            let mut renames = BTreeMap::new();
+            for c in &nested {
+                let same = nested.iter().filter(|o| {
+                    o.is_enum == c.is_enum
+                        && o.members == c.members
+                        && o.cel_rules == c.cel_rules
+                        && o.map_key == c.map_key
+                });
+                let kept = same.clone().min_by_key(|o| emitted_len(&o.name)).unwrap_or(c);
+                for o in same.filter(|o| o.name != kept.name) {
+                    renames.insert(o.name.clone(), kept.name.clone());
+                }
+            }
+            if renames.is_empty() {
+                return self;
+            }
+            self.containers.retain(|c| !renames.contains_key(&c.name));
+            for m in self.containers.iter_mut().flat_map(|c| c.members.iter_mut()) {
+                m.type_ = rename_types(&m.type_, &renames);
+            }
+        }
+    }
+}
+
+/// Containers that cannot soundly implement Default, along with the reason
+///
+/// A struct can if every member is an Option, a collection, schema defaulted, or a struct that can itself,
+/// so that the default does not make up values the schema requires. Enums can if a variant is the schema default.
+pub fn default_exclusions(containers: &[Container]) -> BTreeMap<String, String> {
+    let collections = [
+        "Option<",
+        "Vec<",
+        "BTreeSet<",
+        "BTreeMap<",
+        "HashMap<",
+        "IndexMap<",
+    ];
+    exclusions(containers, |c, m| {
+        if c.is_enum {
+            return match c
+                .members
+                .iter()
+                .any(|m| m.extra_annot.iter().any(|a| a == "#[default]"))
+            {
+                true => Ok(vec![]),
+                false => Err("no variant is the schema default".to_string()),
+            };
+        }
+        if m.default.is_some() || collections.iter().any(|c| m.type_.starts_with(c)) {
+            return Ok(vec![]);
+        }
+        let type_ = m.type_.strip_prefix("Box<").and_then(|t| t.strip_suffix('>'));
+        let type_ = type_.unwrap_or(&m.type_);
+        if containers.iter().any(|c| c.name == type_) {
+            Ok(vec![type_.to_string()])
+        } else {
+            Err(format!("{} is a required {}", m.name, m.type_))
+        }
+    })
+}
+
+/// Containers that cannot implement Eq, along with the reason
+///
+/// Floats cannot, and neither can some of the Kubernetes types used for members, which only implement PartialEq.
+pub fn eq_exclusions(containers: &[Container]) -> BTreeMap<String, String> {
+    let lacking = ["f32", "f64", "Condition", "RawExtension", "DynamicObject"];
+    exclusions(containers, |_, m| member_types(m, containers, &lacking))
+}
+
+/// Containers that cannot implement Hash, along with the reason
+///
+/// Besides the types lacking Eq, json values, hash and index maps and some Kubernetes types implement Eq but not Hash.
+pub fn hash_exclusions(containers: &[Container]) -> BTreeMap<String, String> {
+    let lacking = [
+        "f32",
+        "f64",
+        "Condition",
+        "RawExtension",
+        "DynamicObject",
+        "serde_json::Value",
+        "HashMap",
+        "IndexMap",
+        "IntOrString",
+        "ByteString",
+    ];
+    exclusions(containers, |_, m| member_types(m, containers, &lacking))
+}
+
+/// Containers in the type of a member, or the reason it rules a trait out by holding one of the `lacking` types
+fn member_types(m: &Member, containers: &[Container], lacking: &[&str]) -> Result<Vec<String>, String> {
+    let types = m
+        .type_
+        .split(['<', '>', ',', ' '])
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(t) = types.iter().find(|t| lacking.contains(t)) {
+        return Err(format!("{} holds {}", m.name, t));
+    }
+    Ok(types
+        .into_iter()
+        .filter(|t| containers.iter().any(|c| c.name == *t))
+        .map(String::from)
+        .collect())
+}
+
+/// Containers that cannot implement a trait, along with the reason
+///
+/// `requires` gives the reason a member rules the trait out by itself, or else the containers that need to
+/// implement the trait for the member to. Containers requiring an excluded one are excluded in turn,
+/// taking as many passes as the longest chain of containers requiring each other.
+fn exclusions(
+    containers: &[Container],
+    requires: impl Fn(&Container, &Member) -> Result<Vec<String>, String>,
+) -> BTreeMap<String, String> {
+    let mut excluded = BTreeMap::<String, String>::new();
+    loop {
+        let mut found = vec![];
+        for c in containers.iter().filter(|c| !excluded.contains_key(&c.name)) {
+            let reason = c.members.iter().find_map(|m| match requires(c, m) {
+                Err(reason) => Some(reason),
+                Ok(types) => types
+                    .into_iter()
+                    .find(|t| excluded.contains_key(t))
+                    .map(|t| format!("{} needs {}, which is excluded", m.name, t)),
+            });
+            if let Some(reason) = reason {
+                found.push((c.name.clone(), reason));
+            }
+        }
+        if found.is_empty() {
+            return excluded;
+        }
+        excluded.extend(found);
+    }
+}
+
+/// Add the validation rules for the constraints of a member of type `type_` to `rules`
+///
+/// Constraints that cannot be checked, because the backend lacks a rule or the type does not fit it,
+/// are described in `unmapped` instead.
+fn constraint_rules(
+    c: &Constraints,
+    type_: &str,
+    validation: Validation,
+    rules: &mut Vec<String>,
+    unmapped: &mut Vec<String>,
+) {
+    let inner = type_
+        .strip_prefix("Option<")
+        .and_then(|t| t.strip_suffix('>'))
+        .unwrap_or(type_);
+    let is_float = matches!(inner, "f32" | "f64");
+
+    let mut range = vec![];
+    let bounds = [
+        ("min", "Minimum", c.minimum, c.exclusive_minimum),
+        ("max", "Maximum", c.maximum, c.exclusive_maximum),
+    ];
+    for (bound, doc, value, exclusive) in bounds {
+        let Some(value) = value else { continue };
+        let literal = if is_float {
+            Some(format!("{:?}", value))
+        } else {
+            int_literal(
+                inner,
+                value,
+                exclusive && validation == Validation::Garde,
+                bound == "min",
+            )
+        };
+        match literal {
+            Some(literal) if !exclusive => range.push(format!("{} = {}", bound, literal)),
+            Some(literal) if validation == Validation::Validator => {
+                range.push(format!("exclusive_{} = {}", bound, literal))
+            }
+            // garde has no exclusive bounds, but they can be moved by one for integers
+            Some(literal) if !is_float => range.push(format!("{} = {}", bound, literal)),
+            _ if exclusive => unmapped.push(format!("Exclusive {}: {}", doc.to_lowercase(), value)),
+            _ => unmapped.push(format!("{}: {}", doc, value)),
+        }
+    }
+    if !range.is_empty() {
+        rules.push(format!("range({})", range.join(", ")));
+    }
+
+    let is_string = inner == "String";
+    let mut length = vec![];
+    if is_string && validation == Validation::Garde {
+        // kubernetes counts characters, rather than the bytes garde counts by default
+        length.push("chars".to_string());
+    }
+    let lengths = [
+        ("min", "Minimum length", c.min_length),
+        ("max", "Maximum length", c.max_length),
+    ];
+    for (bound, doc, value) in lengths {
+        match value {
+            Some(value) if is_string && value >= 0 => length.push(format!("{} = {}", bound, value)),
+            Some(value) => unmapped.push(format!("{}: {}", doc, value)),
+            None => {}
+        }
+    }
+    let is_array = inner.starts_with("Vec<") || inner.starts_with("BTreeSet<");
+    let items = [
+        ("min", "Minimum items", c.min_items),
+        ("max", "Maximum items", c.max_items),
+    ];
+    for (bound, doc, value) in items {
+        match value {
+            Some(value) if is_array && value >= 0 => length.push(format!("{} = {}", bound, value)),
+            Some(value) => unmapped.push(format!("{}: {}", doc, value)),
+            None => {}
+        }
+    }
+    if length.iter().any(|l| l != "chars") {
+        rules.push(format!("length({})", length.join(", ")));
+    }
+
+    if let Some(pattern) = &c.pattern {
+        if is_string && validation == Validation::Garde {
+            rules.push(format!("pattern({:?})", pattern));
+        } else if pattern.contains('`') {
+            unmapped.push(format!("Pattern: {}", pattern));
+        } else {
+            unmapped.push(format!("Pattern: `{}`", pattern));
+        }
+    }
+}
+
+/// An integer bound as a literal of the integer type `type_`, if it is one that fits
+///
+/// With `exclusive`, the bound is moved by one to be inclusive, upwards for a minimum.
+fn int_literal(type_: &str, value: f64, exclusive: bool, minimum: bool) -> Option<String> {
+    let (lowest, highest) = match type_ {
+        "i32" => (i32::MIN as f64, i32::MAX as f64),
+        "i64" => (i64::MIN as f64, i64::MAX as f64),
+        "u32" => (0.0, u32::MAX as f64),
+        "u64" => (0.0, u64::MAX as f64),
+        _ => return None,
+    };
+    if value.fract() != 0.0 {
+        return None;
+    }
+    let value = match (exclusive, minimum) {
+        (false, _) => value,
+        (true, true) => value + 1.0,
+        (true, false) => value - 1.0,
+    };
+    // the bounds of 64 bit types are rounded up as floats, so they are left out
+    if value < lowest || value >= highest {
+        return None;
+    }
+    Some(format!("{}", value as i128))
+}
+
+/// The innermost type of a member, which is validated recursively if it is a struct
+///
+/// Looks through options, vectors and boxes, and the values of maps for backends that can validate them.
+fn nested_type(type_: &str, validation: Validation) -> &str {
+    let mut inner = type_;
+    loop {
+        let unwrapped = ["Option<", "Vec<", "Box<"]
+            .iter()
+            .find_map(|w| inner.strip_prefix(w))
+            .or_else(|| {
+                let maps = ["BTreeMap<String, ", "HashMap<String, ", "IndexMap<String, "];
+                maps.iter()
+                    .find_map(|w| inner.strip_prefix(w))
+                    .filter(|_| validation == Validation::Validator)
+            });
+        match unwrapped.and_then(|t| t.strip_suffix('>')) {
+            Some(t) => inner = t,
+            None => return inner,
+        }
+    }
+}
+
+/// The note of the deprecation a description starts with, or None if it does not start with one
+///
+/// Descriptions count as deprecations when they start with `DEPRECATED`, or with `Deprecated` followed by
+/// punctuation, as in `Deprecated: use foo instead.` (with the note `use foo instead`). Descriptions that merely
+/// mention deprecation, such as `Deprecated fields are ignored.` or `Replaces the deprecated foo.`, do not.
+fn deprecation_note(docs: &str) -> Option<String> {
+    let docs = docs.trim_start();
+    let rest = docs
+        .strip_prefix("DEPRECATED")
+        .or_else(|| docs.strip_prefix("Deprecated"))?;
+    let punctuated = rest.is_empty() || rest.starts_with([':', '.', ',', ';', '-', '!']);
+    if rest.starts_with(char::is_alphanumeric) || !(punctuated || docs.starts_with("DEPRECATED")) {
+        return None;
+    }
+    let note = rest.trim_start_matches(|c: char| c.is_whitespace() || ":.,;-!".contains(c));
+    let end = [note.find(". "), note.find('\n')]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(note.len());
+    Some(note[..end].trim().trim_end_matches('.').to_string())
+}
+
+/// What an identifier made by [`sanitize_ident`] names
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum IdentKind {
+    /// A snake_case struct field, e.g. `storage_class_name`
+    Member,
+    /// A PascalCase enum variant, e.g. `RoundRobin`
+    Variant,
+    /// A PascalCase part of a type name, e.g. `HttpGet`, which may start with a digit as it follows a prefix
+    Suffix,
+}
+
+/// Sanitizes a property name or enum value into an identifier, if any usable characters are left
+///
+/// This is where all names are made valid: non-ascii characters are transliterated (`ünïcode` -> `unicode`,
+/// `🚀` -> `rocket`), before other invalid characters separate words, leading digits get a prefix,
+/// and keywords are escaped (see `Container::field_name` and `Container::variant_name`).
+/// `Container::rename` keeps the original through a serde rename, and disambiguates names that end up the same.
+pub(crate) fn sanitize_ident(name: &str, kind: IdentKind) -> Option<String> {
+    let ascii = deunicode::deunicode_with_tofu(name, " ");
+    match kind {
+        IdentKind::Member => Container::field_name(&ascii),
+        IdentKind::Variant => Container::variant_name(&ascii),
+        IdentKind::Suffix => Some(ascii.to_pascal_case()).filter(|s| !s.is_empty()),
+    }
+}
+
+/// The property serde's `rename_all = "camelCase"` gives a field, e.g. `storageClassName` for `storage_class_name`
+fn serde_camel_case(field: &str) -> String {
+    // serde renames the field without its raw identifier prefix, as pascal case with a lowercase first letter
+    let field = field.strip_prefix("r#").unwrap_or(field);
+    let mut pascal = String::with_capacity(field.len());
+    let mut capitalize = true;
+    for c in field.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            pascal.push(c.to_ascii_uppercase());
+            capitalize = false;
+        } else {
+            pascal.push(c);
+        }
+    }
+    let mut chars = pascal.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_ascii_lowercase().to_string() + chars.as_str()
+    })
+}
+
+/// Replace the names of containers within a type such as `Option<Vec<FooRule>>`
+fn rename_types(type_: &str, renames: &BTreeMap<String, String>) -> String {
+    let mut renamed = String::with_capacity(type_.len());
+    let mut rest = type_;
+    while let Some(c) = rest.chars().next() {
+        let end = rest
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        if end == 0 {
+            renamed.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let (word, tail) = rest.split_at(end);
+        renamed.push_str(renames.get(word).map_or(word, String::as_str));
+        rest = tail;
+    }
+    renamed
+}
+
+/// A type with the `Spec` infix dropped from the names below the spec, e.g. `Option<FooTls>` for `Option<FooSpecTls>`
+///
+/// Only names starting with the prefix and `Spec` followed by another word are changed,
+/// so that neither the spec itself nor e.g. `FooSpecification` is.
+fn trim_spec_infix(type_: &str, prefix: &str) -> String {
+    let spec = format!("{}Spec", prefix);
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut trimmed = String::with_capacity(type_.len());
+    let mut rest = type_;
+    while let Some(start) = rest.find(is_ident) {
+        trimmed.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (name, tail) = rest.split_at(rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len()));
+        match name.strip_prefix(&spec) {
+            Some(word) if word.starts_with(|c: char| c.is_ascii_uppercase() || c.is_ascii_digit()) => {
+                trimmed.push_str(prefix);
+                trimmed.push_str(word);
+            }
+            _ => trimmed.push_str(name),
+        }
+        rest = tail;
+    }
+    trimmed.push_str(rest);
+    trimmed
 }
 
 /// Type used for additionalProperties maps
-#[derive(clap::ValueEnum, Clone, Copy, Default, Debug)]
-#[clap(rename_all = "PascalCase")]
+#[derive(Clone, Copy, Default, Debug)]
 pub enum MapType {
     #[default]
     BTreeMap,
     HashMap,
+    /// Keeps the order of the keys, needing the indexmap crate with its serde feature
+    IndexMap,
 }
 impl MapType {
     pub fn name(&self) -> &str {
         match self {
             Self::BTreeMap => "BTreeMap",
             Self::HashMap => "HashMap",
+            Self::IndexMap => "IndexMap",
+        }
+    }
+}
+
+/// Visibility of the generated structs, enums and fields
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum Visibility {
+    #[default]
+    Pub,
+    PubCrate,
+}
+impl Visibility {
+    pub fn keyword(&self) -> &str {
+        match self {
+            Self::Pub => "pub",
+            Self::PubCrate => "pub(crate)",
+        }
+    }
+}
+
+/// Strategy for naming the generated structs and enums
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum Naming {
+    /// Short names, e.g. singular for list items and taken from schema titles
+    #[default]
+    Short,
+    /// Names joining every property key from the root, which stay the same as other parts of the schema change
+    FullPath,
+}
+
+/// Type used for strings with a duration format
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum DurationType {
+    #[default]
+    String,
+    /// std::time::Duration (de)serialized from Go duration strings
+    Duration,
+}
+
+/// Crate deriving `Validate` on generated structs, to check the constraints of their schemas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Validation {
+    /// The garde crate, which needs its regex feature for patterns
+    Garde,
+    /// The validator crate, which cannot check patterns
+    Validator,
+}
+impl Validation {
+    /// Path of the derived trait, which the prelude imports
+    pub fn trait_path(&self) -> &str {
+        match self {
+            Self::Garde => "garde::Validate",
+            Self::Validator => "validator::Validate",
+        }
+    }
+
+    /// Name of the field attribute taking the rules
+    fn attribute(&self) -> &str {
+        match self {
+            Self::Garde => "garde",
+            Self::Validator => "validate",
+        }
+    }
+
+    /// Rule validating a member recursively
+    fn nested_rule(&self) -> &str {
+        match self {
+            Self::Garde => "dive",
+            Self::Validator => "nested",
+        }
+    }
+}
+
+/// Type used for objects embedding a Kubernetes resource
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum EmbeddedType {
+    /// k8s_openapi's RawExtension, holding the resource as arbitrary json
+    #[default]
+    RawExtension,
+    /// kube's DynamicObject, with typed metadata
+    DynamicObject,
+}
+impl EmbeddedType {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::RawExtension => "RawExtension",
+            Self::DynamicObject => "DynamicObject",
         }
     }
 }
@@ -254,7 +1244,7 @@ impl MapType {
 // unit tests
 #[cfg(test)]
 mod test {
-    use super::{Container, Member};
+    use super::{default_exclusions, eq_exclusions, hash_exclusions, Container, Member, Output};
     fn name_only_enum_member(name: &str) -> Member {
         Member {
             name: name.to_string(),
@@ -262,6 +1252,10 @@ mod test {
             serde_annot: vec![],
             extra_annot: vec![],
             docs: None,
+            discriminant: None,
+            default: None,
+            constraints: None,
+            schema: None,
         }
     }
     fn name_only_int_member(name: &str) -> Member {
@@ -271,9 +1265,364 @@ mod test {
             serde_annot: vec![],
             extra_annot: vec![],
             docs: None,
+            discriminant: None,
+            default: None,
+            constraints: None,
+            schema: None,
         }
     }
 
+    #[test]
+    fn enum_variants_are_sanitized() {
+        let sanitized = |values: &[&str]| {
+            let mut c = Container {
+                members: values.iter().map(|v| name_only_enum_member(v)).collect(),
+                is_enum: true,
+                ..Container::default()
+            };
+            c.rename();
+            c.members.into_iter().map(|m| m.name).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sanitized(&[
+                "round-robin",
+                "IPv4",
+                "100m",
+                "",
+                "None",
+                "1.5",
+                "15",
+                "Self",
+                "!="
+            ]),
+            vec![
+                "RoundRobin",
+                "IPv4",
+                "V100m",
+                "KopiumEmpty",
+                "None",
+                "V1_5",
+                "V15",
+                "KopiumSelf",
+                "KopiumVariant8"
+            ]
+        );
+        assert_eq!(sanitized(&["1", "-1", "+1"]), vec!["V1", "V1_2", "V1_3"]);
+    }
+
+    #[test]
+    fn sanitized_enum_variants_are_unique_identifiers() {
+        let nasty = [
+            "round-robin",
+            "IPv4",
+            "IPV4",
+            "ipv4",
+            "100m",
+            "1.5",
+            "15",
+            "-1",
+            "1",
+            "",
+            "-",
+            "_",
+            "None",
+            "Self",
+            "self",
+            "!=",
+            "a.b",
+            "AB",
+            "über",
+            "x²",
+            "  ",
+            "foo bar",
+            "foo_bar",
+            "fooBar",
+            "V100m",
+            "KopiumEmpty",
+            "a\"b",
+            "back\\slash",
+        ];
+        // the list itself, plus deterministic pseudo-random values built from nasty fragments
+        let fragments = [
+            "a", "B", "1", "0", "-", ".", "_", " ", "é", "\"", "Self", "none", "²", "/",
+        ];
+        let mut lists = vec![nasty.iter().map(|v| v.to_string()).collect::<Vec<_>>()];
+        let mut seed: u64 = 0x5eed;
+        let mut next = |bound: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % bound
+        };
+        for _ in 0..200 {
+            let values = (0..1 + next(12))
+                .map(|_| {
+                    (0..next(5))
+                        .map(|_| fragments[next(fragments.len())])
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>();
+            lists.push(values);
+        }
+
+        for values in lists {
+            let mut c = Container {
+                members: values.iter().map(|v| name_only_enum_member(v)).collect(),
+                is_enum: true,
+                ..Container::default()
+            };
+            c.rename();
+            let mut names = std::collections::HashSet::new();
+            for (m, value) in c.members.iter().zip(&values) {
+                assert!(
+                    syn::parse_str::<syn::Ident>(&m.name).is_ok(),
+                    "{:?} -> {}",
+                    value,
+                    m.name
+                );
+                assert!(names.insert(&m.name), "duplicate {} in {:?}", m.name, values);
+                // the wire value is kept unless the variant name already is that value
+                let renamed = m.serde_annot.iter().find_map(|a| a.strip_prefix("rename = "));
+                let wire = renamed.map(|lit| syn::parse_str::<syn::LitStr>(lit).unwrap().value());
+                assert_eq!(wire.as_ref().unwrap_or(&m.name), value);
+            }
+        }
+    }
+
+    #[test]
+    fn exotic_names_are_transliterated() {
+        use super::{sanitize_ident, IdentKind};
+        let sanitized = |name: &str| {
+            [IdentKind::Member, IdentKind::Variant, IdentKind::Suffix].map(|kind| sanitize_ident(name, kind))
+        };
+        let some = |names: [&str; 3]| names.map(|n| Some(n.to_string()));
+        assert_eq!(sanitized("ünïcode"), some(["unicode", "Unicode", "Unicode"]));
+        assert_eq!(sanitized("🚀"), some(["rocket", "Rocket", "Rocket"]));
+        assert_eq!(sanitized("日本"), some(["ri_ben", "RiBen", "RiBen"]));
+        assert_eq!(sanitized("$schema"), some(["schema", "Schema", "Schema"]));
+        assert_eq!(
+            sanitized("with space"),
+            some(["with_space", "WithSpace", "WithSpace"])
+        );
+        assert_eq!(sanitized("5xx"), some(["r#_5xx", "V5xx", "5xx"]));
+        assert_eq!(sanitized("+"), [None, None, None]);
+    }
+
+    #[test]
+    fn sanitized_fields_are_unique_identifiers() {
+        use super::{sanitize_ident, IdentKind};
+        // deterministic pseudo-random properties built from nasty fragments
+        let fragments = [
+            "a", "B", "1", "0", "-", ".", "/", "_", " ", "$", "é", "ß", "日", "🚀", "\"", "\\", "self",
+            "type", "Self", "\u{0}", "\u{200b}", "x²",
+        ];
+        let mut seed: u64 = 0x5eed;
+        let mut next = |bound: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % bound
+        };
+        for _ in 0..500 {
+            let properties = (0..1 + next(12))
+                .map(|_| {
+                    (0..next(5))
+                        .map(|_| fragments[next(fragments.len())])
+                        .collect::<String>()
+                })
+                .collect::<std::collections::BTreeSet<_>>();
+            let mut c = Container {
+                members: properties.iter().map(|p| name_only_int_member(p)).collect(),
+                ..Container::default()
+            };
+            c.rename();
+            let mut names = std::collections::HashSet::new();
+            for (m, property) in c.members.iter().zip(&properties) {
+                assert!(
+                    syn::parse_str::<syn::Ident>(&m.name).is_ok(),
+                    "{:?} -> {}",
+                    property,
+                    m.name
+                );
+                assert!(names.insert(&m.name), "duplicate {} in {:?}", m.name, properties);
+                assert_eq!(
+                    &c.member_wire_name(m).unwrap(),
+                    property,
+                    "{} in {:?}",
+                    m.name,
+                    properties
+                );
+            }
+            // suffixes follow a prefix in a type name
+            for property in &properties {
+                let suffix = sanitize_ident(property, IdentKind::Suffix).unwrap_or_default();
+                assert!(
+                    syn::parse_str::<syn::Ident>(&format!("Foo{suffix}")).is_ok(),
+                    "{:?}",
+                    property
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn deprecations_are_anchored_to_the_start() {
+        use super::deprecation_note;
+        let note = |docs: &str| deprecation_note(docs);
+        assert_eq!(
+            note("Deprecated: use `spec.tls` instead."),
+            Some("use `spec.tls` instead".into())
+        );
+        assert_eq!(
+            note("DEPRECATED: This field is ignored. More text"),
+            Some("This field is ignored".into())
+        );
+        assert_eq!(
+            note("Deprecated. Use template instead.\nSecond line"),
+            Some("Use template instead".into())
+        );
+        assert_eq!(note("  Deprecated, use foo\nbar"), Some("use foo".into()));
+        assert_eq!(note("DEPRECATED use selector"), Some("use selector".into()));
+        assert_eq!(note("Deprecated"), Some("".into()));
+        assert_eq!(note("Deprecated fields are ignored."), None);
+        assert_eq!(note("Deprecation policy of the API."), None);
+        assert_eq!(note("DeprecatedSince is the version deprecating it."), None);
+        assert_eq!(
+            note("Replaces the deprecated `foo` field. Deprecated: never."),
+            None
+        );
+        assert_eq!(note("deprecated: lowercase is a mention"), None);
+        assert_eq!(note("The name of the thing."), None);
+    }
+
+    #[test]
+    fn deprecated_fields_are_marked() {
+        let mut c = Container {
+            name: "FakeStruct".to_string(),
+            level: 1,
+            members: vec![
+                Member {
+                    docs: Some("Deprecated: use \"spec.port\" instead.".into()),
+                    ..name_only_int_member("hostPort")
+                },
+                Member {
+                    docs: Some("DEPRECATED".into()),
+                    ..name_only_int_member("legacy")
+                },
+                Member {
+                    docs: Some("Supersedes the deprecated hostPort.".into()),
+                    ..name_only_int_member("port")
+                },
+                name_only_int_member("undocumented"),
+            ],
+            ..Container::default()
+        };
+        c.deprecated_fields();
+        let annots = c
+            .members
+            .iter()
+            .map(|m| m.extra_annot.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(annots, [
+            vec![r#"#[deprecated(note = "use \"spec.port\" instead")]"#.to_string()],
+            vec!["#[deprecated]".to_string()],
+            vec![],
+            vec![],
+        ]);
+    }
+
+    #[test]
+    fn constraints_become_validation_rules() {
+        use super::{Constraints, Validation};
+        let container = || Container {
+            name: "FakeStruct".to_string(),
+            level: 1,
+            members: vec![
+                Member {
+                    constraints: Some(Constraints {
+                        minimum: Some(0.0),
+                        exclusive_minimum: true,
+                        maximum: Some(10.0),
+                        ..Constraints::default()
+                    }),
+                    ..name_only_int_member("replicas")
+                },
+                Member {
+                    type_: "Option<f64>".to_string(),
+                    constraints: Some(Constraints {
+                        maximum: Some(1.0),
+                        exclusive_maximum: true,
+                        ..Constraints::default()
+                    }),
+                    ..name_only_int_member("ratio")
+                },
+                Member {
+                    type_: "String".to_string(),
+                    constraints: Some(Constraints {
+                        min_length: Some(1),
+                        pattern: Some(r#"^"\d+"$"#.to_string()),
+                        ..Constraints::default()
+                    }),
+                    ..name_only_int_member("name")
+                },
+                Member {
+                    type_: "Option<Vec<FakeItem>>".to_string(),
+                    constraints: Some(Constraints {
+                        max_items: Some(3),
+                        min_length: Some(1),
+                        ..Constraints::default()
+                    }),
+                    ..name_only_int_member("items")
+                },
+                Member {
+                    type_: "BTreeMap<String, FakeItem>".to_string(),
+                    ..name_only_int_member("byName")
+                },
+                name_only_int_member("unconstrained"),
+            ],
+            ..Container::default()
+        };
+        let structs = ["FakeStruct".to_string(), "FakeItem".to_string()];
+        let annots = |c: &Container| {
+            c.members
+                .iter()
+                .map(|m| m.extra_annot.join(""))
+                .collect::<Vec<_>>()
+        };
+
+        let mut c = container();
+        c.validation_fields(Validation::Garde, &structs);
+        assert_eq!(annots(&c), [
+            "#[garde(range(min = 1, max = 10))]",
+            "#[garde(skip)]",
+            r#"#[garde(length(chars, min = 1), pattern("^\"\\d+\"$"))]"#,
+            "#[garde(dive, length(max = 3))]",
+            "#[garde(skip)]",
+            "#[garde(skip)]",
+        ]);
+        let docs = c.members.iter().map(|m| m.docs.as_deref()).collect::<Vec<_>>();
+        assert_eq!(docs, [
+            None,
+            Some("Exclusive maximum: 1"),
+            None,
+            Some("Minimum length: 1"),
+            None,
+            None
+        ]);
+
+        let mut c = container();
+        c.validation_fields(Validation::Validator, &structs);
+        assert_eq!(annots(&c), [
+            "#[validate(range(exclusive_min = 0, max = 10))]",
+            "#[validate(range(exclusive_max = 1.0))]",
+            "#[validate(length(min = 1))]",
+            "#[validate(nested, length(max = 3))]",
+            "#[validate(nested)]",
+            "",
+        ]);
+        assert_eq!(c.members[2].docs.as_deref(), Some(r#"Pattern: `^"\d+"$`"#));
+    }
+
     #[test]
     fn rename_avoids_producing_name_clashes() {
         let mut c = Container {
@@ -296,13 +1645,13 @@ mod test {
 
         c.rename();
         assert_eq!(&c.members[0].name, "Replace");
-        assert_eq!(&c.members[1].name, "ReplaceX");
+        assert_eq!(&c.members[1].name, "Replace2");
         assert_eq!(&c.members[2].name, "Hashmod");
         assert_eq!(&c.members[3].name, "HashMod");
         assert_eq!(&c.members[4].name, "JwksUri");
-        assert_eq!(&c.members[5].name, "JwksUriX");
-        assert_eq!(&c.members[6].name, "JwksUriXX");
-        assert_eq!(&c.members[7].name, "JwksUriXXX");
+        assert_eq!(&c.members[5].name, "JwksUri2");
+        assert_eq!(&c.members[6].name, "JwksUri3");
+        assert_eq!(&c.members[7].name, "JwksUri4");
         assert_eq!(c.members.len(), 8);
         // ditto for a struct
         let mut cs = Container {
@@ -325,87 +1674,431 @@ mod test {
     }
 
     #[test]
-    fn can_derive_default() {
-        let containers = vec![
-            Container {
-                name: "Simple".to_string(),
-                level: 1,
-                ..Container::default()
-            },
-            Container {
-                name: "Enum".to_string(),
-                level: 1,
-                is_enum: true,
-                ..Container::default()
-            },
-            Container {
-                name: "Nested".to_string(),
-                level: 1,
-                members: vec![Member {
-                    name: "simple".to_string(),
-                    type_: "Simple".to_string(),
-                    ..Member::default()
-                }],
-                ..Container::default()
-            },
-            Container {
-                name: "ReferencesEnum".to_string(),
-                level: 1,
-                members: vec![Member {
-                    name: "enum".to_string(),
-                    type_: "Enum".to_string(),
-                    ..Member::default()
-                }],
-                ..Container::default()
-            },
-            Container {
-                name: "ReferencesEnumNested".to_string(),
-                level: 1,
-                members: vec![Member {
-                    name: "references_enum".to_string(),
-                    type_: "ReferencesEnum".to_string(),
-                    ..Member::default()
-                }],
-                ..Container::default()
-            },
-            Container {
-                name: "ReferencesEnumOption".to_string(),
-                level: 1,
-                members: vec![Member {
-                    name: "maybe_enum".to_string(),
-                    type_: "Option<Enum>".to_string(),
+    fn fields_are_snake_cased() {
+        let mut c = Container {
+            name: "FakeStruct".to_string(),
+            level: 1,
+            members: vec![
+                Member {
+                    serde_annot: vec![
+                        "default".into(),
+                        "skip_serializing_if = \"Option::is_none\"".into(),
+                    ],
+                    ..name_only_int_member("storageClassName")
+                },
+                name_only_int_member("fooBar"),
+                name_only_int_member("foo_bar"),
+                name_only_int_member("$ref"),
+                name_only_int_member("+"),
+                name_only_int_member("replicas"),
+                name_only_int_member("@type"),
+                name_only_int_member("type"),
+            ],
+            ..Container::default()
+        };
+        c.rename();
+        let names = c.members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+        // the field already in snake_case keeps its name
+        assert_eq!(names, [
+            "storage_class_name",
+            "foo_bar_x",
+            "foo_bar",
+            "r#ref",
+            "kopium_field4",
+            "replicas",
+            "r#type_x",
+            "r#type",
+        ]);
+        // renamed through the struct, along with the existing annotations
+        assert_eq!(c.members[0].serde_annot, [
+            "default",
+            "skip_serializing_if = \"Option::is_none\"",
+        ]);
+        let wire = c
+            .members
+            .iter()
+            .map(|m| c.member_wire_name(m).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(wire, [
+            "storageClassName",
+            "fooBar",
+            "foo_bar",
+            "$ref",
+            "+",
+            "replicas",
+            "@type",
+            "type",
+        ]);
+    }
+
+    #[test]
+    fn punctuated_properties_are_snake_cased() {
+        let mut c = Container {
+            name: "FakeStruct".to_string(),
+            level: 1,
+            members: vec![
+                name_only_int_member("kubernetes.io/hostname"),
+                name_only_int_member("app.kubernetes.io-name"),
+                name_only_int_member("max-replicas"),
+                name_only_int_member("x--y..z"),
+                name_only_int_member("a.b-c"),
+                name_only_int_member("a-b.c"),
+                name_only_int_member("a/b/c"),
+            ],
+            ..Container::default()
+        };
+        c.rename();
+        let names = c.members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, [
+            "kubernetes_io_hostname",
+            "app_kubernetes_io_name",
+            "max_replicas",
+            "x_y_z",
+            "a_b_c",
+            "a_b_c_x",
+            "a_b_c_x_x",
+        ]);
+        // every property keeps its exact name on the wire
+        for (m, property) in c.members.iter().zip([
+            "kubernetes.io/hostname",
+            "app.kubernetes.io-name",
+            "max-replicas",
+            "x--y..z",
+            "a.b-c",
+            "a-b.c",
+            "a/b/c",
+        ]) {
+            assert_eq!(m.serde_annot, [format!("rename = {property:?}")]);
+            assert_eq!(c.member_wire_name(m).unwrap(), property);
+        }
+        assert!(c.serde_annot.is_empty());
+    }
+
+    /// The property a field is (de)serialized as, given the serde annotations of its struct
+    #[test]
+    fn camel_case_fields_are_renamed_by_the_struct() {
+        let mut c = Container {
+            name: "FakeStruct".to_string(),
+            level: 1,
+            members: vec![
+                Member {
+                    serde_annot: vec!["default".into()],
+                    ..name_only_int_member("storageClassName")
+                },
+                name_only_int_member("replicas"),
+                name_only_int_member("podIP"),
+                name_only_int_member("type"),
+                name_only_int_member("self"),
+                name_only_int_member("nodeSelector"),
+            ],
+            ..Container::default()
+        };
+        c.rename();
+        assert_eq!(c.serde_annot, [r#"rename_all = "camelCase""#]);
+        let annots = c
+            .members
+            .iter()
+            .map(|m| m.serde_annot.join(", "))
+            .collect::<Vec<_>>();
+        // only podIP does not come back from pod_ip
+        assert_eq!(annots, ["default", "", r#"rename = "podIP""#, "", "", ""]);
+        for (m, property) in c.members.iter().zip([
+            "storageClassName",
+            "replicas",
+            "podIP",
+            "type",
+            "self",
+            "nodeSelector",
+        ]) {
+            assert_eq!(c.member_wire_name(m).unwrap(), property);
+        }
+
+        // mixed conventions keep their renames
+        let mut c = Container {
+            name: "FakeStruct".to_string(),
+            level: 1,
+            members: vec![
+                name_only_int_member("storageClassName"),
+                name_only_int_member("nodeSelector"),
+                name_only_int_member("max-surge"),
+                name_only_int_member("foo_bar"),
+                name_only_int_member("x.y"),
+            ],
+            ..Container::default()
+        };
+        c.rename();
+        assert_eq!(c.serde_annot, [r#"rename_all = "camelCase""#]);
+        let annots = c
+            .members
+            .iter()
+            .map(|m| m.serde_annot.join(", "))
+            .collect::<Vec<_>>();
+        assert_eq!(annots, [
+            "",
+            "",
+            r#"rename = "max-surge""#,
+            r#"rename = "foo_bar""#,
+            r#"rename = "x.y""#
+        ]);
+        for (m, property) in
+            c.members
+                .iter()
+                .zip(["storageClassName", "nodeSelector", "max-surge", "foo_bar", "x.y"])
+        {
+            assert_eq!(c.member_wire_name(m).unwrap(), property);
+        }
+
+        // unless the struct attribute would not save any
+        let mut c = Container {
+            name: "FakeStruct".to_string(),
+            level: 1,
+            members: vec![
+                name_only_int_member("storageClassName"),
+                name_only_int_member("foo_bar"),
+                name_only_int_member("max-surge"),
+            ],
+            ..Container::default()
+        };
+        c.rename();
+        assert!(c.serde_annot.is_empty());
+        let wire = c.members.iter().map(|m| m.wire_name()).collect::<Vec<_>>();
+        assert_eq!(wire, ["storageClassName", "foo_bar", "max-surge"]);
+
+        // enums are never renamed as a whole
+        let mut c = Container {
+            name: "FakeEnum".to_string(),
+            level: 1,
+            members: vec![name_only_enum_member("fooBar"), name_only_enum_member("bazQux")],
+            is_enum: true,
+            ..Container::default()
+        };
+        c.rename();
+        assert!(c.serde_annot.is_empty());
+    }
+
+    #[test]
+    fn structs_of_optional_members_are_defaultable() {
+        let member = |type_: &str, default: Option<serde_json::Value>| Member {
+            name: "foo".to_string(),
+            type_: type_.to_string(),
+            default,
+            ..Member::default()
+        };
+        let container = |members: Vec<Member>| Container {
+            members,
+            ..Container::default()
+        };
+        let optional = container(vec![
+            member("Option<String>", None),
+            member("Vec<String>", None),
+            member("BTreeMap<String, String>", None),
+        ]);
+        assert!(optional.is_defaultable());
+        assert!(!optional.has_default_impl());
+
+        let required = container(vec![member("Option<String>", None), member("String", None)]);
+        assert!(!required.is_defaultable());
+        assert!(!required.has_default_impl());
+        let required_struct = container(vec![member("FooSpecBar", None)]);
+        assert!(!required_struct.is_defaultable());
+
+        let defaulted = container(vec![
+            member("Option<String>", None),
+            member("i64", Some(serde_json::json!(1))),
+        ]);
+        assert!(defaulted.is_defaultable());
+        assert!(defaulted.has_default_impl());
+
+        let defaulted_and_required = container(vec![
+            member("String", None),
+            member("i64", Some(serde_json::json!(1))),
+        ]);
+        assert!(!defaulted_and_required.has_default_impl());
+
+        let enumeration = Container {
+            is_enum: true,
+            ..container(vec![])
+        };
+        assert!(!enumeration.is_defaultable());
+    }
+
+    #[test]
+    fn default_is_excluded_along_chains() {
+        let container = |name: &str, members: &[(&str, &str)]| Container {
+            name: name.to_string(),
+            level: 1,
+            members: members
+                .iter()
+                .map(|(name, type_)| Member {
+                    name: name.to_string(),
+                    type_: type_.to_string(),
                     ..Member::default()
-                }],
-                ..Container::default()
-            },
-            Container {
-                name: "ReferencesEnumVec".to_string(),
-                level: 1,
-                members: vec![Member {
-                    name: "enum_list".to_string(),
-                    type_: "Vec<Enum>".to_string(),
+                })
+                .collect(),
+            ..Container::default()
+        };
+        // ordered so that a single pass would miss the chain
+        let containers = vec![
+            container("Spec", &[("a", "A"), ("name", "Option<String>")]),
+            container("A", &[("b", "B")]),
+            container("B", &[("replicas", "i64")]),
+            container("Optional", &[("b", "Option<B>"), ("bs", "Vec<B>")]),
+            container("Boxed", &[("optional", "Box<Optional>")]),
+        ];
+        let excluded = default_exclusions(&containers);
+        assert_eq!(excluded.keys().collect::<Vec<_>>(), ["A", "B", "Spec"]);
+        assert_eq!(excluded["B"], "replicas is a required i64");
+        assert_eq!(excluded["A"], "b needs B, which is excluded");
+        assert_eq!(excluded["Spec"], "a needs A, which is excluded");
+
+        // required members with a schema default do not need one of their own
+        let mut defaulted = container("Defaulted", &[("replicas", "i64")]);
+        defaulted.members[0].default = Some(serde_json::json!(1));
+        assert!(default_exclusions(&[defaulted]).is_empty());
+
+        // enums need a variant that is the schema default
+        let mut enumeration = container("Policy", &[("Always", ""), ("Never", "")]);
+        enumeration.is_enum = true;
+        let excluded = default_exclusions(&[enumeration]);
+        assert_eq!(excluded["Policy"], "no variant is the schema default");
+    }
+
+    #[test]
+    fn eq_and_hash_are_excluded_along_chains() {
+        let container = |name: &str, members: &[(&str, &str)]| Container {
+            name: name.to_string(),
+            level: 1,
+            members: members
+                .iter()
+                .map(|(name, type_)| Member {
+                    name: name.to_string(),
+                    type_: type_.to_string(),
                     ..Member::default()
-                }],
-                ..Container::default()
-            },
-            Container {
-                name: "ReferencesEnumNestedOption".to_string(),
-                level: 1,
-                members: vec![Member {
-                    name: "maybe_references_enum".to_string(),
-                    type_: "Option<ReferencesEnum>".to_string(),
+                })
+                .collect(),
+            ..Container::default()
+        };
+        let containers = vec![
+            container("Spec", &[
+                ("autoscaling", "Option<Autoscaling>"),
+                ("rules", "Vec<Rule>"),
+            ]),
+            container("Autoscaling", &[("targets", "Option<BTreeMap<String, Target>>")]),
+            container("Target", &[("utilization", "Option<f64>")]),
+            container("Rule", &[
+                ("port", "Option<IntOrString>"),
+                ("extra", "Option<Box<Rule>>"),
+            ]),
+            container("Labels", &[("values", "BTreeMap<String, String>")]),
+        ];
+        let excluded = eq_exclusions(&containers);
+        assert_eq!(excluded.keys().collect::<Vec<_>>(), [
+            "Autoscaling",
+            "Spec",
+            "Target"
+        ]);
+        assert_eq!(excluded["Target"], "utilization holds f64");
+        assert_eq!(excluded["Autoscaling"], "targets needs Target, which is excluded");
+        assert_eq!(
+            excluded["Spec"],
+            "autoscaling needs Autoscaling, which is excluded"
+        );
+
+        // a recursive struct can still implement Eq, but IntOrString lacks Hash
+        let excluded = hash_exclusions(&containers);
+        assert_eq!(excluded.keys().collect::<Vec<_>>(), [
+            "Autoscaling",
+            "Rule",
+            "Spec",
+            "Target"
+        ]);
+        assert_eq!(excluded["Rule"], "port holds IntOrString");
+    }
+
+    #[test]
+    fn can_derive_default() {
+        let variant = |annot: &[&str]| Member {
+            name: "Always".to_string(),
+            extra_annot: annot.iter().map(|a| a.to_string()).collect(),
+            ..Member::default()
+        };
+        let enum_ = |members| Container {
+            name: "Enum".to_string(),
+            level: 1,
+            is_enum: true,
+            members,
+            ..Container::default()
+        };
+        assert!(enum_(vec![variant(&["#[default]"])]).can_derive_default());
+        assert!(!enum_(vec![variant(&[])]).can_derive_default());
+        assert!(!enum_(vec![]).can_derive_default());
+        let simple = Container {
+            name: "Simple".to_string(),
+            level: 1,
+            ..Container::default()
+        };
+        assert!(!simple.can_derive_default());
+    }
+
+    #[test]
+    fn identical_containers_are_collapsed() {
+        let container = |name: &str, level: u8, members: &[(&str, &str)]| Container {
+            name: name.to_string(),
+            level,
+            members: members
+                .iter()
+                .map(|(name, type_)| Member {
+                    name: name.to_string(),
+                    type_: type_.to_string(),
                     ..Member::default()
-                }],
-                ..Container::default()
-            },
+                })
+                .collect(),
+            ..Container::default()
+        };
+        let containers = vec![
+            container("FooSpec", 1, &[
+                ("job", "FooSpecJob"),
+                ("template", "Option<FooSpecTemplate>"),
+                ("selectors", "Vec<FooSpecSelector>"),
+            ]),
+            container("FooSpecJob", 2, &[("selector", "Option<FooSpecJobSelector>")]),
+            container("FooSpecJobSelector", 3, &[(
+                "matchLabels",
+                "BTreeMap<String, String>",
+            )]),
+            container("FooSpecTemplate", 2, &[(
+                "selector",
+                "Option<FooSpecTemplateSelector>",
+            )]),
+            container("FooSpecTemplateSelector", 3, &[(
+                "matchLabels",
+                "BTreeMap<String, String>",
+            )]),
+            container("FooSpecSelector", 2, &[(
+                "matchLabels",
+                "BTreeMap<String, String>",
+            )]),
+            container("FooStatus", 1, &[("selector", "Option<FooStatusSelector>")]),
+            container("FooStatusSelector", 2, &[(
+                "matchLabels",
+                "BTreeMap<String, String>",
+            )]),
         ];
-        assert!(containers[0].can_derive_default(&containers)); // Simple
-        assert!(!containers[1].can_derive_default(&containers)); // Enum
-        assert!(containers[2].can_derive_default(&containers)); // Nested
-        assert!(!containers[3].can_derive_default(&containers)); // ReferencesEnum
-        assert!(!containers[4].can_derive_default(&containers)); // ReferencesEnumNested
-        assert!(containers[5].can_derive_default(&containers)); // ReferencesEnumOption
-        assert!(containers[6].can_derive_default(&containers)); // ReferencesEnumVec
-        assert!(containers[7].can_derive_default(&containers)); // ReferencesEnumNestedOption
+        let output = Output {
+            containers,
+            warnings: vec![],
+        };
+        let kept = output.dedup(true).containers;
+        let names = kept.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        // job and template only became identical once their selectors were collapsed
+        assert_eq!(names, ["FooSpec", "FooSpecJob", "FooSpecSelector", "FooStatus"]);
+        let types = |c: &Container| c.members.iter().map(|m| m.type_.clone()).collect::<Vec<_>>();
+        assert_eq!(types(&kept[0]), [
+            "FooSpecJob",
+            "Option<FooSpecJob>",
+            "Vec<FooSpecSelector>"
+        ]);
+        assert_eq!(types(&kept[1]), ["Option<FooSpecSelector>"]);
+        assert_eq!(types(&kept[3]), ["Option<FooSpecSelector>"]);
     }
 }
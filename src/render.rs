@@ -0,0 +1,1103 @@
+//! Writes analyzed containers out as Rust code, along with the kube attributes of their CRD
+use crate::{
+    default_exclusions, eq_exclusions, hash_exclusions, Container, Derive, Naming, Validation, Visibility,
+};
+use anyhow::{bail, Result};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceDefinition, CustomResourceDefinitionVersion,
+};
+use quote::format_ident;
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Containers analyzed from a version of a CRD, to be rendered along with the kube attributes of the CRD
+pub struct Analyzed<'a> {
+    pub crd: &'a CustomResourceDefinition,
+    pub version: &'a CustomResourceDefinitionVersion,
+    /// The structs and enums found for the version, as returned by [`analyze`](crate::analyze)
+    pub containers: Vec<Container>,
+}
+
+/// How analyzed containers are rendered, the counterpart of [`Config`](crate::Config) for code generation
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Emit doc comments from descriptions
+    pub docs: bool,
+    /// Leave out the prelude importing the types the generated code uses
+    ///
+    /// Helpers the generated code refers to, such as the `kopium_duration` module, are still emitted.
+    pub hide_prelude: bool,
+    /// Do not derive CustomResource nor set kube attributes
+    pub hide_kube: bool,
+    /// Derive TypedBuilder on structs
+    pub builders: bool,
+    /// Crate to derive Validate from on structs, for the constraints found with [`Config::constraints`](crate::Config)
+    pub validation: Option<Validation>,
+    /// Generate a `validations()` function on structs returning their CEL rules
+    pub cel_stubs: bool,
+    /// Schema mode of kube-derive, `disabled`, `manual` or `derived`
+    pub schema: String,
+    /// Additional traits to derive
+    pub derive: Vec<Derive>,
+    /// Names of structs and enums to leave out
+    pub elide: Vec<String>,
+    /// Whether the containers were analyzed with [`Config::no_condition`](crate::Config)
+    pub no_condition: bool,
+    /// The naming the containers were analyzed with
+    pub naming: Naming,
+    /// Prefix the containers were analyzed with, when it is not the kind
+    pub struct_prefix: Option<String>,
+    pub visibility: Visibility,
+    /// Traits to implement on enums of unit variants, based on their schema values
+    pub enum_impls: Vec<EnumImpl>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            docs: false,
+            hide_prelude: false,
+            hide_kube: false,
+            builders: false,
+            validation: None,
+            cel_stubs: false,
+            schema: "disabled".to_string(),
+            derive: vec![],
+            elide: vec![],
+            no_condition: false,
+            naming: Naming::default(),
+            struct_prefix: None,
+            visibility: Visibility::default(),
+            enum_impls: vec![],
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Prefix of the generated names for a CRD, its kind unless [`RenderOptions::struct_prefix`] is set
+    pub fn struct_prefix<'a>(&'a self, crd: &'a CustomResourceDefinition) -> &'a str {
+        self.struct_prefix.as_deref().unwrap_or(&crd.spec.names.kind)
+    }
+}
+
+/// Traits that can be implemented on generated enums
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnumImpl {
+    /// Writes the value as it appears in the schema
+    Display,
+    /// Parses the value case-sensitively, failing with a generated `ParseEnumError`
+    FromStr,
+}
+
+/// Render the containers analyzed from CRD versions as one file of unformatted Rust code
+///
+/// The prelude and helper modules are shared, so the names generated for the versions must not collide.
+pub fn render(generated: &[Analyzed], options: &RenderOptions) -> Result<String> {
+    Emitter { options }.render(generated)
+}
+
+/// Writes the code for a [`render`], wrapping the [`RenderOptions`] it was started with
+struct Emitter<'a> {
+    options: &'a RenderOptions,
+}
+
+impl std::ops::Deref for Emitter<'_> {
+    type Target = RenderOptions;
+
+    fn deref(&self) -> &RenderOptions {
+        self.options
+    }
+}
+
+impl Emitter<'_> {
+    fn render(&self, generated: &[Analyzed]) -> Result<String> {
+        let prefixed = generated
+            .iter()
+            .flat_map(|g| g.containers.iter().map(|s| (self.struct_prefix(g.crd), s)))
+            .collect::<Vec<_>>();
+        let collisions = duplicate_struct_names(&prefixed, self.naming);
+        if !collisions.is_empty() {
+            bail!(
+                "generated struct names collide between CRDs: {}",
+                collisions.join(", ")
+            );
+        }
+
+        let mut code = String::new();
+        let w = &mut code;
+        let all_structs = generated.iter().flat_map(|g| &g.containers).collect::<Vec<_>>();
+        if !self.hide_prelude {
+            self.print_prelude(w, &all_structs)?;
+        }
+        self.print_helpers(w, &all_structs)?;
+        for g in generated {
+            self.print_version(w, g.crd, g.version, &g.containers)?;
+        }
+        let prefixed = generated
+            .iter()
+            .map(|g| (self.struct_prefix(g.crd), &g.containers))
+            .collect::<Vec<_>>();
+        self.print_defaults(w, &prefixed)?;
+        self.print_schemas(w, &prefixed)?;
+        Ok(code)
+    }
+
+    fn print_version(
+        &self,
+        w: &mut impl Write,
+        crd: &CustomResourceDefinition,
+        version: &CustomResourceDefinitionVersion,
+        structs: &[Container],
+    ) -> Result<()> {
+        let kind = &crd.spec.names.kind;
+        let prefix = self.struct_prefix(crd);
+
+        if self.visibility != Visibility::Pub && !self.hide_kube {
+            let mut public = vec![format!("{}Spec", prefix)];
+            if self.has_status_resource(structs) {
+                public.push(format!("{}Status", prefix));
+            }
+            warn!(
+                "Keeping {} pub, as kube derives the pub {} from them",
+                public.join(" and "),
+                kind
+            );
+        }
+
+        let excluded = self.derive_exclusions(structs);
+        for s in emission_order(structs) {
+            if s.level == 0 {
+                continue; // ignoring root struct
+            }
+            if self.elide.contains(&s.name) {
+                debug!("eliding {} from the output", s.name);
+                continue;
+            }
+            let mut attrs = vec![self.derive_attr(s, &excluded)];
+            if s.is_main_container() && !self.hide_kube {
+                //root struct gets kube derives unless opted out
+                attrs.extend(self.kube_attrs(crd, version, s, structs, &excluded)?);
+            }
+            if !s.is_main_container() && s.is_untagged_enum() {
+                attrs.push("#[serde(untagged)]".to_string());
+            }
+            if !s.serde_annot.is_empty() {
+                attrs.push(format!("#[serde({})]", s.serde_annot.join(", ")));
+            }
+            self.print_item(w, s, &attrs, prefix, structs)?;
+            if s.is_int_enum() {
+                self.print_int_enum_serde(w, s, &s.emitted_name(prefix, self.naming))?;
+            }
+            if s.has_default_impl() {
+                self.print_default_impl(w, s, &s.emitted_name(prefix, self.naming))?;
+            }
+            if let Some(key) = &s.map_key {
+                let name = s.emitted_name(prefix, self.naming);
+                writeln!(w, "impl kopium_list_map::Keyed for {} {{", name)?;
+                writeln!(w, "    const KEY: &'static str = {:?};", key)?;
+                writeln!(w, "}}")?;
+                writeln!(w)?;
+            }
+            if self.cel_stubs && !s.cel_rules.is_empty() {
+                self.print_cel_rules(w, &s.cel_rules, &s.emitted_name(prefix, self.naming))?;
+            }
+            if s.is_enum {
+                self.print_enum_impls(w, s, &s.emitted_name(prefix, self.naming))?;
+            }
+        }
+        // the root is generated by kube, under the name of the kind
+        if let Some(root) = structs.iter().find(|s| s.is_root()) {
+            if self.cel_stubs && !self.hide_kube && !root.cel_rules.is_empty() {
+                self.print_cel_rules(w, &root.cel_rules, kind)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The kube attributes of the spec, for the type kube generates from it
+    fn kube_attrs(
+        &self,
+        crd: &CustomResourceDefinition,
+        version: &CustomResourceDefinitionVersion,
+        s: &Container,
+        structs: &[Container],
+        excluded: &BTreeMap<&str, BTreeMap<String, String>>,
+    ) -> Result<Vec<String>> {
+        let names = &crd.spec.names;
+        let kind = &names.kind;
+        let prefix = self.struct_prefix(crd);
+        let mut attrs = vec![format!(
+            r#"#[kube(group = "{}", version = "{}", kind = "{}", plural = "{}")]"#,
+            crd.spec.group, version.name, kind, names.plural
+        )];
+        if crd.spec.scope == "Namespaced" {
+            attrs.push("#[kube(namespaced)]".to_string());
+        }
+        // kube infers the lowercased kind as the singular
+        if let Some(singular) = names
+            .singular
+            .as_ref()
+            .filter(|s| **s != kind.to_ascii_lowercase())
+        {
+            attrs.push(format!(r#"#[kube(singular = {:?})]"#, singular));
+        }
+        for shortname in names.short_names.iter().flatten() {
+            attrs.push(format!(r#"#[kube(shortname = {:?})]"#, shortname));
+        }
+        for category in names.categories.iter().flatten() {
+            attrs.push(format!(r#"#[kube(category = {:?})]"#, category));
+        }
+        // the spec docs are above, the description of the whole object goes on the generated type
+        let root_docs = structs.iter().find(|s| s.is_root()).and_then(|s| s.docs.as_ref());
+        if let Some(docs) = root_docs.filter(|_| self.docs) {
+            let docs = doc_lines(docs, usize::MAX).join("\n");
+            attrs.push(format!(r#"#[kube(doc = {:?})]"#, docs));
+        }
+        // the status is typed whenever the schema has one, with or without a status subresource
+        if self.has_status_resource(structs) {
+            attrs.push(format!(r#"#[kube(status = "{}Status")]"#, prefix));
+        }
+        if let Some(scale) = version.subresources.as_ref().and_then(|s| s.scale.as_ref()) {
+            let scale = serde_json::to_string(scale)?;
+            attrs.push(format!("#[kube(scale = {})]", raw_string(&scale)));
+        }
+        for column in version.additional_printer_columns.iter().flatten() {
+            let column = serde_json::to_string(column)?;
+            attrs.push(format!("#[kube(printcolumn = {})]", raw_string(&column)));
+        }
+        if self.schema != "derived" {
+            attrs.push(format!(r#"#[kube(schema = "{}")]"#, self.schema));
+        }
+        for derive in &self.derive {
+            if derive.derived_trait == "JsonSchema" {
+                continue;
+            }
+            if excluded
+                .get(derive.derived_trait.as_str())
+                .is_some_and(|e| e.contains_key(&s.name))
+            {
+                continue;
+            }
+            if matches!(derive.derived_trait.as_str(), "Eq" | "Hash") {
+                // the ObjectMeta of the generated type lacks both
+                continue;
+            }
+            attrs.push(format!(r#"#[kube(derive="{}")]"#, derive.derived_trait));
+        }
+        Ok(attrs)
+    }
+
+    /// Print a struct or enum with its docs, attributes and members
+    ///
+    /// Every generated type goes through here, so that docs, attributes and visibility are written in one place.
+    fn print_item(
+        &self,
+        w: &mut impl Write,
+        s: &Container,
+        attrs: &[String],
+        prefix: &str,
+        structs: &[Container],
+    ) -> Result<()> {
+        self.print_docstr(w, &s.docs, "")?;
+        for attr in attrs {
+            writeln!(w, "{}", attr)?;
+        }
+        let keyword = if s.is_enum { "enum" } else { "struct" };
+        let name = s.emitted_name(prefix, self.naming);
+        writeln!(w, "{} {} {} {{", self.item_visibility(s, structs), keyword, name)?;
+        for m in &s.members {
+            self.print_docstr(w, &m.docs, "    ")?;
+            if !m.serde_annot.is_empty() {
+                writeln!(w, "    #[serde({})]", m.serde_annot.join(", "))?;
+            }
+            let name = format_ident!("{}", m.name);
+            for annot in &m.extra_annot {
+                writeln!(w, "    {}", annot)?;
+            }
+            let spec_trimmed_type = m.emitted_type(prefix, self.naming);
+            if let Some(value) = m.discriminant {
+                writeln!(w, "    {} = {},", name, value)?;
+            } else if s.is_enum && !m.type_.is_empty() {
+                writeln!(w, "    {}({}),", name, spec_trimmed_type)?;
+            } else if s.is_enum {
+                // NB: only supporting plain enumerations atm, not oneOf
+                writeln!(w, "    {},", name)?;
+            } else {
+                writeln!(
+                    w,
+                    "    {} {}: {},",
+                    self.visibility.keyword(),
+                    name,
+                    spec_trimmed_type
+                )?;
+            }
+        }
+        writeln!(w, "}}")?;
+        writeln!(w)?;
+        Ok(())
+    }
+
+    /// List the CEL rules of a struct along with their messages, for controllers to report them
+    fn print_cel_rules(&self, w: &mut impl Write, rules: &[(String, String)], name: &str) -> Result<()> {
+        writeln!(w, "impl {} {{", name)?;
+        writeln!(
+            w,
+            "    /// CEL rules from `x-kubernetes-validations`, along with the messages of their violations"
+        )?;
+        writeln!(
+            w,
+            "    pub fn validations() -> &'static [(&'static str, &'static str)] {{"
+        )?;
+        writeln!(w, "        &[")?;
+        for (rule, message) in rules {
+            writeln!(w, "            ({:?}, {:?}),", rule, message)?;
+        }
+        writeln!(w, "        ]")?;
+        writeln!(w, "    }}")?;
+        writeln!(w, "}}")?;
+        writeln!(w)?;
+        Ok(())
+    }
+
+    /// Functions returning the schema defaults of fields, which their `#[serde(default = "...")]` point at
+    ///
+    /// Takes the struct prefix of each CRD along with the containers generated for it.
+    fn print_defaults(&self, w: &mut impl Write, generated: &[(&str, &Vec<Container>)]) -> Result<()> {
+        let defaulted = generated
+            .iter()
+            .flat_map(|(prefix, structs)| structs.iter().map(move |s| (*prefix, s)))
+            .flat_map(|(prefix, s)| s.members.iter().map(move |m| (prefix, m)))
+            .filter_map(|(prefix, m)| Some((prefix, m, m.default_fn()?, m.default.as_ref()?)))
+            .collect::<Vec<_>>();
+        if defaulted.is_empty() {
+            return Ok(());
+        }
+        writeln!(w, "mod defaults {{")?;
+        writeln!(w, "    use super::*;")?;
+        for (prefix, m, name, value) in defaulted {
+            let type_ = m.emitted_type(prefix, self.naming);
+            writeln!(w, "    pub fn {}() -> {} {{", name, type_)?;
+            writeln!(w, "        {}", default_expr(&type_, value))?;
+            writeln!(w, "    }}")?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    /// Write the `schemas` module returning the declared schema of members whose types derive a different one
+    fn print_schemas(&self, w: &mut impl Write, generated: &[(&str, &Vec<Container>)]) -> Result<()> {
+        let schemas = generated
+            .iter()
+            .flat_map(|(_, structs)| structs.iter())
+            .flat_map(|s| s.members.iter())
+            .filter_map(|m| Some((m.schema_fn()?, m.schema.as_ref()?)))
+            .collect::<Vec<_>>();
+        if schemas.is_empty() {
+            return Ok(());
+        }
+        writeln!(w, "mod schemas {{")?;
+        for (name, schema) in schemas {
+            writeln!(
+                w,
+                "    pub fn {}(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {{",
+                name
+            )?;
+            let schema = raw_string(&serde_json::to_string(schema)?);
+            writeln!(
+                w,
+                "        serde_json::from_str({}).expect(\"valid schema\")",
+                schema
+            )?;
+            writeln!(w, "    }}")?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    /// Implement Default for a struct in terms of the schema defaults of its members
+    fn print_default_impl(&self, w: &mut impl Write, s: &Container, name: &str) -> Result<()> {
+        writeln!(w, "impl Default for {} {{", name)?;
+        writeln!(w, "    fn default() -> Self {{")?;
+        writeln!(w, "        Self {{")?;
+        for m in &s.members {
+            match m.default_fn() {
+                Some(default_fn) => writeln!(w, "            {}: defaults::{}(),", m.name, default_fn)?,
+                None => writeln!(w, "            {}: Default::default(),", m.name)?,
+            }
+        }
+        writeln!(w, "        }}")?;
+        writeln!(w, "    }}")?;
+        writeln!(w, "}}")?;
+        writeln!(w)?;
+        Ok(())
+    }
+
+    /// Serialize integer enums as their numeric value, which serde derives cannot do
+    fn print_int_enum_serde(&self, w: &mut impl Write, s: &Container, name: &str) -> Result<()> {
+        writeln!(w, "impl Serialize for {} {{", name)?;
+        writeln!(
+            w,
+            "    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {{"
+        )?;
+        writeln!(w, "        serializer.serialize_i64(self.clone() as i64)")?;
+        writeln!(w, "    }}")?;
+        writeln!(w, "}}")?;
+        writeln!(w, "impl<'de> Deserialize<'de> for {} {{", name)?;
+        writeln!(
+            w,
+            "    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {{"
+        )?;
+        writeln!(w, "        match i64::deserialize(deserializer)? {{")?;
+        for m in &s.members {
+            if let Some(value) = m.discriminant {
+                writeln!(w, "            {} => Ok(Self::{}),", value, m.name)?;
+            }
+        }
+        writeln!(
+            w,
+            r#"            other => Err(serde::de::Error::custom(format!("unknown {} value {{}}", other))),"#,
+            name
+        )?;
+        writeln!(w, "        }}")?;
+        writeln!(w, "    }}")?;
+        writeln!(w, "}}")?;
+        writeln!(w)?;
+        Ok(())
+    }
+
+    /// Implement the traits requested through --enum-impls in terms of the schema values
+    fn print_enum_impls(&self, w: &mut impl Write, s: &Container, name: &str) -> Result<()> {
+        if self.enum_impls.is_empty() {
+            return Ok(());
+        }
+        if s.members.is_empty() || s.members.iter().any(|m| !m.type_.is_empty()) {
+            return Ok(()); // only unit variants map to a single value
+        }
+        if self.enum_impls.contains(&EnumImpl::Display) {
+            writeln!(w, "impl std::fmt::Display for {} {{", name)?;
+            writeln!(
+                w,
+                "    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{"
+            )?;
+            writeln!(w, "        f.write_str(match self {{")?;
+            for m in &s.members {
+                writeln!(w, "            Self::{} => {:?},", m.name, m.wire_name())?;
+            }
+            writeln!(w, "        }})")?;
+            writeln!(w, "    }}")?;
+            writeln!(w, "}}")?;
+        }
+        if self.enum_impls.contains(&EnumImpl::FromStr) {
+            writeln!(w, "impl std::str::FromStr for {} {{", name)?;
+            writeln!(w, "    type Err = ParseEnumError;")?;
+            writeln!(w, "    fn from_str(s: &str) -> Result<Self, Self::Err> {{")?;
+            writeln!(w, "        match s {{")?;
+            for m in &s.members {
+                writeln!(w, "            {:?} => Ok(Self::{}),", m.wire_name(), m.name)?;
+            }
+            writeln!(
+                w,
+                r#"            _ => Err(ParseEnumError {{ kind: "{}", value: s.to_string() }}),"#,
+                name
+            )?;
+            writeln!(w, "        }}")?;
+            writeln!(w, "    }}")?;
+            writeln!(w, "}}")?;
+        }
+        writeln!(w)?;
+        Ok(())
+    }
+
+    fn print_docstr(&self, w: &mut impl Write, doc: &Option<String>, indent: &str) -> Result<()> {
+        // print doc strings if requested in arguments
+        if self.docs {
+            if let Some(d) = doc {
+                let width = DOC_WIDTH.saturating_sub(indent.len() + "/// ".len());
+                for line in doc_lines(d, width) {
+                    if line.is_empty() {
+                        writeln!(w, "{}///", indent)?;
+                    } else {
+                        writeln!(w, "{}/// {}", indent, line)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Generated structs and enums that cannot implement the traits to derive, along with the reason, by trait
+    fn derive_exclusions(&self, structs: &[Container]) -> BTreeMap<&'static str, BTreeMap<String, String>> {
+        let mut excluded = BTreeMap::new();
+        let derives = |t: &str| self.derive.iter().any(|d| d.derived_trait == t);
+        if derives("Default") {
+            excluded.insert("Default", default_exclusions(structs));
+        }
+        if derives("Eq") {
+            excluded.insert("Eq", eq_exclusions(structs));
+        }
+        if derives("Hash") {
+            excluded.insert("Hash", hash_exclusions(structs));
+        }
+        excluded
+    }
+
+    fn derive_attr(&self, s: &Container, excluded: &BTreeMap<&str, BTreeMap<String, String>>) -> String {
+        let mut derives = vec!["Serialize", "Deserialize", "Clone", "Debug"];
+        if s.is_int_enum() {
+            // serde impls are generated separately, see print_int_enum_serde
+            derives.retain(|d| !matches!(*d, "Serialize" | "Deserialize"));
+        }
+        if s.can_derive_default() {
+            // enums with a schema default carry a #[default] variant
+            derives.push("Default");
+        }
+        if s.is_defaultable() && !s.has_default_impl() {
+            // every member can be left out, so building one does not need to spell out every None
+            derives.push("Default");
+        }
+
+        if s.is_main_container() && !self.hide_kube {
+            // CustomResource first for root struct
+            derives.insert(0, "CustomResource");
+        }
+
+        // TypedBuilder does not work with enums
+        if self.builders && !s.is_enum {
+            derives.push("TypedBuilder");
+        }
+        if self.validation.is_some() && !s.is_enum {
+            derives.push("Validate");
+        }
+
+        for derive in &self.derive {
+            if derive.derived_trait == "Default" && s.has_default_impl() {
+                continue;
+            }
+            if let Some(reason) = excluded
+                .get(derive.derived_trait.as_str())
+                .and_then(|e| e.get(&s.name))
+            {
+                debug!("not deriving {} for {}: {}", derive.derived_trait, s.name, reason);
+                continue;
+            }
+
+            if derive.is_applicable_to(s) && !derives.contains(&derive.derived_trait.as_str()) {
+                derives.push(&derive.derived_trait)
+            }
+        }
+
+        format!("#[derive({})]", derives.join(", "))
+    }
+
+    /// Visibility of a generated struct or enum
+    ///
+    /// kube gives the type it generates the visibility of the spec, and exposes the spec and status through it.
+    fn item_visibility(&self, s: &Container, results: &[Container]) -> &str {
+        let exposed = s.is_main_container() || (s.is_status_container() && self.has_status_resource(results));
+        if exposed && !self.hide_kube {
+            Visibility::Pub.keyword()
+        } else {
+            self.visibility.keyword()
+        }
+    }
+
+    fn has_status_resource(&self, results: &[Container]) -> bool {
+        results
+            .iter()
+            .any(|o| o.is_status_container() && !o.members.is_empty() && !self.elide.contains(&o.name))
+    }
+
+    fn print_prelude(&self, w: &mut impl Write, results: &[&Container]) -> Result<()> {
+        writeln!(w, "#[allow(unused_imports)]")?;
+        writeln!(w, "mod prelude {{")?;
+        if !self.hide_kube {
+            writeln!(w, "    pub use kube::CustomResource;")?;
+        }
+        if self.builders {
+            writeln!(w, "    pub use typed_builder::TypedBuilder;")?;
+        }
+        if let Some(validation) = self.validation {
+            writeln!(w, "    pub use {};", validation.trait_path())?;
+        }
+        if self
+            .derive
+            .iter()
+            .any(|derive| derive.derived_trait == "JsonSchema")
+        {
+            writeln!(w, "    pub use schemars::JsonSchema;")?;
+        }
+        writeln!(w, "    pub use serde::{{Serialize, Deserialize}};")?;
+        if results.iter().any(|o| o.uses_btreemaps()) {
+            writeln!(w, "    pub use std::collections::BTreeMap;")?;
+        }
+        if results.iter().any(|o| o.uses_btreesets()) {
+            writeln!(w, "    pub use std::collections::BTreeSet;")?;
+        }
+        if results.iter().any(|o| o.uses_hashmaps()) {
+            writeln!(w, "    pub use std::collections::HashMap;")?;
+        }
+        if results.iter().any(|o| o.uses_indexmaps()) {
+            writeln!(w, "    pub use indexmap::IndexMap;")?;
+        }
+        if results.iter().any(|o| o.uses_datetime()) {
+            writeln!(w, "    pub use chrono::{{DateTime, Utc}};")?;
+        }
+        if results.iter().any(|o| o.uses_date()) {
+            writeln!(w, "    pub use chrono::naive::NaiveDate;")?;
+        }
+        if results.iter().any(|o| o.uses_duration()) {
+            writeln!(w, "    pub use std::time::Duration;")?;
+        }
+        if results.iter().any(|o| o.uses_byte_string()) {
+            writeln!(w, "    pub use k8s_openapi::ByteString;")?;
+        }
+        if results.iter().any(|o| o.uses_int_or_string()) {
+            writeln!(
+                w,
+                "    pub use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;"
+            )?;
+        }
+        if results.iter().any(|o| o.uses_raw_extension()) {
+            writeln!(
+                w,
+                "    pub use k8s_openapi::apimachinery::pkg::runtime::RawExtension;"
+            )?;
+        }
+        if results.iter().any(|o| o.uses_dynamic_object()) {
+            writeln!(w, "    pub use kube::core::DynamicObject;")?;
+        }
+        if results.iter().any(|o| o.contains_conditions()) && !self.no_condition {
+            writeln!(
+                w,
+                "    pub use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;"
+            )?;
+        }
+        writeln!(w, "}}")?;
+        writeln!(w, "use self::prelude::*;\n")?;
+        Ok(())
+    }
+
+    /// Print the helper modules and types that generated code refers to, even with --hide-prelude
+    fn print_helpers(&self, w: &mut impl Write, results: &[&Container]) -> Result<()> {
+        if results.iter().any(|o| o.uses_duration()) {
+            writeln!(w, "mod kopium_duration {{")?;
+            writeln!(w, "{}", DURATION_MODULE)?;
+            writeln!(w, "}}")?;
+        }
+        if results.iter().any(|o| o.uses_list_maps()) {
+            // CRDs may only use the helpers for required lists, or only those for optional lists
+            writeln!(w, "#[allow(dead_code)]")?;
+            writeln!(w, "mod kopium_list_map {{")?;
+            writeln!(w, "{}", LIST_MAP_MODULE)?;
+            writeln!(w, "}}")?;
+        }
+        if self.enum_impls.contains(&EnumImpl::FromStr) && results.iter().any(|o| o.is_enum) {
+            writeln!(w, "{}", PARSE_ENUM_ERROR)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serde helpers for `--duration-as std::time::Duration`, emitted when a field uses them
+const DURATION_MODULE: &str = include_str!("duration.rs");
+
+/// Serde helpers for `--list-map-as-map`, emitted when a field uses them
+const LIST_MAP_MODULE: &str = include_str!("list_map.rs");
+
+/// Error type for `--enum-impls fromstr`, emitted when an enum is generated
+const PARSE_ENUM_ERROR: &str = r#"
+/// Error returned when parsing a value that is not part of an enum
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseEnumError {
+    /// Name of the enum that was parsed
+    pub kind: &'static str,
+    /// The value that did not match any variant
+    pub value: String,
+}
+
+impl std::fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown {} value {:?}", self.kind, self.value)
+    }
+}
+
+impl std::error::Error for ParseEnumError {}
+"#;
+
+/// Width that doc comments are wrapped at, including their indent
+const DOC_WIDTH: usize = 100;
+
+/// Expression for a schema default of a field of type `type_`
+///
+/// Plain values are written as literals, others (such as enums, lists or objects) are deserialized from json.
+fn default_expr(type_: &str, value: &serde_json::Value) -> String {
+    use serde_json::Value;
+    let integer = ["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64"].contains(&type_);
+    match value {
+        Value::Bool(b) if type_ == "bool" => b.to_string(),
+        Value::Number(n) if integer && (n.is_i64() || n.is_u64()) => n.to_string(),
+        Value::Number(n) if type_ == "f64" || type_ == "f32" => {
+            let n = n.to_string();
+            if n.contains(['.', 'e', 'E']) {
+                n
+            } else {
+                format!("{}.0", n)
+            }
+        }
+        Value::String(s) if type_ == "String" => format!("{:?}.to_string()", s),
+        _ => format!(
+            r#"serde_json::from_value(serde_json::json!({})).expect("valid schema default")"#,
+            json_literal(value)
+        ),
+    }
+}
+
+/// A json value as the input of `serde_json::json!`, with strings as rust literals
+fn json_literal(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(json_literal).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Object(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|(k, v)| format!("{:?}: {}", k, json_literal(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// A raw string literal for `s`, with as many hashes as it needs
+fn raw_string(s: &str) -> String {
+    let mut hashes = String::new();
+    while s.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{0}\"{1}\"{0}", hashes, s)
+}
+
+/// Lines of a doc comment for a description, with its line breaks kept and long lines wrapped at `width`
+///
+/// Code blocks and tables are kept as they are, as wrapping would change their meaning,
+/// but code is fenced as `text` so that rustdoc does not run e.g. indented yaml examples as doctests.
+/// Other lines lose the odd leading space that folded yaml strings tend to have, and are escaped for rustdoc.
+/// Control characters are dropped.
+fn doc_lines(doc: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut fenced = false;
+    let mut indented = false;
+    for line in doc.trim_end().lines() {
+        let line = line
+            .chars()
+            .filter(|&c| c == '\t' || !c.is_control())
+            .collect::<String>();
+        let line = line.trim_end();
+        if line.trim_start().starts_with("```") {
+            let info = line.trim_start().trim_start_matches('`').trim();
+            if !fenced && (info.is_empty() || info == "rust") {
+                lines.push("```text".to_string());
+            } else {
+                lines.push(line.to_string());
+            }
+            fenced = !fenced;
+            continue;
+        }
+        if fenced {
+            lines.push(line.to_string());
+            continue;
+        }
+        if line.starts_with("    ") || line.starts_with('\t') {
+            if !indented {
+                lines.push("```text".to_string());
+                indented = true;
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+        if indented {
+            lines.push("```".to_string());
+            indented = false;
+        }
+        let line = escape_doc(line.trim_start());
+        if line.starts_with('|') || line.len() <= width {
+            lines.push(line);
+            continue;
+        }
+        // wrapped lines of list items are indented to stay part of the item
+        let hang = " ".repeat(list_marker_width(&line));
+        let mut wrapped = String::new();
+        for word in line.split_whitespace() {
+            if !wrapped.trim_start().is_empty() && wrapped.len() + 1 + word.len() > width {
+                lines.push(std::mem::replace(&mut wrapped, hang.clone()));
+            }
+            if !wrapped.trim_start().is_empty() {
+                wrapped.push(' ');
+            }
+            wrapped.push_str(word);
+        }
+        lines.push(wrapped);
+    }
+    if fenced || indented {
+        lines.push("```".to_string());
+    }
+    lines
+}
+
+/// Width of the marker of a list item such as `- ` or `1. ` that a line starts with, or 0 for other lines
+fn list_marker_width(line: &str) -> usize {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        digits + 2
+    } else if digits == 0 && ["- ", "* ", "+ "].iter().any(|m| rest.starts_with(m)) {
+        2
+    } else {
+        0
+    }
+}
+
+/// Prose escaped for rustdoc, which would take brackets for broken links and `<name>` for a broken html tag
+///
+/// Code spans are kept, and a backtick without a pair is escaped instead.
+fn escape_doc(line: &str) -> String {
+    let unpaired = line.matches('`').count() % 2 == 1;
+    let last_backtick = line.rfind('`');
+    let mut escaped = String::with_capacity(line.len());
+    let mut code = false;
+    for (i, c) in line.char_indices() {
+        let next = line[i + c.len_utf8()..].chars().next();
+        match c {
+            '`' if unpaired && Some(i) == last_backtick => escaped.push_str("\\`"),
+            '`' => {
+                code = !code;
+                escaped.push(c);
+            }
+            '<' if !code && next.is_some_and(|n| n.is_ascii_alphabetic() || n == '/' || n == '!') => {
+                escaped.push_str("\\<")
+            }
+            ']' if !code && next != Some('(') => escaped.push_str("\\]"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Order containers for output so that enums come right before the first struct using them
+pub(crate) fn emission_order(structs: &[Container]) -> Vec<&Container> {
+    let mut ordered: Vec<&Container> = Vec::with_capacity(structs.len());
+    for s in structs {
+        if s.is_enum {
+            if !ordered.iter().any(|o| std::ptr::eq(*o, s)) {
+                ordered.push(s);
+            }
+            continue;
+        }
+        for m in &s.members {
+            let referenced = m.type_.split(|c: char| !c.is_alphanumeric() && c != '_');
+            for name in referenced {
+                let used = structs.iter().find(|e| e.is_enum && e.name == name);
+                if let Some(e) = used.filter(|e| !ordered.iter().any(|o| std::ptr::eq(*o, *e))) {
+                    ordered.push(e);
+                }
+            }
+        }
+        ordered.push(s);
+    }
+    ordered
+}
+
+/// Names of emitted containers that are generated more than once
+///
+/// Containers come with the struct prefix of their CRD, as names are compared the way they are emitted.
+fn duplicate_struct_names(structs: &[(&str, &Container)], naming: Naming) -> Vec<String> {
+    let mut seen = std::collections::BTreeSet::new();
+    let mut duplicates = std::collections::BTreeSet::new();
+    // root containers are never emitted
+    for (prefix, s) in structs.iter().filter(|(_, s)| !s.is_root()) {
+        let name = s.emitted_name(prefix, naming);
+        if seen.contains(&name) {
+            duplicates.insert(name);
+        } else {
+            seen.insert(name);
+        }
+    }
+    duplicates.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{doc_lines, duplicate_struct_names, raw_string, render, Analyzed, RenderOptions};
+    use crate::{analyze, duration, Config, Container, Naming};
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+
+    #[test]
+    fn renders_a_crd_in_full() {
+        let crd: CustomResourceDefinition =
+            serde_yaml::from_str(include_str!("../tests/server-crd.yaml")).unwrap();
+        // the storage version, as picked by the binary
+        let version = crd.spec.versions.iter().find(|v| v.storage).unwrap();
+        let schema = version.schema.clone().unwrap().open_api_v3_schema.unwrap();
+        let output = analyze(schema, &crd.spec.names.kind, Config::default())
+            .unwrap()
+            .dedup(true)
+            .rename();
+        let generated = Analyzed {
+            crd: &crd,
+            version,
+            containers: output.containers,
+        };
+        let code = render(&[generated], &RenderOptions::default()).unwrap();
+        // the fixture is the code as rendered, before the binary formats it and adds its header
+        assert_eq!(code, include_str!("../tests/generated/server.rs"));
+        assert!(syn::parse_file(&code).is_ok());
+    }
+
+    #[test]
+    fn detects_struct_name_collisions() {
+        let container = |name: &str, level: u8| Container {
+            name: name.to_string(),
+            level,
+            ..Container::default()
+        };
+        let structs = [
+            container("Foo", 0),
+            container("FooSpec", 1),
+            container("FooSpecTls", 2),
+            container("Foo", 0),
+            container("FooSpec", 1),
+            container("BarSpec", 1),
+        ];
+        let refs = structs.iter().map(|s| ("Foo", s)).collect::<Vec<_>>();
+        assert_eq!(duplicate_struct_names(&refs, Naming::Short), vec![
+            "FooSpec".to_string()
+        ]);
+        assert!(duplicate_struct_names(&refs[..3], Naming::Short).is_empty());
+
+        // names are compared as emitted, without the Spec infix below the spec
+        let structs = [container("FooSpecBarStatus", 2), container("FooBarStatus", 1)];
+        let refs = [("Foo", &structs[0]), ("FooBar", &structs[1])];
+        assert_eq!(duplicate_struct_names(&refs, Naming::Short), vec![
+            "FooBarStatus".to_string()
+        ]);
+        assert!(duplicate_struct_names(&refs, Naming::FullPath).is_empty());
+    }
+
+    #[test]
+    fn raw_strings_have_enough_hashes() {
+        assert_eq!(raw_string("plain"), r#"r"plain""#);
+        assert_eq!(raw_string(r#"{"a":1}"#), r##"r#"{"a":1}"#"##);
+        assert_eq!(raw_string(r##"{"a":"#"}"##), r###"r##"{"a":"#"}"##"###);
+    }
+
+    #[test]
+    fn doc_lines_wrap_prose_only() {
+        assert_eq!(doc_lines("one two three four", 9), ["one two", "three", "four"]);
+        assert_eq!(doc_lines("short\n\nlines\n", 9), ["short", "", "lines"]);
+        assert_eq!(doc_lines("folded\n yaml", 9), ["folded", "yaml"]);
+        assert_eq!(doc_lines("`code` in prose", 9), ["`code` in", "prose"]);
+        // list items stay items when wrapped
+        assert_eq!(doc_lines("- one two three\n10. four five", 9), [
+            "- one two",
+            "  three",
+            "10. four",
+            "    five"
+        ]);
+        // words longer than the width get a line of their own
+        assert_eq!(doc_lines("a https://example.com/long b", 9), [
+            "a",
+            "https://example.com/long",
+            "b"
+        ]);
+        // code and tables are not rewrapped
+        let verbatim = "```yaml\nkey: a long value here\n```\n| a table | row |";
+        assert_eq!(doc_lines(verbatim, 9), verbatim.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn doc_lines_are_safe_for_rustdoc() {
+        // code that rustdoc would run as a doctest is fenced as text
+        assert_eq!(doc_lines("example:\n    kind: Foo\n\tname: foo\nend", 80), [
+            "example:",
+            "```text",
+            "    kind: Foo",
+            "\tname: foo",
+            "```",
+            "end"
+        ]);
+        assert_eq!(doc_lines("```\nlet x = 1;\n```", 80), [
+            "```text",
+            "let x = 1;",
+            "```"
+        ]);
+        assert_eq!(doc_lines("```rust\nlet x = 1;", 80), [
+            "```text",
+            "let x = 1;",
+            "```"
+        ]);
+        assert_eq!(doc_lines("trailing:\n    code", 80), [
+            "trailing:",
+            "```text",
+            "    code",
+            "```"
+        ]);
+        // brackets that are not links, html-like tags and unpaired backticks are escaped
+        assert_eq!(doc_lines("match [a-z]+ or [Pod]", 80), [
+            r"match [a-z\]+ or [Pod\]"
+        ]);
+        assert_eq!(doc_lines("see [docs](https://example.com)", 80), [
+            "see [docs](https://example.com)"
+        ]);
+        assert_eq!(doc_lines("<namespace>/<name> if a < b", 80), [
+            r"\<namespace>/\<name> if a < b"
+        ]);
+        assert_eq!(doc_lines("`a[0]` and `<b>`", 80), ["`a[0]` and `<b>`"]);
+        assert_eq!(doc_lines("run `kubectl` or `ls", 80), [r"run `kubectl` or \`ls"]);
+        // control characters are dropped
+        assert_eq!(doc_lines("bell\u{7}\r\nnext", 80), ["bell", "next"]);
+    }
+
+    #[test]
+    fn durations_round_trip() {
+        use std::time::Duration;
+        for (input, expected, formatted) in [
+            ("1h30m", Duration::from_secs(5400), "1h30m0s"),
+            ("500ms", Duration::from_millis(500), "500ms"),
+            ("0", Duration::ZERO, "0s"),
+            ("1.5s", Duration::from_millis(1500), "1.5s"),
+            ("2m0.25s", Duration::from_millis(120_250), "2m0.25s"),
+            ("+10us", Duration::from_micros(10), "10µs"),
+            ("1h1ns", Duration::new(3600, 1), "1h0m0.000000001s"),
+        ] {
+            let parsed = duration::parse(input).unwrap();
+            assert_eq!(parsed, expected, "parsing {}", input);
+            assert_eq!(duration::format(&parsed), formatted);
+            assert_eq!(duration::parse(formatted).unwrap(), parsed);
+        }
+        for invalid in ["", "10", "-1s", "1d", "h", ".s", "1.2.3s"] {
+            assert!(duration::parse(invalid).is_err(), "{} should not parse", invalid);
+        }
+
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Timeouts {
+            #[serde(with = "duration")]
+            connect: Duration,
+            #[serde(default, skip_serializing_if = "Option::is_none", with = "duration::option")]
+            idle: Option<Duration>,
+        }
+        let timeouts: Timeouts = serde_json::from_str(r#"{"connect": "1m30s"}"#).unwrap();
+        assert_eq!(timeouts, Timeouts {
+            connect: Duration::from_secs(90),
+            idle: None,
+        });
+        let json = serde_json::to_string(&Timeouts {
+            idle: Some(Duration::from_millis(500)),
+            ..timeouts
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"connect":"1m30s","idle":"500ms"}"#);
+    }
+}
@@ -0,0 +1,541 @@
+//! Checks the code in tests/generated against what kopium generates, and compiles it to check how it behaves
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use std::process::Command;
+
+// code generated for properties named after keywords, compiled here to check the escaping
+#[allow(dead_code)]
+mod keywords {
+    include!("generated/keywords.rs");
+}
+// code generated for properties and values starting with a digit
+#[allow(dead_code)]
+mod digits {
+    include!("generated/digits.rs");
+}
+// code generated with --schema-defaults
+#[allow(dead_code)]
+mod schema_defaults {
+    include!("generated/defaults.rs");
+}
+// code generated with --validation validator, compiled against the validator crate
+#[allow(dead_code)]
+mod validation {
+    include!("generated/validation.rs");
+}
+// code generated with --validation garde, compiled against garde with its regex feature
+// (the garde derive expands to a cfg on its js-sys feature, unknown to this crate)
+#[allow(dead_code, unexpected_cfgs)]
+mod garde_validation {
+    include!("generated/garde.rs");
+}
+// code generated with --cel-stubs
+#[allow(dead_code)]
+mod cel {
+    include!("generated/cel.rs");
+}
+// code generated with --list-map-as-map, which includes the generated list_map module
+#[allow(dead_code)]
+mod list_maps {
+    include!("generated/listmap.rs");
+}
+// code generated with --schema derived, for members whose types derive a schema of their own
+#[allow(dead_code)]
+mod derived_schemas {
+    include!("generated/schemas.rs");
+}
+// code generated with --builders, compiled against the typed-builder crate
+#[allow(dead_code)]
+mod builders {
+    include!("generated/builders.rs");
+}
+// code generated with --deny-unknown-fields
+#[allow(dead_code)]
+mod strict {
+    include!("generated/strict.rs");
+}
+// code generated from the names, subresources and printer columns of a crd
+#[allow(dead_code)]
+mod kube_attrs {
+    include!("generated/kube.rs");
+}
+// code generated with --visibility pub-crate
+#[allow(dead_code)]
+mod visibility {
+    include!("generated/visibility.rs");
+}
+
+/// The crd in tests, the arguments and the file in tests/generated of every fixture
+const FIXTURES: &[(&str, &[&str], &str)] = &[
+    ("keywords-crd.yaml", &[], "keywords.rs"),
+    ("digits-crd.yaml", &[], "digits.rs"),
+    // compiled into the library for `cargo test --doc`, where no examples must run
+    ("docs-crd.yaml", &["--docs"], "docs.rs"),
+    ("defaults-crd.yaml", &["--schema-defaults"], "defaults.rs"),
+    (
+        "validation-crd.yaml",
+        &["--docs", "--validation", "validator"],
+        "validation.rs",
+    ),
+    (
+        "validation-crd.yaml",
+        &["--docs", "--validation", "garde"],
+        "garde.rs",
+    ),
+    ("cel-crd.yaml", &["--docs", "--cel-stubs"], "cel.rs"),
+    ("listmap-crd.yaml", &["--list-map-as-map"], "listmap.rs"),
+    ("kube-crd.yaml", &[], "kube.rs"),
+    (
+        "schema-crd.yaml",
+        &["--schema", "derived", "--sets"],
+        "schemas.rs",
+    ),
+    ("builders-crd.yaml", &["--builders"], "builders.rs"),
+    ("strict-crd.yaml", &["--deny-unknown-fields"], "strict.rs"),
+    (
+        "visibility-crd.yaml",
+        &["--visibility", "pub-crate"],
+        "visibility.rs",
+    ),
+    ("server-crd.yaml", &["--emit", "json"], "server.json"),
+];
+
+/// Output of `kopium -q <args> -f tests/<crd>`
+fn kopium(crd: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_kopium"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("-q")
+        .args(args)
+        .args(["-f", &format!("tests/{}", crd)])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "kopium failed on {}: {}",
+        crd,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Generated code without the header, which records how and by which version the code was generated
+fn without_header(code: &str) -> String {
+    code.lines()
+        .filter(|l| !l.starts_with("// kopium "))
+        .map(|l| format!("{}\n", l))
+        .collect()
+}
+
+#[test]
+fn fixtures_are_up_to_date() {
+    let mut stale = vec![];
+    for (crd, args, fixture) in FIXTURES {
+        let path = format!("{}/tests/generated/{}", env!("CARGO_MANIFEST_DIR"), fixture);
+        let expected = std::fs::read_to_string(&path).unwrap();
+        if without_header(&kopium(crd, args)) != without_header(&expected) {
+            let args = [&["-q"], *args].concat().join(" ");
+            stale.push(format!(
+                "cargo run -- {} -f tests/{} > tests/generated/{}",
+                args, crd, fixture
+            ));
+        }
+    }
+    assert!(stale.is_empty(), "regenerate with:\n{}", stale.join("\n"));
+}
+
+fn parse_crd(yaml: &str) -> CustomResourceDefinition {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[test]
+fn keywords_are_escaped() {
+    let code = include_str!("generated/keywords.rs");
+    assert!(code.contains("pub r#type: Option<String>,"));
+    assert!(code.contains("pub r#gen: Option<String>,"));
+    // keywords that cannot be raw identifiers
+    assert!(code.contains("pub self_: Option<String>,"));
+    assert!(code.contains("pub super_: Option<String>,"));
+    assert!(code.contains("pub crate_: Option<bool>,"));
+    assert!(code.contains("#[serde(default, skip_serializing_if = \"Option::is_none\", rename = \"Type\")]"));
+}
+
+#[test]
+fn leading_digits_keep_their_wire_names() {
+    use digits::{MetricSpec, MetricStatusClass};
+    let code = include_str!("generated/digits.rs");
+    assert!(code.contains("rename = \"5xxRate\")]\n    pub r#_5xx_rate: Option<f64>,"));
+    assert!(code.contains("#[serde(rename = \"5xx\")]\n    V5xx,"));
+
+    // the wire names survive a round trip
+    let json = serde_json::json!({
+        "5xxRate": 0.5,
+        "503Responses": 3,
+        "2xx": { "99thPercentile": 0.2, "requestCount": 10 },
+        "statusClass": "5xx",
+        "window": "30s",
+    });
+    let spec: MetricSpec = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(spec.r#_5xx_rate, Some(0.5));
+    assert_eq!(spec.r#_2xx.as_ref().unwrap().r#_99th_percentile, Some(0.2));
+    assert!(matches!(spec.status_class, Some(MetricStatusClass::V5xx)));
+    assert_eq!(serde_json::to_value(&spec).unwrap(), json);
+}
+
+#[test]
+fn docs_are_safe_for_rustdoc() {
+    let code = include_str!("generated/docs.rs");
+    assert!(code.contains("    /// ```text\n    ///     matchLabels:\n"));
+    assert!(code.contains(r"    /// Run them with \`kubectl exec or the [Pod\] console."));
+}
+
+#[test]
+fn missing_fields_take_schema_defaults() {
+    use schema_defaults::{DeployerResources, DeployerSpec, DeployerStrategy};
+    let spec: DeployerSpec = serde_json::from_value(serde_json::json!({ "name": "web" })).unwrap();
+    assert_eq!(spec.replicas, 1);
+    assert!(!spec.paused);
+    assert_eq!(spec.ratio, 1.0);
+    assert_eq!(spec.image, "nginx:\"latest\"");
+    assert!(matches!(spec.strategy, DeployerStrategy::RollingUpdate));
+    assert_eq!(spec.ports, [80, 443]);
+    assert_eq!(spec.labels["app"], "web");
+    assert_eq!(spec.resources.cpu.as_deref(), Some("100m"));
+    assert_eq!(spec.resources.memory, "64Mi");
+    // nullable fields keep an Option, as null is a value of its own
+    assert_eq!(spec.revision, None);
+    assert_eq!(spec.note, None);
+
+    let spec: DeployerSpec =
+        serde_json::from_value(serde_json::json!({ "name": "web", "replicas": 3 })).unwrap();
+    assert_eq!(spec.replicas, 3);
+
+    // structs of optional and defaulted fields implement Default with the schema defaults
+    let resources = DeployerResources::default();
+    assert_eq!(resources.cpu, None);
+    assert_eq!(resources.memory, "64Mi");
+    let code = include_str!("generated/defaults.rs");
+    assert!(code.contains("impl Default for DeployerResources {"));
+    // the spec has a required name
+    assert!(!code.contains("impl Default for DeployerSpec"));
+}
+
+#[test]
+fn validator_checks_schema_constraints() {
+    use validation::ThrottleSpec;
+    use validator::Validate;
+    let spec: ThrottleSpec = serde_json::from_value(serde_json::json!({
+        "name": "api",
+        "rate": 10,
+        "ratio": 0.5,
+        "overrides": [{ "path": "/healthz", "rate": 0 }],
+        "tiers": { "free": { "rate": 100 } },
+    }))
+    .unwrap();
+    assert!(spec.validate().is_ok());
+
+    let spec: ThrottleSpec = serde_json::from_value(serde_json::json!({
+        "name": "",
+        "rate": 0,
+        "burst": 0,
+        "ratio": 1.0,
+        "paths": [],
+        "overrides": [{ "path": "" }],
+        "tiers": { "free": { "rate": 101 } },
+        "window": { "seconds": 0 },
+    }))
+    .unwrap();
+    let errors = spec.validate().unwrap_err();
+    let mut fields = errors.errors().keys().copied().collect::<Vec<_>>();
+    fields.sort();
+    assert_eq!(fields, [
+        "burst",
+        "name",
+        "overrides",
+        "paths",
+        "rate",
+        "ratio",
+        "tiers",
+        "window"
+    ]);
+}
+
+#[test]
+fn garde_checks_schema_constraints() {
+    use garde::Validate;
+    use garde_validation::ThrottleSpec;
+    let spec: ThrottleSpec = serde_json::from_value(serde_json::json!({
+        "name": "api",
+        "message": "\"slow down\"",
+        "rate": 10,
+        "ratio": 0.5,
+        "overrides": [{ "path": "/healthz", "rate": 0 }],
+    }))
+    .unwrap();
+    assert!(spec.validate().is_ok());
+
+    let spec: ThrottleSpec = serde_json::from_value(serde_json::json!({
+        "name": "API",
+        "rate": 0,
+        "burst": 0,
+    }))
+    .unwrap();
+    let report = spec.validate().unwrap_err();
+    let mut fields = report
+        .iter()
+        .map(|(path, _)| path.to_string())
+        .collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+    assert_eq!(fields, ["burst", "name", "rate"]);
+}
+
+#[test]
+fn cel_stubs_return_the_rules() {
+    use cel::{Scaler, ScalerMetric, ScalerSpec};
+    // quotes survive as they are in the schema
+    assert_eq!(ScalerSpec::validations(), [
+        (
+            "self.minReplicas <= self.maxReplicas",
+            "minReplicas must not exceed maxReplicas"
+        ),
+        (
+            r#"!has(self.mode) || self.mode != "off" || self.minReplicas == 0"#,
+            r#"mode "off" requires minReplicas of 0"#
+        ),
+    ]);
+    assert_eq!(ScalerMetric::validations().len(), 1);
+    assert_eq!(Scaler::validations(), [(
+        "self.metadata.name.size() <= 63",
+        "name must fit in a label"
+    )]);
+    // rules of scalars and arrays are only documented on their fields
+    let code = include_str!("generated/cel.rs");
+    assert!(code.contains(r#"/// - `self.matches('^[a-z]([-a-z0-9]*[a-z0-9])?\\z')`"#));
+    assert!(code.contains("/// - Rule: thresholds must be positive"));
+}
+
+#[test]
+fn list_maps_round_trip() {
+    use list_maps::RouterSpec;
+    let wire = serde_json::json!({
+        "listeners": [
+            { "name": "https", "port": 443, "tls": { "secretName": "cert" } },
+            { "name": "http", "port": 80 },
+        ],
+        "env": [{ "name": "MODE", "value": "edge" }],
+        "ports": [{ "containerPort": 80, "protocol": "TCP" }],
+    });
+    let spec: RouterSpec = serde_json::from_value(wire.clone()).unwrap();
+    assert_eq!(spec.listeners.keys().collect::<Vec<_>>(), ["http", "https"]);
+    assert_eq!(spec.listeners["https"].port, 443);
+    assert_eq!(spec.env.as_ref().unwrap()["MODE"].value.as_deref(), Some("edge"));
+    // lists keyed by several fields stay lists
+    assert_eq!(spec.ports.as_ref().unwrap()[0].container_port, 80);
+
+    // keys are put back into the items, which are ordered by their key
+    let mut expected = wire;
+    expected["listeners"].as_array_mut().unwrap().reverse();
+    assert_eq!(serde_json::to_value(&spec).unwrap(), expected);
+    let spec: RouterSpec = serde_json::from_value(serde_json::to_value(&spec).unwrap()).unwrap();
+    assert_eq!(spec.listeners.len(), 2);
+
+    let missing: Result<RouterSpec, _> =
+        serde_json::from_value(serde_json::json!({ "listeners": [{ "port": 80 }] }));
+    assert!(missing.unwrap_err().to_string().contains("missing its key name"));
+    let duplicate: Result<RouterSpec, _> = serde_json::from_value(serde_json::json!({
+        "listeners": [{ "name": "http", "port": 80 }, { "name": "http", "port": 8080 }],
+    }));
+    assert!(duplicate.unwrap_err().to_string().contains("duplicate"));
+}
+
+#[test]
+fn kube_crd_keeps_the_original_attributes() {
+    use kube::CustomResourceExt;
+    use kube_attrs::{Workload, WorkloadSpec, WorkloadStatus};
+    let crd = parse_crd(include_str!("kube-crd.yaml"));
+    assert!(include_str!("generated/kube.rs").contains(r#"#[kube(status = "WorkloadStatus")]"#));
+
+    // the generated crd keeps the names, subresources and printer columns of the original
+    let original = crd.spec.versions[0].subresources.as_ref();
+    let generated = Workload::crd().spec.versions[0].subresources.clone();
+    assert_eq!(generated.as_ref(), original);
+    let original = &crd.spec.versions[0].additional_printer_columns;
+    let generated = &Workload::crd().spec.versions[0].additional_printer_columns;
+    assert_eq!(generated, original);
+    assert_eq!(generated.as_ref().unwrap()[1].priority, Some(1));
+    assert_eq!(Workload::crd().spec.names, crd.spec.names);
+
+    let mut workload = Workload::new("web", WorkloadSpec {
+        image: "nginx".into(),
+        replicas: Some(2),
+        selector: None,
+    });
+    workload.status = Some(WorkloadStatus {
+        phase: Some("Running".into()),
+        ..WorkloadStatus::default()
+    });
+    let wire = serde_json::to_value(&workload).unwrap();
+    assert_eq!(wire["status"]["phase"], "Running");
+    let workload: Workload = serde_json::from_value(wire).unwrap();
+    assert_eq!(workload.status.unwrap().phase.as_deref(), Some("Running"));
+}
+
+#[test]
+fn unset_options_are_left_out() {
+    use kube_attrs::{Workload, WorkloadSpec, WorkloadStatus};
+    let mut workload = Workload::new("web", WorkloadSpec {
+        image: "nginx".into(),
+        replicas: None,
+        selector: None,
+    });
+    assert_eq!(
+        serde_json::to_value(&workload.spec).unwrap(),
+        serde_json::json!({ "image": "nginx" })
+    );
+    // neither optional scalars nor optional structs are written as null
+    let wire = serde_json::to_value(&workload).unwrap();
+    assert!(wire.get("status").is_none());
+    workload.status = Some(WorkloadStatus::default());
+    let wire = serde_json::to_value(&workload).unwrap();
+    assert_eq!(wire["status"], serde_json::json!({}));
+}
+
+#[test]
+fn derived_schemas_are_structural() {
+    use derived_schemas::Collector;
+    use kube::CustomResourceExt;
+    fn assert_structural(schema: &serde_json::Value, path: &str) {
+        let preserved = schema.get("x-kubernetes-preserve-unknown-fields").is_some()
+            || schema.get("x-kubernetes-int-or-string").is_some();
+        assert!(
+            preserved || schema.get("type").is_some(),
+            "untyped schema at {}",
+            path
+        );
+        assert!(schema.get("uniqueItems").is_none(), "uniqueItems at {}", path);
+        for (key, property) in schema
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .into_iter()
+            .flatten()
+        {
+            assert_structural(property, &format!("{}.{}", path, key));
+        }
+        if let Some(items) = schema.get("items") {
+            assert_structural(items, &format!("{}[*]", path));
+        }
+        if let Some(values) = schema.get("additionalProperties").filter(|v| v.is_object()) {
+            assert_structural(values, &format!("{}.*", path));
+        }
+    }
+    let derived = Collector::crd().spec.versions[0]
+        .schema
+        .clone()
+        .unwrap()
+        .open_api_v3_schema;
+    let derived = serde_json::to_value(derived.unwrap()).unwrap();
+    assert_structural(&derived, "");
+
+    // members whose types derive a different schema keep the declared one
+    let crd = parse_crd(include_str!("schema-crd.yaml"));
+    let declared = crd.spec.versions[0].schema.clone().unwrap().open_api_v3_schema;
+    let declared = serde_json::to_value(declared.unwrap()).unwrap();
+    let spec = ["properties", "spec", "properties"];
+    let member =
+        |schema: &serde_json::Value, name: &str| spec.iter().fold(schema, |s, key| &s[key])[name].clone();
+    assert_eq!(member(&derived, "config"), member(&declared, "config"));
+    assert_eq!(member(&derived, "tags"), member(&declared, "tags"));
+    assert_eq!(
+        derived["properties"]["status"]["properties"]["observed"],
+        declared["properties"]["status"]["properties"]["observed"]
+    );
+}
+
+#[test]
+fn builders_leave_out_optional_members() {
+    use builders::{PipelineSpec, PipelineStage, PipelineTrigger};
+    // optional members can be left out, and strings set from a &str
+    let spec = PipelineSpec::builder()
+        .image("rust:1")
+        .stages(vec![
+            PipelineStage::builder().name("test").build(),
+            PipelineStage::builder()
+                .name("release")
+                .args(vec!["--locked".into()])
+                .retries(2)
+                .build(),
+        ])
+        .trigger(PipelineTrigger::builder().schedule("@daily").build())
+        .build();
+    assert_eq!(
+        serde_json::to_value(&spec).unwrap(),
+        serde_json::json!({
+            "image": "rust:1",
+            "stages": [
+                { "name": "test" },
+                { "name": "release", "args": ["--locked"], "retries": 2 },
+            ],
+            "trigger": { "schedule": "@daily" },
+        })
+    );
+}
+
+#[test]
+fn empty_collections_are_told_apart_from_missing_ones() {
+    use builders::PipelineStage;
+    for wire in [
+        serde_json::json!({ "name": "test" }),
+        serde_json::json!({ "name": "test", "args": [], "env": {} }),
+    ] {
+        let stage: PipelineStage = serde_json::from_value(wire.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&stage).unwrap(), wire);
+    }
+}
+
+#[test]
+fn strict_structs_deny_unknown_fields() {
+    use strict::GatewaySpec;
+    let spec: GatewaySpec = serde_json::from_value(serde_json::json!({
+        "listener": { "hostName": "example.com", "port": 443 },
+        "plugins": { "name": "auth", "issuer": "example.com" },
+    }))
+    .unwrap();
+    // unknown fields are kept where the schema preserves them
+    assert_eq!(spec.plugins.unwrap().extra["issuer"], "example.com");
+
+    let typo: Result<GatewaySpec, _> = serde_json::from_value(serde_json::json!({
+        "listener": { "hostname": "example.com" },
+    }));
+    assert!(typo.unwrap_err().to_string().contains("unknown field `hostname`"));
+    // which the structs generated without the flag drop
+    let lenient: kube_attrs::WorkloadSpec =
+        serde_json::from_value(serde_json::json!({ "image": "nginx", "imag": "typo" })).unwrap();
+    assert_eq!(lenient.image, "nginx");
+}
+
+#[test]
+fn crate_visibility_keeps_the_spec_pub() {
+    use visibility::{Tenant, TenantQuota, TenantSpec, TenantTier};
+    let code = include_str!("generated/visibility.rs");
+    // kube gives the generated type the visibility of the spec, which is kept pub along with the status
+    assert!(code.contains("pub struct TenantSpec {"));
+    assert!(code.contains("pub struct TenantStatus {"));
+    assert!(code.contains("pub(crate) struct TenantQuota {"));
+    assert!(code.contains("pub(crate) enum TenantTier {"));
+    // fields are never pub, only the prelude re-exports its imports
+    assert!(!code
+        .lines()
+        .any(|l| l.starts_with("    pub ") && !l.starts_with("    pub use ")));
+
+    let tenant = Tenant::new("team-a", TenantSpec {
+        quota: Some(TenantQuota {
+            cpu: Some("2".into()),
+            memory: None,
+        }),
+        tier: Some(TenantTier::Gold),
+    });
+    let json = serde_json::to_value(&tenant).unwrap();
+    assert_eq!(
+        json["spec"],
+        serde_json::json!({ "quota": { "cpu": "2" }, "tier": "gold" })
+    );
+}
@@ -0,0 +1,48 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --builders -f tests/builders-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use typed_builder::TypedBuilder;
+    pub use serde::{Serialize, Deserialize};
+    pub use std::collections::BTreeMap;
+}
+use self::prelude::*;
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, TypedBuilder)]
+#[kube(group = "clux.dev", version = "v1", kind = "Pipeline", plural = "pipelines")]
+#[kube(namespaced)]
+#[kube(schema = "disabled")]
+pub struct PipelineSpec {
+    #[builder(setter(into))]
+    pub image: String,
+    #[builder(default)]
+    pub stages: Vec<PipelineStage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    pub trigger: Option<PipelineTrigger>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, TypedBuilder)]
+pub struct PipelineStage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    pub args: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    pub env: Option<BTreeMap<String, String>>,
+    #[builder(setter(into))]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    pub retries: Option<i32>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, TypedBuilder)]
+pub struct PipelineTrigger {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    pub paused: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(into, strip_option))]
+    pub schedule: Option<String>,
+}
@@ -0,0 +1,88 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --docs --cel-stubs -f tests/cel-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+}
+use self::prelude::*;
+/// Replica bounds of the scaler
+///
+/// Validation rules:
+/// - `self.minReplicas <= self.maxReplicas`: minReplicas must not exceed maxReplicas
+/// - `!has(self.mode) || self.mode != "off" || self.minReplicas == 0`: mode "off" requires
+///   minReplicas of 0
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug)]
+#[kube(group = "clux.dev", version = "v1", kind = "Scaler", plural = "scalers")]
+#[kube(namespaced)]
+#[kube(
+    doc = "Properties validated by CEL rules\n\nValidation rules:\n- `self.metadata.name.size() <= 63`: name must fit in a label"
+)]
+#[kube(schema = "disabled")]
+#[serde(rename_all = "camelCase")]
+pub struct ScalerSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_replicas: Option<i32>,
+    /// Validation rules:
+    /// - Rule: thresholds must be positive
+    /// ```text
+    /// self.all(m,
+    ///   m.threshold > 0)
+    /// ```
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<Vec<ScalerMetric>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_replicas: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    /// Name of the scaled deployment
+    ///
+    /// Validation rules:
+    /// - `self == oldSelf`: target is immutable
+    /// - `self.matches('^[a-z]([-a-z0-9]*[a-z0-9])?\\z')`
+    pub target: String,
+}
+impl ScalerSpec {
+    /// CEL rules from `x-kubernetes-validations`, along with the messages of their violations
+    pub fn validations() -> &'static [(&'static str, &'static str)] {
+        &[
+            (
+                "self.minReplicas <= self.maxReplicas",
+                "minReplicas must not exceed maxReplicas",
+            ),
+            (
+                "!has(self.mode) || self.mode != \"off\" || self.minReplicas == 0",
+                "mode \"off\" requires minReplicas of 0",
+            ),
+        ]
+    }
+}
+/// Validation rules:
+/// - `self.name.startsWith("custom.") || self.name in ["cpu", "memory"]`: metrics are either cpu,
+///   memory or custom
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ScalerMetric {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<i64>,
+}
+impl ScalerMetric {
+    /// CEL rules from `x-kubernetes-validations`, along with the messages of their violations
+    pub fn validations() -> &'static [(&'static str, &'static str)] {
+        &[
+            (
+                "self.name.startsWith(\"custom.\") || self.name in [\"cpu\", \"memory\"]",
+                "metrics are either cpu, memory or custom",
+            ),
+        ]
+    }
+}
+impl Scaler {
+    /// CEL rules from `x-kubernetes-validations`, along with the messages of their violations
+    pub fn validations() -> &'static [(&'static str, &'static str)] {
+        &[("self.metadata.name.size() <= 63", "name must fit in a label")]
+    }
+}
@@ -0,0 +1,93 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --schema-defaults -f tests/defaults-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+    pub use std::collections::BTreeMap;
+}
+use self::prelude::*;
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub enum DeployerStrategy {
+    Recreate,
+    #[default]
+    RollingUpdate,
+}
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug)]
+#[kube(group = "clux.dev", version = "v1", kind = "Deployer", plural = "deployers")]
+#[kube(namespaced)]
+#[kube(schema = "disabled")]
+pub struct DeployerSpec {
+    #[serde(default = "defaults::deployer_spec_image")]
+    pub image: String,
+    #[serde(default = "defaults::deployer_spec_labels")]
+    pub labels: BTreeMap<String, String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default = "defaults::deployer_spec_paused")]
+    pub paused: bool,
+    #[serde(default = "defaults::deployer_spec_ports")]
+    pub ports: Vec<i64>,
+    #[serde(default = "defaults::deployer_spec_ratio")]
+    pub ratio: f64,
+    #[serde(default = "defaults::deployer_spec_replicas")]
+    pub replicas: i32,
+    #[serde(default = "defaults::deployer_spec_resources")]
+    pub resources: DeployerResources,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i64>,
+    #[serde(default = "defaults::deployer_spec_strategy")]
+    pub strategy: DeployerStrategy,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeployerResources {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<String>,
+    #[serde(default = "defaults::deployer_spec_resources_memory")]
+    pub memory: String,
+}
+impl Default for DeployerResources {
+    fn default() -> Self {
+        Self {
+            cpu: Default::default(),
+            memory: defaults::deployer_spec_resources_memory(),
+        }
+    }
+}
+mod defaults {
+    use super::*;
+    pub fn deployer_spec_image() -> String {
+        "nginx:\"latest\"".to_string()
+    }
+    pub fn deployer_spec_labels() -> BTreeMap<String, String> {
+        serde_json::from_value(serde_json::json!({ "app" : "web" }))
+            .expect("valid schema default")
+    }
+    pub fn deployer_spec_paused() -> bool {
+        false
+    }
+    pub fn deployer_spec_ports() -> Vec<i64> {
+        serde_json::from_value(serde_json::json!([80, 443]))
+            .expect("valid schema default")
+    }
+    pub fn deployer_spec_ratio() -> f64 {
+        1.0
+    }
+    pub fn deployer_spec_replicas() -> i32 {
+        1
+    }
+    pub fn deployer_spec_resources() -> DeployerResources {
+        serde_json::from_value(serde_json::json!({ "cpu" : "100m" }))
+            .expect("valid schema default")
+    }
+    pub fn deployer_spec_strategy() -> DeployerStrategy {
+        serde_json::from_value(serde_json::json!("RollingUpdate"))
+            .expect("valid schema default")
+    }
+    pub fn deployer_spec_resources_memory() -> String {
+        "64Mi".to_string()
+    }
+}
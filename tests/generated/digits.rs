@@ -0,0 +1,57 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q -f tests/digits-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+}
+use self::prelude::*;
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum MetricStatusClass {
+    #[serde(rename = "1xx")]
+    V1xx,
+    #[serde(rename = "2xx")]
+    V2xx,
+    #[serde(rename = "3xx")]
+    V3xx,
+    #[serde(rename = "4xx")]
+    V4xx,
+    #[serde(rename = "5xx")]
+    V5xx,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum MetricWindow {
+    #[serde(rename = "30s")]
+    V30s,
+    #[serde(rename = "5m")]
+    V5m,
+    #[serde(rename = "1h")]
+    V1h,
+}
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Default)]
+#[kube(group = "clux.dev", version = "v1", kind = "Metric", plural = "metrics")]
+#[kube(namespaced)]
+#[kube(schema = "disabled")]
+#[serde(rename_all = "camelCase")]
+pub struct MetricSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none", rename = "2xx")]
+    pub r#_2xx: Option<Metric2xx>,
+    #[serde(default, skip_serializing_if = "Option::is_none", rename = "503Responses")]
+    pub r#_503responses: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", rename = "5xxRate")]
+    pub r#_5xx_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_class: Option<MetricStatusClass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<MetricWindow>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Metric2xx {
+    #[serde(default, skip_serializing_if = "Option::is_none", rename = "99thPercentile")]
+    pub r#_99th_percentile: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_count: Option<i64>,
+}
@@ -0,0 +1,56 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --docs -f tests/docs-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+    pub use std::collections::BTreeMap;
+}
+use self::prelude::*;
+/// RunbookSpec holds the steps. Names must match [a-z0-9\]+ and may refer to \<namespace>/\<name>.
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Default)]
+#[kube(group = "clux.dev", version = "v1", kind = "Runbook", plural = "runbooks")]
+#[kube(namespaced)]
+#[kube(
+    doc = "Runbook describes how to recover a service, for example:\n\n```text\n    apiVersion: clux.dev/v1\n    kind: Runbook\n    spec:\n      steps: []\n```"
+)]
+#[kube(schema = "disabled")]
+pub struct RunbookSpec {
+    /// Selector of the pods to recover, typically
+    /// ```text
+    ///     matchLabels:
+    ///       app: web
+    /// ```
+    /// which targets the web pods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<RunbookSelector>,
+    /// Steps to run in order, such as
+    /// ```text
+    /// kubectl rollout restart deploy/web
+    /// ```
+    /// Run them with \`kubectl exec or the [Pod\] console.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<String>>,
+    /// Timeout in seconds, which ends the */ run.
+    /// See [the docs](https://example.com/docs) for details.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<i64>,
+}
+/// Selector of the pods to recover, typically
+/// ```text
+///     matchLabels:
+///       app: web
+/// ```
+/// which targets the web pods.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RunbookSelector {
+    /// Labels to match, as `key: value` pairs.
+    /// ```yaml
+    /// app: web
+    /// ```
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_labels: Option<BTreeMap<String, String>>,
+}
@@ -0,0 +1,147 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q -f tests/keywords-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+}
+use self::prelude::*;
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Default)]
+#[kube(group = "clux.dev", version = "v1", kind = "Keyword", plural = "keywords")]
+#[kube(namespaced)]
+#[kube(status = "KeywordStatus")]
+#[kube(schema = "disabled")]
+#[serde(rename_all = "camelCase")]
+pub struct KeywordSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none", rename = "Self")]
+    pub self_x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#abstract: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#as: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#async: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#await: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#box: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#break: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#const: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#continue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crate_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#do: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#dyn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#else: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#enum: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#extern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#final: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#fn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#for: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#gen: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#if: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#impl: Option<KeywordImpl>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#in: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#let: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#loop: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#macro: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#mod: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#move: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#mut: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#override: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#priv: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#pub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#ref: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#return: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#static: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#struct: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub super_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#trait: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#try: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#typeof: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub union: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#unsafe: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#unsized: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#use: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#virtual: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#where: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#while: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#yield: Option<String>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum KeywordImplMatch {
+    #[serde(rename = "self")]
+    KopiumSelf,
+    #[serde(rename = "Self")]
+    KopiumSelf2,
+    #[serde(rename = "type")]
+    Type,
+    #[serde(rename = "super")]
+    Super,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct KeywordImpl {
+    #[serde(default, skip_serializing_if = "Option::is_none", rename = "Type")]
+    pub r#type: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crate_: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#match: Option<KeywordImplMatch>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct KeywordStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#ref: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub super_: Option<String>,
+}
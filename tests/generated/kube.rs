@@ -0,0 +1,44 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q -f tests/kube-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+}
+use self::prelude::*;
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug)]
+#[kube(group = "clux.dev", version = "v1", kind = "Workload", plural = "workloads")]
+#[kube(namespaced)]
+#[kube(shortname = "wl")]
+#[kube(shortname = "wls")]
+#[kube(category = "all")]
+#[kube(category = "clux")]
+#[kube(status = "WorkloadStatus")]
+#[kube(
+    scale = r#"{"labelSelectorPath":".status.selector","specReplicasPath":".spec.replicas","statusReplicasPath":".status.replicas"}"#
+)]
+#[kube(
+    printcolumn = r#"{"description":"The phase of the workload","jsonPath":".status.phase","name":"Phase","type":"string"}"#
+)]
+#[kube(
+    printcolumn = r#"{"jsonPath":".spec.image","name":"Image","priority":1,"type":"string"}"#
+)]
+#[kube(schema = "disabled")]
+pub struct WorkloadSpec {
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct WorkloadStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+}
@@ -0,0 +1,174 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --list-map-as-map -f tests/listmap-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+    pub use std::collections::BTreeMap;
+}
+use self::prelude::*;
+#[allow(dead_code)]
+mod kopium_list_map {
+    //! Serde helpers for lists with `x-kubernetes-list-type: map`, represented as maps keyed by a field of their items
+    //!
+    //! Generated by kopium for such lists when using `--list-map-as-map`.
+    //! The key field is pulled out of each item when deserializing, and put back in when serializing.
+    //! Items are serialized in the order of their keys, which the apiserver accepts as the list is unordered.
+    use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+    /// Items of a list keyed by one of their fields, which the generated struct of the items leaves out
+    pub trait Keyed {
+        /// Name of the key field on the wire
+        const KEY: &'static str;
+    }
+    pub fn serialize<T, S>(map: &BTreeMap<String, T>, s: S) -> Result<S::Ok, S::Error>
+    where
+        T: Keyed + Serialize,
+        S: Serializer,
+    {
+        to_items(map).map_err(serde::ser::Error::custom)?.serialize(s)
+    }
+    pub fn deserialize<'de, T, D>(d: D) -> Result<BTreeMap<String, T>, D::Error>
+    where
+        T: Keyed + DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        from_items(Vec::<serde_json::Value>::deserialize(d)?)
+            .map_err(serde::de::Error::custom)
+    }
+    /// Helpers for optional lists
+    pub mod option {
+        use super::*;
+        pub fn serialize<T, S>(
+            map: &Option<BTreeMap<String, T>>,
+            s: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            T: Keyed + Serialize,
+            S: Serializer,
+        {
+            match map {
+                Some(map) => {
+                    s.serialize_some(
+                        &super::to_items(map).map_err(serde::ser::Error::custom)?,
+                    )
+                }
+                None => s.serialize_none(),
+            }
+        }
+        pub fn deserialize<'de, T, D>(
+            d: D,
+        ) -> Result<Option<BTreeMap<String, T>>, D::Error>
+        where
+            T: Keyed + DeserializeOwned,
+            D: Deserializer<'de>,
+        {
+            Option::<Vec<serde_json::Value>>::deserialize(d)?
+                .map(|items| super::from_items(items).map_err(serde::de::Error::custom))
+                .transpose()
+        }
+    }
+    /// The wire form of the items, with their keys put back in
+    fn to_items<T: Keyed + Serialize>(
+        map: &BTreeMap<String, T>,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let mut items = Vec::with_capacity(map.len());
+        for (key, item) in map {
+            let mut value = serde_json::to_value(item)
+                .map_err(|e| format!("list item {:?}: {}", key, e))?;
+            let Some(fields) = value.as_object_mut() else {
+                return Err(
+                    format!("list item {:?} does not serialize to an object", key),
+                );
+            };
+            fields.insert(T::KEY.to_string(), key.as_str().into());
+            items.push(value);
+        }
+        Ok(items)
+    }
+    /// Items keyed by their key field, which is removed from them
+    ///
+    /// Duplicate keys are rejected, as the apiserver does.
+    fn from_items<T: Keyed + DeserializeOwned>(
+        items: Vec<serde_json::Value>,
+    ) -> Result<BTreeMap<String, T>, String> {
+        let mut map = BTreeMap::new();
+        for mut value in items {
+            let key = match value
+                .as_object_mut()
+                .and_then(|fields| fields.remove(T::KEY))
+            {
+                Some(serde_json::Value::String(key)) => key,
+                Some(other) => {
+                    return Err(
+                        format!(
+                            "key {} of a list item is not a string: {}", T::KEY, other
+                        ),
+                    );
+                }
+                None => return Err(format!("list item is missing its key {}", T::KEY)),
+            };
+            let item = serde_json::from_value(value)
+                .map_err(|e| format!("list item {:?}: {}", key, e))?;
+            if map.insert(key.clone(), item).is_some() {
+                return Err(format!("duplicate list item with {} {:?}", T::KEY, key));
+            }
+        }
+        Ok(map)
+    }
+}
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Default)]
+#[kube(group = "clux.dev", version = "v1", kind = "Router", plural = "routers")]
+#[kube(namespaced)]
+#[kube(schema = "disabled")]
+pub struct RouterSpec {
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "kopium_list_map::option"
+    )]
+    pub env: Option<BTreeMap<String, RouterEnv>>,
+    #[serde(with = "kopium_list_map")]
+    pub listeners: BTreeMap<String, RouterListener>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ports: Option<Vec<RouterPort>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weights: Option<Vec<RouterWeight>>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RouterEnv {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+impl kopium_list_map::Keyed for RouterEnv {
+    const KEY: &'static str = "name";
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RouterListener {
+    pub port: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<RouterListenerTls>,
+}
+impl kopium_list_map::Keyed for RouterListener {
+    const KEY: &'static str = "name";
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RouterListenerTls {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_name: Option<String>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RouterPort {
+    pub container_port: i32,
+    pub protocol: String,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RouterWeight {
+    pub priority: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<i64>,
+}
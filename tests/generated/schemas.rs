@@ -0,0 +1,74 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --schema derived --sets -f tests/schema-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use schemars::JsonSchema;
+    pub use serde::{Serialize, Deserialize};
+    pub use std::collections::BTreeMap;
+    pub use std::collections::BTreeSet;
+    pub use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+}
+use self::prelude::*;
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+#[kube(group = "clux.dev", version = "v1", kind = "Collector", plural = "collectors")]
+#[kube(namespaced)]
+#[kube(status = "CollectorStatus")]
+pub struct CollectorSpec {
+    #[schemars(schema_with = "schemas::collector_spec_config")]
+    pub config: BTreeMap<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exporters: Option<Vec<CollectorExporter>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<IntOrString>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "schemas::collector_spec_tags")]
+    pub tags: Option<BTreeSet<String>>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct CollectorExporter {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "schemas::collector_spec_exporter_options")]
+    pub options: Option<serde_json::Value>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct CollectorStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "schemas::collector_status_observed")]
+    pub observed: Option<BTreeMap<String, serde_json::Value>>,
+}
+mod schemas {
+    pub fn collector_spec_config(
+        _: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        serde_json::from_str(
+                r#"{"description":"Configuration passed on as it is","type":"object","x-kubernetes-preserve-unknown-fields":true}"#,
+            )
+            .expect("valid schema")
+    }
+    pub fn collector_spec_tags(
+        _: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        serde_json::from_str(
+                r#"{"items":{"type":"string"},"type":"array","x-kubernetes-list-type":"set"}"#,
+            )
+            .expect("valid schema")
+    }
+    pub fn collector_spec_exporter_options(
+        _: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        serde_json::from_str(r#"{"x-kubernetes-preserve-unknown-fields":true}"#)
+            .expect("valid schema")
+    }
+    pub fn collector_status_observed(
+        _: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        serde_json::from_str(
+                r#"{"additionalProperties":{"x-kubernetes-preserve-unknown-fields":true},"type":"object"}"#,
+            )
+            .expect("valid schema")
+    }
+}
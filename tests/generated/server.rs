@@ -0,0 +1,66 @@
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+    pub use std::collections::BTreeMap;
+    pub use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+}
+use self::prelude::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub enum ServerProxyProtocol {
+    #[serde(rename = "unknown")]
+    #[default]
+    Unknown,
+    #[serde(rename = "HTTP/1")]
+    Http1,
+    #[serde(rename = "HTTP/2")]
+    Http2,
+    #[serde(rename = "gRPC")]
+    GRpc,
+    #[serde(rename = "opaque")]
+    Opaque,
+    #[serde(rename = "TLS")]
+    Tls,
+}
+
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug)]
+#[kube(group = "policy.linkerd.io", version = "v1beta1", kind = "Server", plural = "servers")]
+#[kube(namespaced)]
+#[kube(shortname = "srv")]
+#[kube(printcolumn = r#"{"description":"The port the server is listening on","jsonPath":".spec.port","name":"Port","type":"string"}"#)]
+#[kube(printcolumn = r#"{"description":"The protocol of the server","jsonPath":".spec.proxyProtocol","name":"Protocol","type":"string"}"#)]
+#[kube(schema = "disabled")]
+#[serde(rename_all = "camelCase")]
+pub struct ServerSpec {
+    pub pod_selector: ServerPodSelector,
+    pub port: IntOrString,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_protocol: Option<ServerProxyProtocol>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerPodSelector {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_expressions: Option<Vec<ServerPodSelectorMatchExpression>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_labels: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ServerPodSelectorMatchExpressionOperator {
+    In,
+    NotIn,
+    Exists,
+    DoesNotExist,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServerPodSelectorMatchExpression {
+    pub key: String,
+    pub operator: ServerPodSelectorMatchExpressionOperator,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+}
+
@@ -0,0 +1,77 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --docs --validation validator -f tests/validation-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use validator::Validate;
+    pub use serde::{Serialize, Deserialize};
+    pub use std::collections::BTreeMap;
+}
+use self::prelude::*;
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Validate)]
+#[kube(group = "clux.dev", version = "v1", kind = "Throttle", plural = "throttles")]
+#[kube(namespaced)]
+#[kube(doc = "Properties with validation constraints")]
+#[kube(schema = "disabled")]
+pub struct ThrottleSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(exclusive_min = 0))]
+    pub burst: Option<i64>,
+    /// Shown to throttled clients
+    ///
+    /// Pattern: `^"[^"\\]*"$`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Pattern: `^[a-z0-9]([-a-z0-9]*[a-z0-9])?$`
+    #[validate(length(min = 1, max = 63))]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub overrides: Option<Vec<ThrottleOverride>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(length(min = 1, max = 8))]
+    pub paths: Option<Vec<String>>,
+    #[validate(range(min = 1, max = 1000))]
+    pub rate: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 0.0, exclusive_max = 1.0))]
+    pub ratio: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub tiers: Option<BTreeMap<String, ThrottleTier>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub window: Option<ThrottleWindow>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct ThrottleOverride {
+    #[validate(length(min = 1))]
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 0))]
+    pub rate: Option<i32>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Validate)]
+pub struct ThrottleTier {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(max = 100))]
+    pub rate: Option<i32>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ThrottleWindowUnit {
+    #[serde(rename = "s")]
+    S,
+    #[serde(rename = "m")]
+    M,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Validate)]
+pub struct ThrottleWindow {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 1))]
+    pub seconds: Option<i64>,
+    /// Maximum length: 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<ThrottleWindowUnit>,
+}
@@ -0,0 +1,39 @@
+// WARNING: generated by kopium - manual changes will be overwritten
+// kopium command: kopium -q --visibility pub-crate -f tests/visibility-crd.yaml
+// kopium version: 0.20.0
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use kube::CustomResource;
+    pub use serde::{Serialize, Deserialize};
+}
+use self::prelude::*;
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) enum TenantTier {
+    #[serde(rename = "gold")]
+    Gold,
+    #[serde(rename = "silver")]
+    Silver,
+}
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, Default)]
+#[kube(group = "clux.dev", version = "v1", kind = "Tenant", plural = "tenants")]
+#[kube(status = "TenantStatus")]
+#[kube(schema = "disabled")]
+pub struct TenantSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) quota: Option<TenantQuota>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) tier: Option<TenantTier>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct TenantQuota {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cpu: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) memory: Option<String>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TenantStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) phase: Option<String>,
+}